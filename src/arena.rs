@@ -0,0 +1,233 @@
+/*!
+Module for allocating token payloads in an arena.
+
+Tokens can borrow the haystack, but a payload that is built from a match, like an unescaped string, has to be allocated. \
+An [`Arena`] gives such payloads the lifetime of the haystack, and they are all freed at once when it is dropped or reset,
+instead of one `String` per token. \
+It also interns strings, so identifiers can be [`Symbol`]s instead of a copy of the same `String` for each occurrence.
+
+# Example
+```
+# use plexer::lexer;
+# use plexer::arena::Arena;
+#
+lexer!(
+    STRING(&'a str) {
+        regex!(r#""[^"]*""#) => |v| {
+            let unquoted = v.as_str().trim_matches('"').replace("\\t", "\t");
+            v.arena().map(|arena| Token::STRING(arena.alloc_str(&unquoted)))
+        },
+    },
+);
+use lexer::Token;
+
+let mut arena = Arena::new();
+let tokens: Vec<_> = Token::tokenize_in(r#""a\tb""#, &arena).collect();
+assert_eq!(tokens, vec![Ok(Token::STRING("a\tb"))]);
+
+// Without an arena, the builder above rejects the match
+assert!(Token::tokenize(r#""a""#).next().is_some_and(|result| result.is_err()));
+
+drop(tokens);
+arena.reset();
+assert_eq!(arena.allocated(), 0);
+```
+
+Interned identifiers are resolved back to `&str` with the arena, which the `Lexer` also gives access to.
+```
+# use plexer::lexer;
+# use plexer::arena::Arena;
+#
+lexer!(
+    IDENTIFIER(plexer::arena::Symbol) {
+        regex!(r"[a-z]+") => |v| v.arena().map(|arena| Token::IDENTIFIER(arena.intern(v.as_str()))),
+    },
+    #[skip]
+    WHITESPACE {
+        ' ' => |_| Token::WHITESPACE,
+    },
+);
+use lexer::Token;
+
+let arena = Arena::new();
+let mut lex = Token::tokenize_in("foo bar foo", &arena);
+let tokens: Vec<_> = lex.by_ref().flatten().collect();
+
+assert_eq!(tokens[0], tokens[2]);
+assert_ne!(tokens[0], tokens[1]);
+if let Token::IDENTIFIER(symbol) = tokens[1] {
+    assert_eq!(lex.arena().and_then(|arena| arena.resolve(symbol)), Some("bar"));
+}
+```
+*/
+
+use alloc::collections::BTreeMap;
+use alloc::vec::Vec;
+use crate::sync::{lock, Mutex};
+
+// Size of the chunks the arena allocates, larger payloads get a chunk of their own
+const CHUNK_SIZE: usize = 4096;
+
+/**
+A bump allocator for the payloads of tokens.
+
+Payloads are copied into large chunks that are never reallocated, so they can be borrowed for as long as the arena is. \
+The arena is `Sync`, so a lexer using it can still be sent to another thread, and several threads can share it.
+
+# Example
+```
+# use plexer::arena::Arena;
+#
+let arena = Arena::new();
+let a = arena.alloc_str("one");
+let b = arena.alloc_bytes(b"two");
+
+assert_eq!((a, b), ("one", &b"two"[..]));
+assert_eq!(arena.allocated(), 6);
+```
+
+Lexers on several threads can intern their identifiers in the same arena.
+```
+# #[cfg(feature = "std")]
+# {
+# use plexer::arena::Arena;
+#
+let arena = Arena::new();
+let (a, b) = std::thread::scope(|scope| {
+    let a = scope.spawn(|| arena.intern("a"));
+    let b = scope.spawn(|| arena.intern("b"));
+    (a.join().unwrap(), b.join().unwrap())
+});
+
+assert_ne!(a, b);
+assert_eq!(arena.resolve(a), Some("a"));
+# }
+```
+*/
+#[derive(Debug, Default)]
+pub struct Arena {
+    // Interned strings point into the chunks, they are given out with the lifetime of the arena
+    symbols: Mutex<BTreeMap<&'static str, Symbol>>,
+    strings: Mutex<Vec<&'static str>>,
+    chunks: Mutex<Vec<Vec<u8>>>,
+}
+
+impl Arena {
+    /// Create an empty arena, it doesn't allocate until something is put in it.
+    pub const fn new() -> Self {
+        Self {
+            symbols: Mutex::new(BTreeMap::new()),
+            strings: Mutex::new(Vec::new()),
+            chunks: Mutex::new(Vec::new()),
+        }
+    }
+
+    /// Copy bytes into the arena.
+    pub fn alloc_bytes(&self, bytes: &[u8]) -> &[u8] {
+        let mut chunks = lock(&self.chunks);
+
+        let fits = chunks
+            .last()
+            .is_some_and(|chunk| chunk.capacity() - chunk.len() >= bytes.len());
+        if !fits {
+            chunks.push(Vec::with_capacity(core::cmp::max(CHUNK_SIZE, bytes.len())));
+        }
+
+        let chunk = chunks.last_mut().expect("a chunk was just pushed");
+        let start = chunk.len();
+        chunk.extend_from_slice(bytes);
+
+        // The chunk had enough capacity, so its buffer didn't move and earlier slices are still valid,
+        // and it is only freed by `reset` or `drop`, which need the arena to not be borrowed anymore
+        unsafe { core::slice::from_raw_parts(chunk.as_ptr().add(start), bytes.len()) }
+    }
+
+    /// Copy a `&str` into the arena.
+    pub fn alloc_str(&self, string: &str) -> &str {
+        // The bytes were copied from a `&str`
+        unsafe { core::str::from_utf8_unchecked(self.alloc_bytes(string.as_bytes())) }
+    }
+
+    /**
+    Intern a `&str`, the same string always gives the same [`Symbol`] and is only copied once into the arena.
+
+    # Panics
+    When more than `u32::MAX` strings are interned.
+
+    # Example
+    ```
+    # use plexer::arena::Arena;
+    #
+    let arena = Arena::new();
+    let a = arena.intern("name");
+
+    assert_eq!(arena.intern("name"), a);
+    assert_ne!(arena.intern("other"), a);
+    assert_eq!(arena.allocated(), 9);
+    ```
+    */
+    pub fn intern(&self, string: &str) -> Symbol {
+        // The symbols stay locked, so that another thread can't intern the same string in between
+        let mut symbols = lock(&self.symbols);
+        if let Some(&symbol) = symbols.get(string) {
+            return symbol;
+        }
+
+        let mut strings = lock(&self.strings);
+        let symbol = Symbol(u32::try_from(strings.len()).expect("too many interned strings"));
+        // The string lives in a chunk until `reset` or `drop`, and `reset` forgets the symbols first
+        let string: &'static str = unsafe { &*(self.alloc_str(string) as *const str) };
+        strings.push(string);
+        symbols.insert(string, symbol);
+        symbol
+    }
+
+    /**
+    Returns the string of a [`Symbol`], or `None` if it wasn't interned in this arena.
+
+    # Example
+    ```
+    # use plexer::arena::Arena;
+    #
+    let arena = Arena::new();
+    let symbol = arena.intern("name");
+
+    assert_eq!(arena.resolve(symbol), Some("name"));
+    assert_eq!(Arena::new().resolve(symbol), None);
+    ```
+    */
+    pub fn resolve(&self, symbol: Symbol) -> Option<&str> {
+        lock(&self.strings).get(symbol.0 as usize).copied()
+    }
+
+    /// Returns the number of bytes allocated in the arena.
+    pub fn allocated(&self) -> usize {
+        lock(&self.chunks).iter().map(Vec::len).sum()
+    }
+
+    /// Free everything allocated in the arena at once, keeping its last chunk to be reused.
+    ///
+    /// Symbols interned before are forgotten, and may resolve to other strings afterwards.
+    pub fn reset(&mut self) {
+        lock(&self.symbols).clear();
+        lock(&self.strings).clear();
+
+        let mut chunks = lock(&self.chunks);
+        if let Some(mut last) = chunks.pop() {
+            last.clear();
+            chunks.clear();
+            chunks.push(last);
+        }
+    }
+}
+
+/// An interned string, see [`Arena::intern`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Symbol(u32);
+
+impl Symbol {
+    /// Returns the index of the symbol, symbols are numbered from `0` in the order they were interned.
+    pub fn index(self) -> u32 {
+        self.0
+    }
+}
@@ -0,0 +1,97 @@
+/*!
+Tokenizes files with the rules of a grammar file, to check how real inputs lex.
+
+```text
+plexer <GRAMMAR> [FILE]...
+```
+
+Each token is printed on its own line with its location, span, name and lexeme,
+errors are rendered on stderr and the exit code is then `1`. \
+Without files, the standard input is tokenized.
+*/
+
+use plexer::runtime::LexerBuilder;
+use plexer::source::SourceMap;
+use plexer::span::Position;
+use std::io::Read;
+use std::process::ExitCode;
+
+const USAGE: &str = "usage: plexer <GRAMMAR> [FILE]...
+
+Tokenizes each FILE, or the standard input, with the rules of GRAMMAR.
+A grammar has a rule `NAME PRIORITY PATTERN` per line, and `skip NAME PRIORITY PATTERN` for skipped rules.";
+
+fn main() -> ExitCode {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    if args.is_empty() || args.iter().any(|arg| arg == "-h" || arg == "--help") {
+        eprintln!("{}", USAGE);
+        return ExitCode::from(2);
+    }
+
+    let lexer = match std::fs::read_to_string(&args[0]) {
+        Ok(grammar) => match LexerBuilder::from_grammar(&grammar) {
+            Ok(builder) => builder.build(),
+            Err(err) => {
+                eprintln!("error: {}: {}", args[0], err);
+                return ExitCode::from(2);
+            }
+        },
+        Err(err) => {
+            eprintln!("error: {}: {}", args[0], err);
+            return ExitCode::from(2);
+        }
+    };
+
+    let inputs: Vec<(String, std::io::Result<String>)> = if args.len() == 1 {
+        let mut text = String::new();
+        let read = std::io::stdin().read_to_string(&mut text).map(|_| text);
+        vec![(String::from("<stdin>"), read)]
+    } else {
+        args[1..].iter().map(|path| (path.clone(), std::fs::read_to_string(path))).collect()
+    };
+
+    let mut sources = SourceMap::new();
+    for (name, text) in inputs {
+        match text {
+            Ok(text) => sources.add(name, text),
+            Err(err) => {
+                eprintln!("error: {}: {}", name, err);
+                return ExitCode::from(2);
+            }
+        };
+    }
+
+    let mut failed = false;
+    for (file, source) in sources.files() {
+        let text = source.text();
+        let mut position = Position::default();
+        let mut cursor = 0;
+        for result in lexer.tokenize(text) {
+            match result {
+                Ok(token) => {
+                    position.advance(&text[cursor..token.span.start]);
+                    cursor = token.span.start;
+                    println!(
+                        "{}:{}:{}\t{}\t{}\t{:?}",
+                        source.name(),
+                        position.line,
+                        position.column,
+                        token.span,
+                        token.value,
+                        &text[token.span.as_range()]
+                    );
+                }
+                Err(err) => {
+                    failed = true;
+                    eprintln!("{}", err.render_in(&sources, file).unwrap_or_else(|| err.to_string()));
+                }
+            }
+        }
+    }
+
+    if failed {
+        ExitCode::FAILURE
+    } else {
+        ExitCode::SUCCESS
+    }
+}
@@ -0,0 +1,626 @@
+/*!
+Module for building tokens. \
+
+A token builder can return the token itself, or reject the match by returning an [`Option`] or a [`Result`].
+For more details, see the trait [`Outcome`]. \
+It can also return several tokens in a [`Vec`] or an array, which are gathered in a [`Built`],
+and [`embed`] splices the tokens of another lexer in a match.
+
+# Example
+```
+# use plexer::build::Outcome;
+#
+let accepted: Option<u8> = "12".parse().ok();
+let rejected: Result<u8, _> = "300".parse::<u8>();
+
+assert_eq!(accepted.outcome(), Ok(12));
+assert_eq!(rejected.outcome(), Err(Some(String::from("number too large to fit in target type"))));
+```
+
+# Grammar files
+Rules can also be written in a grammar file, which [`compile_grammar`] turns into a [`lexer!`](crate::lexer) invocation
+from a build script. Each line is a rule `NAME PRIORITY PATTERN`, where the pattern is a regex running to the end of the line,
+and `skip NAME PRIORITY PATTERN` declares a skipped rule. \
+Names are unique Rust identifiers that aren't keywords, since they become the variants of `Token`. \
+Rules with a higher priority come first, other tokens hold the matched `&str`. Empty lines and lines starting with `#` are ignored. \
+[`parse_grammar`] gives the rules themselves, and [`LexerBuilder::from_grammar`](crate::runtime::LexerBuilder::from_grammar)
+loads them at runtime, like the `plexer` binary of the `cli` feature does.
+```text
+# name      priority  pattern
+skip SPACE  0         \s+
+KEYWORD     2         let|fn
+IDENTIFIER  1         [a-z_][a-z0-9_]*
+```
+
+# Fallible builders
+A builder can also return an `Option<Token>` or a `Result<Token, E>` to reject a match, see [`Outcome`].
+The `Lexer` then uses the next best match, or returns an error with the reason of the rejection.
+```
+# use plexer::lexer;
+#
+lexer!(
+    BYTE(u8) {
+        regex!(r"[0-9]+") => |v| v.parse().map(Token::BYTE),
+    },
+    DIGIT(char) {
+        regex!(r"[0-9]") => |v| v.chars().next().map(Token::DIGIT),
+    },
+);
+
+let mut lex = lexer::Token::tokenize("255");
+assert_eq!(lex.next(), Some(Ok(lexer::Token::BYTE(255))));
+
+// Too big for a BYTE, but a DIGIT matches
+let mut lex = lexer::Token::tokenize("256");
+assert_eq!(lex.next(), Some(Ok(lexer::Token::DIGIT('2'))));
+
+lexer!(
+    mod bytes;
+
+    BYTE(u8) {
+        regex!(r"[0-9]+") => |v| v.parse().map(Token::BYTE),
+    },
+);
+
+let err = bytes::Token::tokenize("256").next().unwrap().unwrap_err();
+assert_eq!(err.to_string(), "invalid token '256' at line 1, column 1: number too large to fit in target type");
+```
+
+# Empty matches
+A pattern never matches the empty string, a regex like `a*` only gives its non-empty matches. When nothing else matches where a rule only matched the empty string, the error names the rule,
+see `LexerError::empty_rule`, and the `Lexer` goes on after the unexpected character.
+```
+# use plexer::lexer;
+#
+lexer!(
+    AS(&'a str) {
+        regex!("a*") => |v| Token::AS(v.as_str()),
+    },
+);
+use lexer::{Token, TokenKind};
+
+let mut lex = Token::tokenize("aab");
+assert_eq!(lex.next(), Some(Ok(Token::AS("aa"))));
+
+let err = lex.next().unwrap().unwrap_err();
+assert_eq!(err.empty_rule(), Some(TokenKind::AS));
+assert_eq!(err.to_string(), "rule AS matched the empty string at 'b' at line 1, column 3");
+assert_eq!(lex.next(), None);
+```
+
+# Multiple tokens
+A builder can return a `Vec<Token>` or an array of tokens to yield several tokens from one match, see [`Built`].
+They are yielded in order and share the span of the match, an empty `Vec` yields nothing.
+```
+# use plexer::lexer;
+#
+lexer!(
+    IDENTIFIER(&'a str) {
+        regex!(r"[a-z]+") => |v| Token::IDENTIFIER(v.as_str()),
+    },
+    ANGLE(char) {
+        ['<', '>'] => |v| Token::ANGLE(v.chars().next().unwrap()),
+        ">>" => |_| [Token::ANGLE('>'), Token::ANGLE('>')],
+    },
+);
+use lexer::Token;
+
+let tokens: Vec<_> = Token::tokenize("vec<vec<u>>").map(Result::unwrap).collect();
+assert_eq!(tokens[4..], [Token::IDENTIFIER("u"), Token::ANGLE('>'), Token::ANGLE('>')]);
+```
+
+A region of the input can be handed to another lexer, its tokens are then spliced in the stream
+with spans in the outer haystack by [`embed`].
+```
+# use plexer::lexer;
+# use plexer::span::{Span, Spanned};
+#
+lexer!(
+    mod script;
+
+    NUMBER(usize) {
+        regex!(r"[0-9]+") => |v| v.parse().map(Token::NUMBER),
+    },
+    #[skip]
+    WHITESPACE {
+        ' ' => |_| Token::WHITESPACE,
+    },
+);
+
+lexer!(
+    mod page;
+
+    TEXT(&'a str) {
+        regex!(r"[^<]+") => |v| Token::TEXT(v.as_str()),
+    },
+    SCRIPT(super::script::Token<'a>) {
+        regex!(r"<script>[^<]*</script>") => |v| {
+            let inner = plexer::pattern::Match::new(v.haystack, v.start + 8, v.end - 9);
+            let tokens = super::script::Token::tokenize(inner.as_str()).spanned();
+            plexer::build::embed(&inner, tokens, Token::SCRIPT)
+        },
+    },
+);
+# fn main() {
+use page::Token;
+
+let tokens: Vec<_> = Token::tokenize("a<script>1 23</script>").spanned().map(Result::unwrap).collect();
+assert_eq!(
+    tokens,
+    vec![
+        Spanned::new(Token::TEXT("a"), Span::new(0, 1)),
+        Spanned::new(Token::SCRIPT(script::Token::NUMBER(1)), Span::new(9, 10)),
+        Spanned::new(Token::SCRIPT(script::Token::NUMBER(23)), Span::new(11, 13)),
+    ]
+);
+# }
+```
+
+# State
+With `#![state(T)]`, builders take a `&mut T` after the match, to keep track of nesting or collect values while lexing. \
+`Token::tokenize_with` starts from a given state, `Token::tokenize` from `T::default()`,
+and `Lexer::state` and `Lexer::state_mut` give access to it. \
+Each builder is given its own copy of the state, and only the changes of the best match are kept,
+the state is also saved by `Lexer::checkpoint`, so `T` has to implement `Clone`, `Debug`, `PartialEq` and `Default`.
+```
+# use plexer::lexer;
+#
+lexer!(
+    #![state(usize)]
+
+    OPEN(usize) {
+        '(' => |_, depth| {
+            *depth += 1;
+            Token::OPEN(*depth)
+        },
+    },
+    CLOSE(usize) {
+        ')' => |_, depth| {
+            *depth -= 1;
+            Token::CLOSE(*depth + 1)
+        },
+    },
+    WORD(&'a str) {
+        regex!(r"[a-z]+") => |v, _| Token::WORD(v.as_str()),
+    },
+);
+use lexer::Token;
+
+let mut lex = Token::tokenize("(a(b)");
+let tokens: Vec<_> = lex.by_ref().flatten().collect();
+
+assert_eq!(tokens[2], Token::OPEN(2));
+assert_eq!(tokens[4], Token::CLOSE(2));
+assert_eq!(*lex.state(), 1);
+
+let mut lex = Token::tokenize_with(")", 1);
+assert_eq!(lex.next(), Some(Ok(Token::CLOSE(1))));
+assert_eq!(*lex.state(), 0);
+```
+*/
+
+use crate::pattern::Match;
+use crate::span::{Span, Spanned};
+use alloc::collections::BTreeMap;
+use alloc::string::{String, ToString};
+use alloc::{format, vec, vec::Vec};
+#[cfg(feature = "std")]
+use std::path::Path;
+
+/**
+The value returned by a token builder.
+
+The [`lexer!`](crate::lexer) macro implements it for the generated `Token`, which is always accepted,
+by default it is also implemented for the following types.
+
+| Outcome type       | Rejected when | Reason             |
+|--------------------|---------------|--------------------|
+| ```Option<T>```    | `None`        | none               |
+| ```Result<T, E>``` | `Err(e)`      | `e.to_string()`    |
+| ```Vec<T>```       | never         | none               |
+| ```[T; N]```       | never         | none               |
+*/
+#[diagnostic::on_unimplemented(
+    message = "`{Self}` can't be returned by a token builder",
+    label = "the builder of this rule returns `{Self}`",
+    note = "a builder returns a `Token`, or an `Option`, a `Result`, a `Vec` or an array of them"
+)]
+pub trait Outcome<T> {
+    /**
+    Returns the built value, or the reason why the match was rejected if there is one.
+
+    # Example
+    ```
+    # use plexer::build::Outcome;
+    #
+    assert_eq!(Some('a').outcome(), Ok('a'));
+    assert_eq!(None::<char>.outcome(), Err(None));
+    ```
+    */
+    fn outcome(self) -> Result<T, Option<String>>;
+}
+
+impl<T> Outcome<T> for Option<T> {
+    fn outcome(self) -> Result<T, Option<String>> {
+        self.ok_or(None)
+    }
+}
+
+impl<T, E> Outcome<T> for Result<T, E>
+where
+    E: core::fmt::Display,
+{
+    fn outcome(self) -> Result<T, Option<String>> {
+        self.map_err(|err| Some(err.to_string()))
+    }
+}
+
+impl<T> Outcome<Vec<T>> for Vec<T> {
+    fn outcome(self) -> Result<Vec<T>, Option<String>> {
+        Ok(self)
+    }
+}
+
+impl<T, const N: usize> Outcome<[T; N]> for [T; N] {
+    fn outcome(self) -> Result<[T; N], Option<String>> {
+        Ok(self)
+    }
+}
+
+// The token of a `keywords` rule, whose variant is a constructor taking the keyword or a value without it
+#[doc(hidden)]
+pub struct Keyword<V>(pub V);
+
+// Tried first, on `&&Keyword(Token::KIND)`, and skipped when the variant isn't a constructor
+#[doc(hidden)]
+pub trait KeywordField<'h, F, T> {
+    fn keyword(&self, mat: Match<'h>) -> T;
+}
+
+impl<'h, V, F, T> KeywordField<'h, F, T> for &Keyword<V>
+where
+    V: Fn(F) -> T,
+    F: From<&'h str>,
+{
+    fn keyword(&self, mat: Match<'h>) -> T {
+        (self.0)(F::from(mat.as_str()))
+    }
+}
+
+#[doc(hidden)]
+pub trait KeywordUnit<T> {
+    fn keyword<M>(&self, mat: M) -> T;
+}
+
+impl<T: Clone> KeywordUnit<T> for Keyword<T> {
+    fn keyword<M>(&self, _mat: M) -> T {
+        self.0.clone()
+    }
+}
+
+/**
+The tokens built from a single match, in order.
+
+Builders can return any type that converts into it, the tokens after the first one
+are queued by the `Lexer` and all share the span of the match, unless they come with their own span.
+
+# Example
+```
+# use plexer::build::Built;
+#
+let built: Built<char> = vec!['>', '>'].into();
+assert_eq!(built.into_iter().collect::<Vec<_>>(), vec!['>', '>']);
+assert_eq!(Built::from('>').into_iter().count(), 1);
+```
+*/
+#[derive(Debug, Clone, PartialEq)]
+pub enum Built<T> {
+    /// A single token, the usual case.
+    One(T),
+    /// Any number of tokens, none means the match is consumed without yielding anything.
+    Many(Vec<T>),
+    /// Any number of tokens with their span in the haystack, see [`embed`].
+    Spanned(Vec<Spanned<T>>),
+}
+
+impl<T> From<T> for Built<T> {
+    fn from(value: T) -> Self {
+        Built::One(value)
+    }
+}
+
+impl<T> From<Vec<T>> for Built<T> {
+    fn from(value: Vec<T>) -> Self {
+        Built::Many(value)
+    }
+}
+
+impl<T> From<Vec<Spanned<T>>> for Built<T> {
+    fn from(value: Vec<Spanned<T>>) -> Self {
+        Built::Spanned(value)
+    }
+}
+
+impl<T, const N: usize> From<[T; N]> for Built<T> {
+    fn from(value: [T; N]) -> Self {
+        Built::Many(Vec::from(value))
+    }
+}
+
+impl<T> Built<T> {
+    /**
+    Push the tokens into a queue, with the span of their match unless they have their own.
+
+    # Example
+    ```
+    # use plexer::build::Built;
+    # use plexer::span::Span;
+    #
+    let mut queue = Vec::new();
+    Built::from(['<', '<']).spread(Span::new(3, 5), &mut queue);
+
+    assert_eq!(queue, vec![('<', Span::new(3, 5)), ('<', Span::new(3, 5))]);
+    ```
+    */
+    pub fn spread(self, span: Span, queue: &mut impl Extend<(T, Span)>) {
+        match self {
+            Built::One(value) => queue.extend(Some((value, span))),
+            Built::Many(values) => queue.extend(values.into_iter().map(|value| (value, span))),
+            Built::Spanned(values) => queue.extend(values.into_iter().map(<(T, Span)>::from)),
+        }
+    }
+}
+
+impl<T> IntoIterator for Built<T> {
+    type Item = T;
+    type IntoIter = alloc::vec::IntoIter<T>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        match self {
+            Built::One(value) => vec![value].into_iter(),
+            Built::Many(values) => values.into_iter(),
+            Built::Spanned(values) => values.into_iter().map(|spanned| spanned.value).collect::<Vec<_>>().into_iter(),
+        }
+    }
+}
+
+/**
+Splices the tokens of another lexer run on a match, with their spans moved into the outer haystack.
+
+Each token is wrapped with `wrap`, and the first error rejects the whole match.
+This makes island grammars possible, like a lexer handing the content of `<script>` to another lexer.
+
+# Example
+```
+# use plexer::build::embed;
+# use plexer::pattern::Match;
+# use plexer::span::{Span, Spanned};
+#
+let mat = Match::new("say 1 2", 4, 7);
+let inner = mat.as_str().match_indices(char::is_numeric).map(|(i, v)| Ok::<_, String>(Spanned::new(v, Span::new(i, i + 1))));
+
+let tokens = embed(&mat, inner, |v| v.parse::<u8>().unwrap());
+assert_eq!(tokens, Ok(vec![Spanned::new(1, Span::new(4, 5)), Spanned::new(2, Span::new(6, 7))]));
+```
+*/
+pub fn embed<T, U, E>(
+    mat: &Match,
+    tokens: impl IntoIterator<Item = Result<Spanned<U>, E>>,
+    mut wrap: impl FnMut(U) -> T,
+) -> Result<Vec<Spanned<T>>, E> {
+    tokens
+        .into_iter()
+        .map(|result| {
+            result.map(|Spanned { value, span }| {
+                Spanned::new(wrap(value), Span::new(mat.start + span.start, mat.start + span.end))
+            })
+        })
+        .collect()
+}
+
+/// Error in a grammar file, returned by [`parse_grammar`] and [`generate_lexer`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct GrammarError {
+    /// Line of the error, starting at 1, or 0 when the error isn't on a line, like an invalid module name
+    pub line: usize,
+    /// What is wrong with the line
+    pub message: String,
+}
+
+impl core::fmt::Display for GrammarError {
+    fn fmt(&self, f: &mut core::fmt::Formatter) -> core::fmt::Result {
+        match self.line {
+            0 => f.write_str(&self.message),
+            line => write!(f, "line {}: {}", line, self.message),
+        }
+    }
+}
+
+impl core::error::Error for GrammarError {}
+
+/// A rule of a grammar file, returned by [`parse_grammar`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GrammarRule {
+    /// Name of the rule, which is also the name of its token
+    pub name: String,
+    /// Priority of the rule, higher comes first
+    pub priority: i64,
+    /// Whether the rule is skipped
+    pub skip: bool,
+    /// Regex of the rule
+    pub pattern: String,
+}
+
+/**
+Parses the rules of a grammar, sorted by priority, the first rule wins between rules with the same priority.
+
+Names are Rust identifiers, since they become the variants of `Token`, so Rust keywords and `skip` are rejected,
+and two rules can't have the same name.
+
+# Example
+```
+# use plexer::build::parse_grammar;
+#
+let rules = parse_grammar("# numbers\nskip SPACE 0 \\s+\nNUMBER 1 [0-9]+").unwrap();
+assert_eq!(rules[0].name, "NUMBER");
+assert!(rules[1].skip);
+
+let err = parse_grammar("NUMBER 1").unwrap_err();
+assert_eq!(err.to_string(), "line 1: missing pattern for 'NUMBER'");
+
+let err = parse_grammar("NUMBER 1 [0-9]+\nNUMBER 2 0x[0-9a-f]+").unwrap_err();
+assert_eq!(err.to_string(), "line 2: duplicate rule 'NUMBER', first defined on line 1");
+
+let err = parse_grammar("match 1 =>").unwrap_err();
+assert_eq!(err.to_string(), "line 1: invalid name 'match', it is a Rust keyword");
+
+let err = parse_grammar("skip 1 x").unwrap_err();
+assert_eq!(err.to_string(), "line 1: missing name after 'skip', which can't name a rule");
+```
+*/
+pub fn parse_grammar(grammar: &str) -> Result<Vec<GrammarRule>, GrammarError> {
+    let mut rules = Vec::new();
+    // Line of each name, to point at the first definition of a duplicate
+    let mut lines = BTreeMap::new();
+
+    for (index, line) in grammar.lines().enumerate() {
+        let error = |message: String| GrammarError { line: index + 1, message };
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+
+        let (skip, line) = match line.strip_prefix("skip ") {
+            Some(rest) => (true, rest.trim_start()),
+            None => (false, line),
+        };
+        let (name, rest) = line.split_once(char::is_whitespace).unwrap_or((line, ""));
+        let rest = rest.trim_start();
+        let (priority, pattern) = rest.split_once(char::is_whitespace).unwrap_or((rest, ""));
+        let pattern = pattern.trim();
+
+        if skip && name.parse::<i64>().is_ok() {
+            return Err(error(String::from("missing name after 'skip', which can't name a rule")));
+        }
+        if name == "skip" || is_keyword(name) {
+            return Err(error(format!("invalid name '{}', it is a {}", name, if name == "skip" { "grammar keyword" } else { "Rust keyword" })));
+        }
+        if !is_identifier(name) {
+            return Err(error(format!("invalid name '{}'", name)));
+        }
+        if let Some(first) = lines.insert(name, index + 1) {
+            return Err(error(format!("duplicate rule '{}', first defined on line {}", name, first)));
+        }
+        let priority: i64 = priority.parse().map_err(|_| error(format!("invalid priority '{}'", priority)))?;
+        if pattern.is_empty() {
+            return Err(error(format!("missing pattern for '{}'", name)));
+        }
+        if let Err(err) = regex::Regex::new(pattern) {
+            return Err(error(format!("invalid pattern for '{}': {}", name, err)));
+        }
+
+        rules.push(GrammarRule {
+            name: name.to_string(),
+            priority,
+            skip,
+            pattern: pattern.to_string(),
+        });
+    }
+
+    rules.sort_by_key(|rule| core::cmp::Reverse(rule.priority));
+    Ok(rules)
+}
+
+// ASCII identifier, like the names of rules and modules
+fn is_identifier(name: &str) -> bool {
+    let mut chars = name.chars();
+    name != "_"
+        && chars.next().is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+// Strict and reserved keywords of every edition, which can't be used as identifiers
+fn is_keyword(name: &str) -> bool {
+    const KEYWORDS: &[&str] = &[
+        "as", "async", "await", "break", "const", "continue", "crate", "dyn", "else", "enum", "extern", "false", "fn", "for", "if",
+        "impl", "in", "let", "loop", "match", "mod", "move", "mut", "pub", "ref", "return", "self", "Self", "static", "struct",
+        "super", "trait", "true", "type", "unsafe", "use", "where", "while", "abstract", "become", "box", "do", "final", "gen",
+        "macro", "override", "priv", "try", "typeof", "unsized", "virtual", "yield",
+    ];
+    KEYWORDS.contains(&name)
+}
+
+/**
+Generates the [`lexer!`](crate::lexer) invocation of a grammar, in a module named `module`.
+
+# Example
+```
+# use plexer::build::generate_lexer;
+#
+let code = generate_lexer("skip SPACE 0 \\s+\nNUMBER 1 [0-9]+", "numbers").unwrap();
+assert!(code.contains("pub mod numbers;"));
+assert!(code.contains(r#"NUMBER(&'a str) {"#));
+
+let err = generate_lexer("NUMBER high [0-9]+", "numbers").unwrap_err();
+assert_eq!(err.to_string(), "line 1: invalid priority 'high'");
+
+let err = generate_lexer("NUMBER 1 [0-9]+", "my-tokens").unwrap_err();
+assert_eq!(err.line, 0);
+assert_eq!(err.to_string(), "invalid module name 'my-tokens'");
+```
+*/
+pub fn generate_lexer(grammar: &str, module: &str) -> Result<String, GrammarError> {
+    if !is_identifier(module) || is_keyword(module) {
+        return Err(GrammarError { line: 0, message: format!("invalid module name '{}'", module) });
+    }
+
+    let mut code = format!("plexer::lexer!(\n    pub mod {};\n", module);
+    for GrammarRule { name, skip, pattern, .. } in parse_grammar(grammar)? {
+        code.push('\n');
+        if skip {
+            code += &format!("    #[skip]\n    {} {{\n        regex!({:?}) => |_| Token::{},\n    }},\n", name, pattern, name);
+        } else {
+            code += &format!(
+                "    {}(&'a str) {{\n        regex!({:?}) => |v| Token::{}(v.as_str()),\n    }},\n",
+                name, pattern, name
+            );
+        }
+    }
+    code.push_str(");\n");
+
+    Ok(code)
+}
+
+/**
+Reads a grammar file and writes its [`lexer!`](crate::lexer) invocation to `OUT_DIR/<module>.rs`, to be called from a build script.
+
+# Errors
+When the file can't be read or written, or when the grammar is invalid, see [`generate_lexer`].
+
+# Example
+```no_run
+// In the `main` of build.rs
+plexer::build::compile_grammar("src/tokens.lex", "tokens").unwrap();
+```
+Which is then included in the crate.
+```ignore
+include!(concat!(env!("OUT_DIR"), "/tokens.rs"));
+
+let tokens = tokens::Token::tokenize("let x");
+```
+*/
+#[cfg(feature = "std")]
+pub fn compile_grammar(path: impl AsRef<Path>, module: &str) -> std::io::Result<()> {
+    let path = path.as_ref();
+    let grammar = std::fs::read_to_string(path)?;
+    let code = generate_lexer(&grammar, module).map_err(|err| {
+        std::io::Error::new(std::io::ErrorKind::InvalidData, format!("{}: {}", path.display(), err))
+    })?;
+
+    let out_dir = std::env::var_os("OUT_DIR")
+        .ok_or_else(|| std::io::Error::new(std::io::ErrorKind::NotFound, "OUT_DIR is not set, call it from a build script"))?;
+    std::fs::write(Path::new(&out_dir).join(format!("{}.rs", module)), code)?;
+    println!("cargo:rerun-if-changed={}", path.display());
+
+    Ok(())
+}
@@ -0,0 +1,817 @@
+/*!
+Module for Pattern matching on bytes. \
+
+Formats like PDF, ELF sections or network protocols aren't valid UTF-8, so they can't be searched as a `&str`. \
+[`BytePattern`] mirrors [`Pattern`](crate::pattern::Pattern) for `&[u8]` haystacks, and returns a [`ByteMatch`]. \
+With `#![bytes]`, the [`lexer!`](crate::lexer) macro lexes a `&[u8]` with byte patterns, see its documentation.
+
+# Examples
+
+[`BytePattern`] is implemented for `u8`, ranges of `u8`, byte strings, `char` and `&str` (their UTF-8 bytes),
+slices of `char`, `&str` and byte strings, [`bytes::Regex`](https://docs.rs/regex/latest/regex/bytes/struct.Regex.html)
+and closures implementing `Fn(&[u8]) -> bool`. \
+Byte strings are always matched as a whole, a set of bytes is written as a range or a slice of `char` or byte strings.
+
+```
+# use plexer::bytes::BytePattern;
+#
+let hay = b"%PDF-1.7\n\xE2\xE3\xCF\xD3";
+
+// u8 pattern
+assert!(b'\n'.find_in(hay).is_some_and(|m| m.start == 8));
+// byte string pattern
+assert!(b"PDF".find_in(hay).is_some_and(|m| m.start == 1));
+// range of u8 pattern
+assert!((b'0'..=b'9').find_in(hay).is_some_and(|m| m.start == 5));
+// array of char pattern
+assert!(['.', '-'].find_in(hay).is_some_and(|m| m.start == 4));
+// closure pattern
+assert!((|s: &[u8]| s.iter().all(|b| *b >= 0x80)).find_in(hay).is_some_and(|m| m.len() == 4));
+```
+*/
+
+use crate::pattern::ByteSet;
+use crate::span::{Position, Span};
+use alloc::format;
+use alloc::string::{String, ToString};
+use alloc::vec;
+use alloc::vec::Vec;
+
+/// Returned by [`BytePattern`] on match.
+#[derive(Debug, Clone)]
+pub struct ByteMatch<'a> {
+    /// The bytes that were searched in
+    pub haystack: &'a [u8],
+    /// Start of the match
+    pub start: usize,
+    /// End of the match
+    pub end: usize,
+    arena: Option<&'a crate::arena::Arena>,
+}
+
+impl<'a> ByteMatch<'a> {
+    /**
+    Create a match from a haystack `&[u8]` and `start..end` range.
+
+    # Panics
+    When ```start >= end``` or ```haystack.len() < end```.
+
+    # Example
+    ```should_panic
+    # use plexer::bytes::ByteMatch;
+    #
+    let mat = ByteMatch::new(b"short", 0, 100000);
+    ```
+    */
+    pub fn new(haystack: &'a [u8], start: usize, end: usize) -> Self {
+        assert!(start < end);
+        assert!(haystack.len() >= end);
+        Self {
+            haystack,
+            start,
+            end,
+            arena: None,
+        }
+    }
+
+    /**
+    Returns the arena of the lexer that found the match, see [`arena`](crate::arena).
+
+    # Example
+    ```
+    # use plexer::bytes::ByteMatch;
+    #
+    assert!(ByteMatch::new(b"one", 0, 1).arena().is_none());
+    ```
+    */
+    pub fn arena(&self) -> Option<&'a crate::arena::Arena> {
+        self.arena
+    }
+
+    #[doc(hidden)]
+    pub fn in_arena(mut self, arena: Option<&'a crate::arena::Arena>) -> Self {
+        self.arena = arena;
+        self
+    }
+
+    /**
+    Returns the number of bytes in the match.
+
+    # Example
+    ```
+    # use plexer::bytes::ByteMatch;
+    #
+    assert_eq!(ByteMatch::new(b"three", 1, 4).len(), 3);
+    ```
+    */
+    pub fn len(&self) -> usize {
+        self.end - self.start
+    }
+
+    /**
+    Returns `true` if the match is empty, which can't happen for a match built with [`ByteMatch::new`].
+
+    # Example
+    ```
+    # use plexer::bytes::ByteMatch;
+    #
+    assert!(!ByteMatch::new(b"one", 0, 1).is_empty());
+    ```
+    */
+    pub fn is_empty(&self) -> bool {
+        self.start == self.end
+    }
+
+    /**
+    Convert to `&[u8]`.
+
+    # Example
+    ```
+    # use plexer::bytes::ByteMatch;
+    #
+    let mat = ByteMatch::new(b"\x00\x01here\xFF", 2, 6);
+
+    assert_eq!(mat.as_bytes(), b"here");
+    ```
+    */
+    pub fn as_bytes(&self) -> &'a [u8] {
+        &self.haystack[self.start..self.end]
+    }
+}
+
+// The arena only matters to the builders
+impl<'a> PartialEq for ByteMatch<'a> {
+    fn eq(&self, other: &Self) -> bool {
+        self.haystack == other.haystack && self.start == other.start && self.end == other.end
+    }
+}
+
+impl<'a> core::ops::Deref for ByteMatch<'a> {
+    type Target = [u8];
+
+    fn deref(&self) -> &[u8] {
+        self.as_bytes()
+    }
+}
+
+/**
+A bytes `Pattern` trait.
+
+The type implementing it can be used as a pattern for `&[u8]`,
+by default it is implemented for the following types.
+
+| Pattern type                | Match condition                        |
+|-----------------------------|----------------------------------------|
+| ```u8```                    | is contained in bytes                  |
+| ```RangeInclusive<u8>```    | any `u8` in the range match            |
+| ```&[u8]```, ```&[u8; N]``` | is subslice                            |
+| ```&[&[u8]]```              | any `&[u8]` match                      |
+| ```char```                  | its UTF-8 bytes are subslice           |
+| ```&str```                  | its UTF-8 bytes are subslice           |
+| ```&[char]```               | any `char` match                       |
+| ```&[&str]```               | any `&str` match                       |
+| ```F: Fn(&[u8]) -> bool```  | `F` returns `true` for subslice (slow) |
+| ```bytes::Regex```          | `Regex` match subslice                 |
+| ```ByteRegex```             | `Regex` match subslice (fast prefix)   |
+| ```pattern::Literals```     | any literal match (single pass)        |
+*/
+#[diagnostic::on_unimplemented(
+    message = "`{Self}` isn't a byte pattern",
+    label = "this pattern of a rule is a `{Self}`",
+    note = "a byte pattern is a `u8`, a `&[u8]`, a `ByteRegex` or one of the other types listed in the documentation of `BytePattern`"
+)]
+pub trait BytePattern<'a> {
+    /**
+    Find first occurence of the pattern in the given `&[u8]`.
+
+    # Examples
+    ```
+    # use plexer::bytes::{ByteMatch, BytePattern};
+    #
+    assert!(b"ab".find_in(b"cd").is_none());
+    assert_eq!(b"ab".find_in(b"cabd"), Some(ByteMatch::new(b"cabd", 1, 3)));
+    ```
+    */
+    fn find_in(&self, haystack: &'a [u8]) -> Option<ByteMatch<'a>>;
+
+    /**
+    Find last occurence of the pattern in the given `&[u8]`.
+
+    # Examples
+    ```
+    # use plexer::bytes::{ByteMatch, BytePattern};
+    #
+    assert!(b"ab".rev_find_in(b"cd").is_none());
+    assert_eq!(b"ab".rev_find_in(b"abab"), Some(ByteMatch::new(b"abab", 2, 4)));
+    ```
+    */
+    fn rev_find_in(&self, haystack: &'a [u8]) -> Option<ByteMatch<'a>> {
+        (0..haystack.len()).rev().find_map(|cursor| {
+            self.find_in(&haystack[cursor..])
+                .map(|mat| ByteMatch::new(haystack, cursor + mat.start, cursor + mat.end))
+        })
+    }
+
+    /**
+    Find one occurrence of the pattern in the given `&[u8]` that is prefix.
+
+    # Examples
+    ```
+    # use plexer::bytes::{ByteMatch, BytePattern};
+    #
+    assert!(b"ab".find_prefix_in(b"cdab").is_none());
+    assert_eq!(b"ab".find_prefix_in(b"abcd"), Some(ByteMatch::new(b"abcd", 0, 2)));
+    ```
+    */
+    fn find_prefix_in(&self, haystack: &'a [u8]) -> Option<ByteMatch<'a>> {
+        self.find_in(haystack).filter(|mat| mat.start == 0)
+    }
+
+    /**
+    Find the longest occurrence of the pattern in the given `&[u8]` that is suffix.
+
+    # Examples
+    ```
+    # use plexer::bytes::{ByteMatch, BytePattern};
+    #
+    assert!(b"ab".find_suffix_in(b"abcd").is_none());
+    assert_eq!(b"ab".find_suffix_in(b"cdab"), Some(ByteMatch::new(b"cdab", 2, 4)));
+    ```
+    */
+    fn find_suffix_in(&self, haystack: &'a [u8]) -> Option<ByteMatch<'a>> {
+        // The longest suffix is the one that starts first
+        (0..haystack.len())
+            .find(|&start| {
+                self.find_prefix_in(&haystack[start..])
+                    .is_some_and(|mat| mat.end == haystack.len() - start)
+            })
+            .map(|start| ByteMatch::new(haystack, start, haystack.len()))
+    }
+
+    /// Returns the source of the pattern if it is a regex, see [`Pattern::as_regex`](crate::pattern::Pattern::as_regex).
+    fn as_regex(&self) -> Option<&str> {
+        None
+    }
+
+    /// Returns the bytes a match can start with, see [`Pattern::first_bytes`](crate::pattern::Pattern::first_bytes).
+    fn first_bytes(&self) -> Option<ByteSet> {
+        None
+    }
+
+    /// Returns every sequence the pattern matches, see [`Pattern::literals`](crate::pattern::Pattern::literals).
+    fn literals(&self) -> Option<Vec<Vec<u8>>> {
+        None
+    }
+
+    /// Returns `true` if the pattern only matches the empty sequence at the start of the given `&[u8]`, see [`Pattern::matches_empty`](crate::pattern::Pattern::matches_empty).
+    fn matches_empty(&self, _haystack: &'a [u8]) -> bool {
+        false
+    }
+}
+
+impl<'a> BytePattern<'a> for u8 {
+    fn find_in(&self, haystack: &'a [u8]) -> Option<ByteMatch<'a>> {
+        memchr::memchr(*self, haystack).map(|i| ByteMatch::new(haystack, i, i + 1))
+    }
+
+    fn find_suffix_in(&self, haystack: &'a [u8]) -> Option<ByteMatch<'a>> {
+        (haystack.last() == Some(self))
+            .then(|| ByteMatch::new(haystack, haystack.len() - 1, haystack.len()))
+    }
+
+    fn first_bytes(&self) -> Option<ByteSet> {
+        Some(ByteSet::from_iter([*self]))
+    }
+
+    fn literals(&self) -> Option<Vec<Vec<u8>>> {
+        Some(vec![vec![*self]])
+    }
+}
+
+impl<'a> BytePattern<'a> for core::ops::RangeInclusive<u8> {
+    fn find_in(&self, haystack: &'a [u8]) -> Option<ByteMatch<'a>> {
+        haystack
+            .iter()
+            .position(|byte| self.contains(byte))
+            .map(|i| ByteMatch::new(haystack, i, i + 1))
+    }
+
+    fn find_suffix_in(&self, haystack: &'a [u8]) -> Option<ByteMatch<'a>> {
+        haystack
+            .last()
+            .filter(|byte| self.contains(byte))
+            .map(|_| ByteMatch::new(haystack, haystack.len() - 1, haystack.len()))
+    }
+
+    fn first_bytes(&self) -> Option<ByteSet> {
+        let mut set = ByteSet::new();
+        set.insert_range(self.clone());
+        Some(set)
+    }
+
+    fn literals(&self) -> Option<Vec<Vec<u8>>> {
+        Some(self.clone().map(|byte| vec![byte]).collect())
+    }
+}
+
+impl<'a> BytePattern<'a> for &[u8] {
+    fn find_in(&self, haystack: &'a [u8]) -> Option<ByteMatch<'a>> {
+        if self.is_empty() {
+            return None;
+        }
+        memchr::memmem::find(haystack, self).map(|i| ByteMatch::new(haystack, i, i + self.len()))
+    }
+
+    fn find_suffix_in(&self, haystack: &'a [u8]) -> Option<ByteMatch<'a>> {
+        (!self.is_empty() && haystack.ends_with(self))
+            .then(|| ByteMatch::new(haystack, haystack.len() - self.len(), haystack.len()))
+    }
+
+    fn first_bytes(&self) -> Option<ByteSet> {
+        Some(self.first().copied().into_iter().collect())
+    }
+
+    fn literals(&self) -> Option<Vec<Vec<u8>>> {
+        Some((!self.is_empty()).then(|| self.to_vec()).into_iter().collect())
+    }
+}
+
+impl<'a, const N: usize> BytePattern<'a> for &[u8; N] {
+    fn find_in(&self, haystack: &'a [u8]) -> Option<ByteMatch<'a>> {
+        self.as_slice().find_in(haystack)
+    }
+
+    fn find_suffix_in(&self, haystack: &'a [u8]) -> Option<ByteMatch<'a>> {
+        self.as_slice().find_suffix_in(haystack)
+    }
+
+    fn first_bytes(&self) -> Option<ByteSet> {
+        self.as_slice().first_bytes()
+    }
+
+    fn literals(&self) -> Option<Vec<Vec<u8>>> {
+        self.as_slice().literals()
+    }
+}
+
+impl<'a> BytePattern<'a> for char {
+    fn find_in(&self, haystack: &'a [u8]) -> Option<ByteMatch<'a>> {
+        self.encode_utf8(&mut [0; 4]).as_bytes().find_in(haystack)
+    }
+
+    fn find_suffix_in(&self, haystack: &'a [u8]) -> Option<ByteMatch<'a>> {
+        self.encode_utf8(&mut [0; 4]).as_bytes().find_suffix_in(haystack)
+    }
+
+    fn first_bytes(&self) -> Option<ByteSet> {
+        crate::pattern::Pattern::first_bytes(self)
+    }
+
+    fn literals(&self) -> Option<Vec<Vec<u8>>> {
+        Some(vec![self.encode_utf8(&mut [0; 4]).as_bytes().to_vec()])
+    }
+}
+
+impl<'a> BytePattern<'a> for [char] {
+    fn find_in(&self, haystack: &'a [u8]) -> Option<ByteMatch<'a>> {
+        self.iter()
+            .flat_map(|c| c.find_in(haystack))
+            .min_by_key(|mat| mat.start)
+    }
+
+    fn find_suffix_in(&self, haystack: &'a [u8]) -> Option<ByteMatch<'a>> {
+        self.iter().find_map(|c| c.find_suffix_in(haystack))
+    }
+
+    fn first_bytes(&self) -> Option<ByteSet> {
+        crate::pattern::Pattern::first_bytes(self)
+    }
+
+    fn literals(&self) -> Option<Vec<Vec<u8>>> {
+        Some(self.iter().map(|c| c.encode_utf8(&mut [0; 4]).as_bytes().to_vec()).collect())
+    }
+}
+
+impl<'a, const N: usize> BytePattern<'a> for [char; N] {
+    fn find_in(&self, haystack: &'a [u8]) -> Option<ByteMatch<'a>> {
+        self.as_slice().find_in(haystack)
+    }
+
+    fn find_suffix_in(&self, haystack: &'a [u8]) -> Option<ByteMatch<'a>> {
+        self.as_slice().find_suffix_in(haystack)
+    }
+
+    fn first_bytes(&self) -> Option<ByteSet> {
+        self.as_slice().first_bytes()
+    }
+
+    fn literals(&self) -> Option<Vec<Vec<u8>>> {
+        self.as_slice().literals()
+    }
+}
+
+impl<'a, const N: usize> BytePattern<'a> for &[char; N] {
+    fn find_in(&self, haystack: &'a [u8]) -> Option<ByteMatch<'a>> {
+        self.as_slice().find_in(haystack)
+    }
+
+    fn find_suffix_in(&self, haystack: &'a [u8]) -> Option<ByteMatch<'a>> {
+        self.as_slice().find_suffix_in(haystack)
+    }
+
+    fn first_bytes(&self) -> Option<ByteSet> {
+        self.as_slice().first_bytes()
+    }
+
+    fn literals(&self) -> Option<Vec<Vec<u8>>> {
+        self.as_slice().literals()
+    }
+}
+
+impl<'a> BytePattern<'a> for &str {
+    fn find_in(&self, haystack: &'a [u8]) -> Option<ByteMatch<'a>> {
+        self.as_bytes().find_in(haystack)
+    }
+
+    fn find_suffix_in(&self, haystack: &'a [u8]) -> Option<ByteMatch<'a>> {
+        self.as_bytes().find_suffix_in(haystack)
+    }
+
+    fn first_bytes(&self) -> Option<ByteSet> {
+        self.as_bytes().first_bytes()
+    }
+
+    fn literals(&self) -> Option<Vec<Vec<u8>>> {
+        self.as_bytes().literals()
+    }
+}
+
+impl<'a> BytePattern<'a> for String {
+    fn find_in(&self, haystack: &'a [u8]) -> Option<ByteMatch<'a>> {
+        self.as_bytes().find_in(haystack)
+    }
+
+    fn find_suffix_in(&self, haystack: &'a [u8]) -> Option<ByteMatch<'a>> {
+        self.as_bytes().find_suffix_in(haystack)
+    }
+
+    fn first_bytes(&self) -> Option<ByteSet> {
+        self.as_bytes().first_bytes()
+    }
+
+    fn literals(&self) -> Option<Vec<Vec<u8>>> {
+        self.as_bytes().literals()
+    }
+}
+
+impl<'a> BytePattern<'a> for [&str] {
+    fn find_in(&self, haystack: &'a [u8]) -> Option<ByteMatch<'a>> {
+        // Take the leftmost match, and the longest one if several start at the same place
+        self.iter()
+            .flat_map(|s| s.find_in(haystack))
+            .min_by_key(|mat| (mat.start, core::cmp::Reverse(mat.len())))
+    }
+
+    fn find_suffix_in(&self, haystack: &'a [u8]) -> Option<ByteMatch<'a>> {
+        self.iter()
+            .flat_map(|s| s.find_suffix_in(haystack))
+            .min_by_key(|mat| mat.start)
+    }
+
+    fn first_bytes(&self) -> Option<ByteSet> {
+        crate::pattern::Pattern::first_bytes(self)
+    }
+
+    fn literals(&self) -> Option<Vec<Vec<u8>>> {
+        Some(self.iter().filter(|s| !s.is_empty()).map(|s| s.as_bytes().to_vec()).collect())
+    }
+}
+
+impl<'a, const N: usize> BytePattern<'a> for [&str; N] {
+    fn find_in(&self, haystack: &'a [u8]) -> Option<ByteMatch<'a>> {
+        self.as_slice().find_in(haystack)
+    }
+
+    fn find_suffix_in(&self, haystack: &'a [u8]) -> Option<ByteMatch<'a>> {
+        self.as_slice().find_suffix_in(haystack)
+    }
+
+    fn first_bytes(&self) -> Option<ByteSet> {
+        self.as_slice().first_bytes()
+    }
+
+    fn literals(&self) -> Option<Vec<Vec<u8>>> {
+        self.as_slice().literals()
+    }
+}
+
+impl<'a, const N: usize> BytePattern<'a> for &[&str; N] {
+    fn find_in(&self, haystack: &'a [u8]) -> Option<ByteMatch<'a>> {
+        self.as_slice().find_in(haystack)
+    }
+
+    fn find_suffix_in(&self, haystack: &'a [u8]) -> Option<ByteMatch<'a>> {
+        self.as_slice().find_suffix_in(haystack)
+    }
+
+    fn first_bytes(&self) -> Option<ByteSet> {
+        self.as_slice().first_bytes()
+    }
+
+    fn literals(&self) -> Option<Vec<Vec<u8>>> {
+        self.as_slice().literals()
+    }
+}
+
+impl<'a> BytePattern<'a> for [&[u8]] {
+    fn find_in(&self, haystack: &'a [u8]) -> Option<ByteMatch<'a>> {
+        self.iter()
+            .flat_map(|s| s.find_in(haystack))
+            .min_by_key(|mat| (mat.start, core::cmp::Reverse(mat.len())))
+    }
+
+    fn find_suffix_in(&self, haystack: &'a [u8]) -> Option<ByteMatch<'a>> {
+        self.iter()
+            .flat_map(|s| s.find_suffix_in(haystack))
+            .min_by_key(|mat| mat.start)
+    }
+
+    fn first_bytes(&self) -> Option<ByteSet> {
+        Some(self.iter().filter_map(|s| s.first().copied()).collect())
+    }
+
+    fn literals(&self) -> Option<Vec<Vec<u8>>> {
+        Some(self.iter().filter(|s| !s.is_empty()).map(|s| s.to_vec()).collect())
+    }
+}
+
+impl<'a, const N: usize> BytePattern<'a> for [&[u8]; N] {
+    fn find_in(&self, haystack: &'a [u8]) -> Option<ByteMatch<'a>> {
+        self.as_slice().find_in(haystack)
+    }
+
+    fn find_suffix_in(&self, haystack: &'a [u8]) -> Option<ByteMatch<'a>> {
+        self.as_slice().find_suffix_in(haystack)
+    }
+
+    fn first_bytes(&self) -> Option<ByteSet> {
+        self.as_slice().first_bytes()
+    }
+
+    fn literals(&self) -> Option<Vec<Vec<u8>>> {
+        self.as_slice().literals()
+    }
+}
+
+impl<'a, const M: usize, const N: usize> BytePattern<'a> for [&[u8; M]; N] {
+    fn find_in(&self, haystack: &'a [u8]) -> Option<ByteMatch<'a>> {
+        self.iter()
+            .flat_map(|s| s.find_in(haystack))
+            .min_by_key(|mat| (mat.start, core::cmp::Reverse(mat.len())))
+    }
+
+    fn find_suffix_in(&self, haystack: &'a [u8]) -> Option<ByteMatch<'a>> {
+        self.iter()
+            .flat_map(|s| s.find_suffix_in(haystack))
+            .min_by_key(|mat| mat.start)
+    }
+
+    fn first_bytes(&self) -> Option<ByteSet> {
+        Some(self.iter().filter_map(|s| s.first().copied()).collect())
+    }
+
+    fn literals(&self) -> Option<Vec<Vec<u8>>> {
+        Some(self.iter().filter(|s| !s.is_empty()).map(|s| s.to_vec()).collect())
+    }
+}
+
+impl<'a: 'b, 'b, F> BytePattern<'a> for F
+where
+    F: Fn(&'b [u8]) -> bool,
+{
+    fn find_in(&self, haystack: &'a [u8]) -> Option<ByteMatch<'a>> {
+        // The goal is to check from left to right and to take the largest match
+        for start in 0..haystack.len() {
+            for end in (start + 1..=haystack.len()).rev() {
+                if (self)(&haystack[start..end]) {
+                    return Some(ByteMatch::new(haystack, start, end));
+                }
+            }
+        }
+
+        None
+    }
+
+    fn find_suffix_in(&self, haystack: &'a [u8]) -> Option<ByteMatch<'a>> {
+        (0..haystack.len())
+            .find(|&start| (self)(&haystack[start..]))
+            .map(|start| ByteMatch::new(haystack, start, haystack.len()))
+    }
+}
+
+impl<'a> BytePattern<'a> for regex::bytes::Regex {
+    fn find_in(&self, haystack: &'a [u8]) -> Option<ByteMatch<'a>> {
+        self.find_iter(haystack)
+            .find(|m| !m.is_empty())
+            .map(|m| ByteMatch::new(haystack, m.start(), m.end()))
+    }
+
+    fn matches_empty(&self, haystack: &'a [u8]) -> bool {
+        self.find(haystack).is_some_and(|m| m.range() == (0..0))
+    }
+}
+
+/**
+A [bytes::Regex](https://docs.rs/regex/latest/regex/bytes/struct.Regex.html) that is fast at finding prefixes,
+like [`pattern::Regex`](crate::pattern::Regex) but for `&[u8]`. \
+It is what the [`byte_regex!`](crate::byte_regex) macro builds.
+
+# Example
+```
+# use plexer::bytes::{BytePattern, ByteRegex};
+#
+let re = ByteRegex::new(r"(?-u)\xFF[0-9]+").unwrap();
+
+assert_eq!(re.find_in(b"abc \xFF123").map(|m| m.start), Some(4));
+assert!(re.find_prefix_in(b"abc \xFF123").is_none());
+assert_eq!(re.find_prefix_in(b"\xFF12 abc").map(|m| m.end), Some(3));
+```
+*/
+#[derive(Debug, Clone)]
+pub struct ByteRegex {
+    regex: regex::bytes::Regex,
+    anchored: regex::bytes::Regex,
+}
+
+impl ByteRegex {
+    /**
+    Compile a regex, see [`bytes::Regex::new`](https://docs.rs/regex/latest/regex/bytes/struct.Regex.html#method.new).
+
+    # Errors
+    When the pattern is not a valid regex.
+    ```
+    # use plexer::bytes::ByteRegex;
+    #
+    assert!(ByteRegex::new("(").is_err());
+    ```
+    */
+    pub fn new(pattern: &str) -> Result<Self, regex::Error> {
+        Ok(Self {
+            regex: regex::bytes::Regex::new(pattern)?,
+            anchored: regex::bytes::Regex::new(&format!("^(?:{})", pattern))?,
+        })
+    }
+}
+
+impl core::ops::Deref for ByteRegex {
+    type Target = regex::bytes::Regex;
+
+    fn deref(&self) -> &regex::bytes::Regex {
+        &self.regex
+    }
+}
+
+impl<'a> BytePattern<'a> for ByteRegex {
+    fn find_in(&self, haystack: &'a [u8]) -> Option<ByteMatch<'a>> {
+        self.regex.find_in(haystack)
+    }
+
+    fn find_prefix_in(&self, haystack: &'a [u8]) -> Option<ByteMatch<'a>> {
+        self.anchored.find_in(haystack)
+    }
+
+    fn matches_empty(&self, haystack: &'a [u8]) -> bool {
+        self.anchored.matches_empty(haystack)
+    }
+
+    fn as_regex(&self) -> Option<&str> {
+        Some(self.regex.as_str())
+    }
+
+    fn first_bytes(&self) -> Option<ByteSet> {
+        ByteSet::of_regex(self.regex.as_str(), false)
+    }
+
+    fn literals(&self) -> Option<Vec<Vec<u8>>> {
+        crate::pattern::literals_of_regex(self.regex.as_str(), false)
+    }
+}
+
+// What the generated lexers need from their haystack, a `&str` or a `&[u8]` with `#![bytes]`
+#[doc(hidden)]
+pub trait Haystack {
+    fn as_bytes(&self) -> &[u8];
+
+    fn is_boundary(&self, index: usize) -> bool;
+
+    fn unit_len(&self, index: usize) -> usize;
+
+    fn units(&self) -> usize;
+
+    fn advance(&self, position: &mut Position);
+
+    fn escape(&self) -> String;
+
+    fn render(&self, span: Span, message: &str) -> String;
+}
+
+impl Haystack for str {
+    fn as_bytes(&self) -> &[u8] {
+        str::as_bytes(self)
+    }
+
+    fn is_boundary(&self, index: usize) -> bool {
+        self.is_char_boundary(index)
+    }
+
+    fn unit_len(&self, index: usize) -> usize {
+        self[index..].chars().next().map_or(1, char::len_utf8)
+    }
+
+    fn units(&self) -> usize {
+        self.chars().count()
+    }
+
+    fn advance(&self, position: &mut Position) {
+        position.advance(self)
+    }
+
+    fn escape(&self) -> String {
+        self.to_string()
+    }
+
+    fn render(&self, span: Span, message: &str) -> String {
+        span.render(self, message)
+    }
+}
+
+impl Haystack for [u8] {
+    fn as_bytes(&self) -> &[u8] {
+        self
+    }
+
+    fn is_boundary(&self, _: usize) -> bool {
+        true
+    }
+
+    fn unit_len(&self, _: usize) -> usize {
+        1
+    }
+
+    fn units(&self) -> usize {
+        self.len()
+    }
+
+    fn advance(&self, position: &mut Position) {
+        for byte in self {
+            if *byte == b'\n' {
+                position.line += 1;
+                position.column = 1;
+            } else {
+                position.column += 1;
+            }
+        }
+    }
+
+    fn escape(&self) -> String {
+        self.iter().map(|byte| escape_byte(*byte)).collect()
+    }
+
+    fn render(&self, span: Span, message: &str) -> String {
+        let mut position = Position::default();
+        self[..span.start].advance(&mut position);
+        let line_start = self[..span.start].iter().rposition(|byte| *byte == b'\n').map_or(0, |i| i + 1);
+        let line_end = self[span.start..]
+            .iter()
+            .position(|byte| *byte == b'\n')
+            .map_or(self.len(), |i| span.start + i);
+        let line = self[line_start..line_end].strip_suffix(b"\r").unwrap_or(&self[line_start..line_end]);
+
+        // Escaped bytes are wider, so the indent and the carets follow their width
+        let indent: String = self[line_start..span.start]
+            .iter()
+            .map(|byte| if *byte == b'\t' { "\t".to_string() } else { " ".repeat(escape_byte(*byte).len()) })
+            .collect();
+        let carets = self[span.start..core::cmp::min(span.end, line_end)]
+            .iter()
+            .map(|byte| escape_byte(*byte).len())
+            .sum::<usize>()
+            .max(1);
+
+        let number = position.line.to_string();
+        let gutter = " ".repeat(number.len());
+        format!(
+            "error: {message}\n{gutter}--> {position}\n{gutter} |\n{number} | {}\n{gutter} | {indent}{}",
+            line.escape(),
+            "^".repeat(carets)
+        )
+    }
+}
+
+// Printable ASCII and tabs are kept, other bytes are written as `\xNN`
+fn escape_byte(byte: u8) -> String {
+    match byte {
+        b' '..=b'~' | b'\t' => char::from(byte).to_string(),
+        _ => format!("\\x{:02X}", byte),
+    }
+}
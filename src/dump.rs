@@ -0,0 +1,137 @@
+/*!
+Module for dumping token streams, to diff them between versions of a lexer or feed them to other tools. \
+
+`Lexer::to_json` and `Lexer::write_csv` turn each token or error into an [`Entry`],
+with the name of its kind, its lexeme and its span, and an error message instead of the kind for errors. \
+[`json`] and [`write_csv`] format any entries, like the ones of a [`runtime`](crate::runtime) lexer.
+
+# Example
+```
+# use plexer::lexer;
+#
+lexer!(
+    NUMBER(u8) {
+        regex!(r"[0-9]+") => |v| v.parse().map(Token::NUMBER),
+    },
+    #[skip]
+    WHITESPACE {
+        ' ' => |_| Token::WHITESPACE,
+    },
+);
+use lexer::Token;
+
+assert_eq!(
+    Token::tokenize("1 ? 300").to_json(),
+    r#"[
+  {"kind": "NUMBER", "lexeme": "1", "start": 0, "end": 1},
+  {"error": "unexpected character '?'", "lexeme": "?", "start": 2, "end": 3},
+  {"error": "invalid token '300': number too large to fit in target type", "lexeme": "300", "start": 4, "end": 7}
+]
+"#
+);
+
+# #[cfg(feature = "std")]
+# {
+let mut csv = Vec::new();
+Token::tokenize("1, 2").write_csv(&mut csv).unwrap();
+assert_eq!(
+    String::from_utf8(csv).unwrap(),
+    "kind,lexeme,start,end,error\nNUMBER,1,0,1,\n,\",\",1,2,\"unexpected character ','\"\nNUMBER,2,3,4,\n"
+);
+# }
+```
+*/
+
+use crate::span::Span;
+use alloc::format;
+use alloc::string::String;
+use alloc::vec::Vec;
+
+/// A token or an error of a token stream.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Entry {
+    /// Name of the kind of the token, `None` for an error
+    pub kind: Option<&'static str>,
+    /// Text of the token, with the bytes that aren't valid UTF-8 escaped
+    pub lexeme: String,
+    /// Span of the token in the haystack
+    pub span: Span,
+    /// Message of the error, `None` for a token
+    pub error: Option<String>,
+}
+
+/// Format the entries as a JSON array, with one object per line.
+pub fn json(entries: impl IntoIterator<Item = Entry>) -> String {
+    let objects: Vec<String> = entries
+        .into_iter()
+        .map(|entry| {
+            let head = match (entry.kind, &entry.error) {
+                (_, Some(error)) => format!("\"error\": {}", quote(error)),
+                (kind, None) => format!("\"kind\": {}", quote(kind.unwrap_or_default())),
+            };
+            format!(
+                "  {{{}, \"lexeme\": {}, \"start\": {}, \"end\": {}}}",
+                head,
+                quote(&entry.lexeme),
+                entry.span.start,
+                entry.span.end
+            )
+        })
+        .collect();
+    if objects.is_empty() {
+        String::from("[]\n")
+    } else {
+        format!("[\n{}\n]\n", objects.join(",\n"))
+    }
+}
+
+/**
+Write the entries as CSV, with a header and the columns `kind,lexeme,start,end,error`.
+
+# Errors
+When the writer fails.
+*/
+#[cfg(feature = "std")]
+pub fn write_csv(mut writer: impl std::io::Write, entries: impl IntoIterator<Item = Entry>) -> std::io::Result<()> {
+    writeln!(writer, "kind,lexeme,start,end,error")?;
+    for entry in entries {
+        writeln!(
+            writer,
+            "{},{},{},{},{}",
+            field(entry.kind.unwrap_or_default()),
+            field(&entry.lexeme),
+            entry.span.start,
+            entry.span.end,
+            field(entry.error.as_deref().unwrap_or_default())
+        )?;
+    }
+    Ok(())
+}
+
+// JSON string literal
+pub(crate) fn quote(text: &str) -> String {
+    let mut quoted = String::from("\"");
+    for c in text.chars() {
+        match c {
+            '"' => quoted.push_str("\\\""),
+            '\\' => quoted.push_str("\\\\"),
+            '\n' => quoted.push_str("\\n"),
+            '\r' => quoted.push_str("\\r"),
+            '\t' => quoted.push_str("\\t"),
+            c if (c as u32) < 0x20 => quoted.push_str(&format!("\\u{:04x}", c as u32)),
+            c => quoted.push(c),
+        }
+    }
+    quoted.push('"');
+    quoted
+}
+
+// CSV field, quoted when it holds a separator, a quote or a line break
+#[cfg(feature = "std")]
+fn field(text: &str) -> String {
+    if text.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", text.replace('"', "\"\""))
+    } else {
+        String::from(text)
+    }
+}
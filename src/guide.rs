@@ -0,0 +1,593 @@
+/*!
+Guide to the `Lexer` generated by [`lexer!`](crate::lexer), beyond iterating over its tokens. \
+The features of the other modules are documented there, like the spans of [`span`](crate::span) or the builders of [`build`](crate::build).
+
+# Kinds
+`TokenKind` has the same variants as `Token` but without fields, it is given by `Token::kind`.
+```
+# use plexer::lexer;
+#
+lexer!(
+    NUMBER(usize) {
+        regex!(r"[0-9]+") => |v| v.parse().map(Token::NUMBER),
+    },
+    OPERATOR(char) {
+        ['+', '-'] => |v| v.chars().next().map(Token::OPERATOR),
+    },
+);
+use lexer::{Token, TokenKind};
+
+assert_eq!(Token::NUMBER(42).kind(), TokenKind::NUMBER);
+assert_eq!(TokenKind::all(), &[TokenKind::NUMBER, TokenKind::OPERATOR]);
+assert_eq!(TokenKind::OPERATOR.name(), "OPERATOR");
+```
+
+# Batch
+`Token::tokenize_all` lexes the whole haystack at once, and returns either all the tokens or all the errors.
+```
+# use plexer::lexer;
+#
+lexer!(
+    NUMBER(usize) {
+        regex!(r"[0-9]+") => |v| v.parse().map(Token::NUMBER),
+    },
+    #[skip]
+    WHITESPACE {
+        ' ' => |_| Token::WHITESPACE,
+    },
+);
+use lexer::Token;
+
+assert_eq!(Token::tokenize_all("1 2"), Ok(vec![Token::NUMBER(1), Token::NUMBER(2)]));
+assert!(Token::tokenize_all("1 + 2 - 3").is_err_and(|errors| errors.len() == 2));
+```
+
+`Token::tokenize_into` does the same but appends the tokens to a given `Vec`, even when there are errors,
+so one buffer can be cleared and reused for many haystacks without allocating it again.
+```
+# use plexer::lexer;
+#
+lexer!(
+    NUMBER(usize) {
+        regex!(r"[0-9]+") => |v| v.parse().map(Token::NUMBER),
+    },
+    #[skip]
+    WHITESPACE {
+        ' ' => |_| Token::WHITESPACE,
+    },
+);
+use lexer::Token;
+
+let files = ["1 2", "3", "4 + 5"];
+let mut tokens = Vec::new();
+let mut lengths = Vec::new();
+
+for file in files {
+    tokens.clear();
+    let result = Token::tokenize_into(file, &mut tokens);
+    lengths.push((tokens.len(), result.is_ok()));
+}
+
+assert_eq!(lengths, vec![(2, true), (1, true), (2, false)]);
+assert!(tokens.capacity() >= 2);
+```
+
+`Token::tokenize_in` lexes with an [`Arena`](crate::arena::Arena), which builders reach with `Match::arena`,
+payloads allocated in it live as long as the haystack and are all freed at once. \
+The arena also interns identifiers as [`Symbol`](crate::arena::Symbol)s, which `Lexer::arena` resolves back to `&str`.
+
+# Peeking
+`Lexer::peek` and `Lexer::peek_n` look at the next tokens without consuming them,
+peeked tokens are kept so that they are only lexed once.
+```
+# use plexer::lexer;
+#
+lexer!(
+    NUMBER(usize) {
+        regex!(r"[0-9]+") => |v| v.parse().map(Token::NUMBER),
+    },
+    OPERATOR(char) {
+        ['+', '-'] => |v| v.chars().next().map(Token::OPERATOR),
+    },
+);
+use lexer::Token;
+
+let mut lex = Token::tokenize("1+2");
+assert_eq!(lex.peek(), Some(Ok(&Token::NUMBER(1))));
+assert_eq!(lex.peek_n(1), Some(Ok(&Token::OPERATOR('+'))));
+assert_eq!(lex.peek_n(3), None);
+
+assert_eq!(lex.next(), Some(Ok(Token::NUMBER(1))));
+assert_eq!(lex.peek(), Some(Ok(&Token::OPERATOR('+'))));
+```
+
+# Backtracking
+`Lexer::checkpoint` saves the state of the `Lexer`, which can be restored later with `Lexer::rewind`.
+```
+# use plexer::lexer;
+#
+lexer!(
+    NUMBER(usize) {
+        regex!(r"[0-9]+") => |v| v.parse().map(Token::NUMBER),
+    },
+    #[skip]
+    WHITESPACE {
+        ' ' => |_| Token::WHITESPACE,
+    },
+);
+use lexer::Token;
+
+let mut lex = Token::tokenize("1 2 3");
+lex.next();
+
+let state = lex.checkpoint();
+assert_eq!(lex.nth(1), Some(Ok(Token::NUMBER(3))));
+
+lex.rewind(state);
+assert_eq!(lex.next(), Some(Ok(Token::NUMBER(2))));
+```
+
+The lexers also implement `Clone`, a clone goes on from the same place without affecting the original. \
+They implement `FusedIterator` too, and their `size_hint` is exact once the whole haystack is consumed.
+```
+# use plexer::lexer;
+#
+# lexer!(
+#     NUMBER(usize) {
+#         regex!(r"[0-9]+") => |v| v.parse().map(Token::NUMBER),
+#     },
+#     #[skip]
+#     WHITESPACE {
+#         ' ' => |_| Token::WHITESPACE,
+#     },
+# );
+# use lexer::Token;
+#
+let mut lex = Token::tokenize("1 2");
+assert_eq!(lex.clone().count(), 2);
+
+lex.nth(1);
+assert_eq!(lex.size_hint(), (0, Some(0)));
+```
+
+# Seeking
+`Lexer::cursor` gives the byte offset of the `Lexer` and `Lexer::remaining` the part of the haystack it hasn't consumed. \
+`Lexer::seek` moves it to another offset, so a parser can consume raw text itself, like the body of a heredoc, and then go on lexing.
+Tokens that were peeked or not yielded yet are dropped, but the mode is kept.
+```
+# use plexer::lexer;
+# use plexer::span::Position;
+#
+lexer!(
+    HEREDOC {
+        "<<" => |_| Token::HEREDOC,
+    },
+    WORD(&'a str) {
+        regex!(r"[a-z]+") => |v| Token::WORD(v.as_str()),
+    },
+    #[skip]
+    WHITESPACE {
+        [' ', '\n'] => |_| Token::WHITESPACE,
+    },
+);
+use lexer::Token;
+
+let mut lex = Token::tokenize("cat <<\nraw $text!\nEND\ndone");
+assert_eq!(lex.nth(1), Some(Ok(Token::HEREDOC)));
+
+let body = lex.remaining().split_once("END").map(|(body, _)| body);
+assert_eq!(body, Some("\nraw $text!\n"));
+
+lex.seek(lex.cursor() + body.map_or(0, str::len) + "END".len());
+assert_eq!(lex.position(), Position { line: 3, column: 4 });
+assert_eq!(lex.next(), Some(Ok(Token::WORD("done"))));
+```
+
+`Token::tokenize_range` only lexes a range of the haystack, but its spans and positions are still those of the whole haystack,
+which suits snippets embedded in a larger document.
+```
+# use plexer::lexer;
+# use plexer::span::{Position, Span, Spanned};
+#
+# lexer!(
+#     WORD(&'a str) {
+#         regex!(r"[a-z]+") => |v| Token::WORD(v.as_str()),
+#     },
+#     #[skip]
+#     WHITESPACE {
+#         [' ', '\n'] => |_| Token::WHITESPACE,
+#     },
+# );
+# use lexer::Token;
+#
+let document = "<p>\n  {{ one two }}\n</p>";
+let mut lex = Token::tokenize_range(document, 9..16);
+
+assert_eq!(lex.position(), Position::new(2, 6));
+let words: Vec<_> = lex.spanned().flatten().collect();
+assert_eq!(words, vec![
+    Spanned::new(Token::WORD("one"), Span::new(9, 12)),
+    Spanned::new(Token::WORD("two"), Span::new(13, 16)),
+]);
+```
+
+# Reverse
+`Token::tokenize_rev` returns a `RevLexer`, which lexes backwards from the end of the haystack with [`Pattern::find_suffix_in`](crate::pattern::Pattern::find_suffix_in)
+and yields spanned tokens, the last one first. \
+Modes, guards and actions depend on what comes before a token, so only the rules of the initial mode are used, guards are ignored,
+and there are no `INDENT`, `DEDENT` or `EOF` tokens.
+```
+# use plexer::lexer;
+# use plexer::span::{Span, Spanned};
+#
+lexer!(
+    WORD(&'a str) {
+        regex!(r"[a-z]+") => |v| Token::WORD(v.as_str()),
+    },
+    #[skip]
+    WHITESPACE {
+        ' ' => |_| Token::WHITESPACE,
+    },
+);
+use lexer::Token;
+
+let haystack = "one two three";
+let before = |cursor: usize| Token::tokenize_rev(&haystack[..cursor]).next();
+
+assert_eq!(before(7), Some(Ok(Spanned::new(Token::WORD("two"), Span::new(4, 7)))));
+assert_eq!(before(6).map(|result| result.map(|token| token.value)), Some(Ok(Token::WORD("tw"))));
+```
+
+# Owned inputs
+Tokens of a lexer with `#![owned]` don't borrow the input, so they can be lexed from a [`BufRead`](std::io::BufRead) with `Token::tokenize_reader`,
+which only keeps the input needed to match the next token, up to the maximum length, in memory.
+Errors are then returned as [`io::Error`](std::io::Error), including the errors of the `Lexer`.
+```
+# #[cfg(feature = "std")]
+# {
+# use plexer::lexer;
+#
+lexer!(
+    #![owned]
+    #![max_length(16)]
+
+    WORD(String) {
+        regex!(r"[a-z]+") => |v| Token::WORD(v.to_string()),
+    },
+    #[skip]
+    WHITESPACE {
+        [' ', '\n'] => |_| Token::WHITESPACE,
+    },
+);
+
+let log = "first line\nsecond line\n".repeat(1000);
+let words = lexer::Token::tokenize_reader(log.as_bytes()).map(Result::unwrap).count();
+assert_eq!(words, 4000);
+
+let err = lexer::Token::tokenize_reader("ok\n?".as_bytes()).nth(1).unwrap().unwrap_err();
+assert_eq!(err.to_string(), "unexpected character '?' at line 2, column 1");
+# }
+```
+
+With the `tokio` feature, `Token::tokenize_async` does the same from a tokio [`AsyncBufRead`](https://docs.rs/tokio/latest/tokio/io/trait.AsyncBufRead.html),
+as a [`Stream`](https://docs.rs/futures-core/latest/futures_core/stream/trait.Stream.html) of tokens.
+Any `AsyncRead` can be wrapped in a `tokio::io::BufReader` first.
+```
+# #[cfg(feature = "tokio")]
+# {
+# use plexer::lexer;
+#
+lexer!(
+    #![owned]
+
+    WORD(String) {
+        regex!(r"[a-z]+") => |v| Token::WORD(v.to_string()),
+    },
+    #[skip]
+    WHITESPACE {
+        [' ', '\n'] => |_| Token::WHITESPACE,
+    },
+);
+use futures_core::Stream;
+use std::pin::Pin;
+
+let runtime = tokio::runtime::Builder::new_current_thread().build().unwrap();
+let words = runtime.block_on(async {
+    let mut lexer = lexer::Token::tokenize_async("first line\nsecond line\n".as_bytes());
+    let mut words = Vec::new();
+    while let Some(token) = std::future::poll_fn(|cx| Pin::new(&mut lexer).poll_next(cx)).await {
+        words.push(token.unwrap());
+    }
+    words
+});
+assert_eq!(words[3], lexer::Token::WORD("line".to_string()));
+# }
+```
+
+Any `Iterator<Item = char>` can be lexed the same way with `Token::tokenize_chars`, errors are then returned as their message.
+```
+# use plexer::lexer;
+#
+lexer!(
+    #![owned]
+
+    DIGIT(u32) {
+        regex!(r"[0-9]") => |v| v.parse().map(Token::DIGIT),
+    },
+);
+
+let digits = (0..5).filter_map(|i| char::from_digit(i * 2, 10));
+let tokens: Vec<_> = lexer::Token::tokenize_chars(digits).map(Result::unwrap).collect();
+assert_eq!(tokens.last(), Some(&lexer::Token::DIGIT(8)));
+
+let err = lexer::Token::tokenize_chars("1a".chars()).nth(1).unwrap().unwrap_err();
+assert_eq!(err, "unexpected character 'a' at line 1, column 2");
+```
+
+Chunked text, like the chunks of a rope in an editor, can be lexed with `Token::tokenize_chunks` without joining it first.
+Tokens can span several chunks.
+```
+# use plexer::lexer;
+#
+lexer!(
+    #![owned]
+
+    WORD(String) {
+        regex!(r"[a-z]+") => |v| Token::WORD(v.to_string()),
+    },
+    #[skip]
+    WHITESPACE {
+        ' ' => |_| Token::WHITESPACE,
+    },
+);
+
+let chunks = ["hel", "lo wo", "rld"];
+let tokens: Vec<_> = lexer::Token::tokenize_chunks(chunks.iter()).map(Result::unwrap).collect();
+assert_eq!(tokens, vec![lexer::Token::WORD(String::from("hello")), lexer::Token::WORD(String::from("world"))]);
+```
+
+A `TokenBuffer` keeps the tokens of a text along with the state of the `Lexer` before each of them,
+so that `TokenBuffer::edit` only lexes again the tokens around an edit, and returns the range of tokens it lexed again. \
+It is only generated with `#![owned]`, since the tokens it keeps can't borrow a text that changes with each edit.
+It is given the edited text, the byte range that was replaced and the length of the inserted text.
+Since patterns can look up to the maximum length ahead, lexing starts again that far before the edit.
+```
+# use plexer::lexer;
+#
+lexer!(
+    #![owned]
+    #![max_length(16)]
+
+    WORD(String) {
+        regex!(r"[a-z]+") => |v| Token::WORD(v.to_string()),
+    },
+    NUMBER(u32) {
+        regex!(r"[0-9]+") => |v| v.parse().map(Token::NUMBER),
+    },
+    EQUAL {
+        '=' => |_| Token::EQUAL,
+    },
+    #[skip]
+    WHITESPACE {
+        [' ', '\n'] => |_| Token::WHITESPACE,
+    },
+);
+use lexer::TokenBuffer;
+
+let mut text = "x = 1\n".repeat(100);
+let mut buffer = TokenBuffer::new(&text);
+assert_eq!(buffer.len(), 300);
+
+// Replace the "x" of the 50th line with "abc"
+text.replace_range(294..295, "abc");
+let damaged = buffer.edit(&text, 294..295, 3);
+assert_eq!(damaged, 139..149);
+assert!(buffer.tokens().eq(TokenBuffer::new(&text).tokens()));
+```
+
+# Errors
+`LexerError` implements [`Error`](std::error::Error), so it can be propagated with `?`.
+```
+# use plexer::lexer;
+#
+# lexer!(
+#     NUMBER(usize) {
+#         regex!(r"[0-9]+") => |v| v.parse().map(Token::NUMBER),
+#     },
+#     WHITESPACE {
+#         ' ' => |_| Token::WHITESPACE,
+#     },
+# );
+#
+fn sum(input: &str) -> Result<usize, Box<dyn std::error::Error + '_>> {
+    let mut total = 0;
+    for token in lexer::Token::tokenize(input) {
+        if let lexer::Token::NUMBER(n) = token? {
+            total += n;
+        }
+    }
+    Ok(total)
+}
+
+assert_eq!(sum("1 2 3").unwrap(), 6);
+assert!(sum("1 + 2").is_err());
+```
+
+`LexerError::into_owned` gives an `OwnedLexerError`, which copies what its messages need instead of borrowing the haystack,
+so it can outlive the input or be sent to another thread.
+```
+# use plexer::lexer;
+#
+# lexer!(
+#     NUMBER(usize) {
+#         regex!(r"[0-9]+") => |v| v.parse().map(Token::NUMBER),
+#     },
+#     WHITESPACE {
+#         ' ' => |_| Token::WHITESPACE,
+#     },
+# );
+#
+fn count(path: &str) -> Result<usize, lexer::OwnedLexerError> {
+    // The input only lives in this function
+    let input = format!("{} 1 + 2", path.len());
+    lexer::Token::tokenize(&input).try_fold(0, |count, token| token.map(|_| count + 1).map_err(|err| err.into_owned()))
+}
+
+let err = std::thread::spawn(|| count("file.txt")).join().unwrap().unwrap_err();
+assert_eq!(err.to_string(), "unexpected character '+' at line 1, column 5");
+assert_eq!(format!("{:#}", err).lines().nth(3), Some("1 | 8 1 + 2"));
+```
+
+`LexerError::render`, or the alternate format `{:#}`, shows the line of the error with a caret under it.
+```
+# use plexer::lexer;
+#
+# lexer!(
+#     NUMBER(usize) {
+#         regex!(r"[0-9]+") => |v| v.parse().map(Token::NUMBER),
+#     },
+#     WHITESPACE {
+#         [' ', '\n'] => |_| Token::WHITESPACE,
+#     },
+# );
+#
+let err = lexer::Token::tokenize("1 2\n3 ? 4").nth(6).unwrap().unwrap_err();
+
+assert_eq!(
+    format!("{:#}", err),
+    "\
+error: unexpected character '?'
+ --> line 2, column 3
+  |
+2 | 3 ? 4
+  |   ^"
+);
+```
+
+`LexerError::expected` gives the kinds of the rules that were tried where the error is, except `#[trivia]` ones,
+and `LexerError::expectation` puts them in words for an error message.
+```
+# use plexer::lexer;
+#
+lexer!(
+    NUMBER(usize) {
+        regex!(r"[0-9]+") => |v| v.parse().map(Token::NUMBER),
+    },
+    IDENTIFIER(&'a str) {
+        regex!(r"[a-z]+") => |v| Token::IDENTIFIER(v.as_str()),
+    },
+    OPERATOR(char) {
+        ['+', '-'] => |v| v.chars().next().map(Token::OPERATOR),
+    },
+    #[skip]
+    WHITESPACE {
+        ' ' => |_| Token::WHITESPACE,
+    },
+);
+use lexer::{Token, TokenKind};
+
+let err = Token::tokenize("x + ?").find_map(Result::err).unwrap();
+
+assert_eq!(err.expected(), &[TokenKind::NUMBER, TokenKind::IDENTIFIER, TokenKind::OPERATOR]);
+assert_eq!(
+    format!("{}, {}", err, err.expectation().unwrap()),
+    "unexpected character '?' at line 1, column 5, expected NUMBER, IDENTIFIER or OPERATOR"
+);
+```
+
+With the `miette` feature, `LexerError` and `OwnedLexerError` implement `miette::Diagnostic`,
+with a code for each `LexerErrorKind`, labeled spans and the expected rules as help. \
+An `OwnedLexerError` doesn't keep the haystack, `miette::Report::with_source_code` gives it back.
+```
+# #[cfg(feature = "miette")]
+# {
+# use plexer::lexer;
+# use miette::Diagnostic;
+#
+# lexer!(
+#     NUMBER(usize) {
+#         regex!(r"[0-9]+") => |v| v.parse().map(Token::NUMBER),
+#     },
+#     WHITESPACE {
+#         ' ' => |_| Token::WHITESPACE,
+#     },
+# );
+#
+let err = lexer::Token::tokenize("1 ? 2").find_map(Result::err).unwrap();
+
+assert_eq!(err.code().map(|code| code.to_string()).as_deref(), Some("plexer::unexpected"));
+assert_eq!(err.help().map(|help| help.to_string()).as_deref(), Some("expected NUMBER or WHITESPACE"));
+assert!(err.labels().is_some_and(|mut labels| labels.any(|label| label.offset() == 2)));
+
+let report = miette::Report::new(err.into_owned()).with_source_code("1 ? 2");
+assert!(report.to_string().starts_with("unexpected character '?'"));
+# }
+```
+
+The `Lexer` always moves by whole `char`, so errors never split a multi-byte character.
+```
+# use plexer::lexer;
+# use plexer::span::Span;
+#
+# lexer!(
+#     NUMBER(usize) {
+#         regex!(r"[0-9]+") => |v| v.parse().map(Token::NUMBER),
+#     },
+# );
+#
+let err = lexer::Token::tokenize("1€2").spanned().nth(1).unwrap().unwrap_err();
+
+assert_eq!(err.span(), Span::new(1, 4));
+assert_eq!(err.to_string(), "unexpected character '€' at line 1, column 2");
+```
+
+With `#![error_tokens]`, the `Lexer` never fails: errors are yielded as a `Token::ERROR` holding the unexpected input.
+`Lexer::tokens` then gives an iterator of plain tokens.
+```
+# use plexer::lexer;
+#
+lexer!(
+    #![error_tokens]
+    #![group_errors]
+
+    NUMBER(usize) {
+        regex!(r"[0-9]+") => |v| v.parse().map(Token::NUMBER),
+    },
+);
+use lexer::Token;
+
+let tokens: Vec<_> = Token::tokenize("12ab3").tokens().collect();
+assert_eq!(tokens, vec![Token::NUMBER(12), Token::ERROR("ab"), Token::NUMBER(3)]);
+```
+
+# Saving
+With the `serde` feature, [`Span`](crate::span::Span), [`Spanned`](crate::span::Spanned), [`Position`](crate::span::Position),
+`TokenKind`, `LexerErrorKind` and `OwnedLexerError` implement `Serialize` and `Deserialize`,
+to cache tokens or send errors to another process. \
+`#![serde]` derives them for `Token` too, which needs its fields to implement them,
+and `Token<'a>` borrows its `&'a str` fields from the deserializer. \
+The crate doesn't need its own `serde` dependency for the derives.
+```
+# #[cfg(feature = "serde")]
+# {
+# use plexer::lexer;
+use serde::{de::DeserializeOwned, Serialize};
+
+lexer!(
+    #![serde]
+    NUMBER(usize) {
+        regex!(r"[0-9]+") => |v| v.parse().map(Token::NUMBER),
+    },
+    WHITESPACE {
+        ' ' => |_| Token::WHITESPACE,
+    },
+);
+
+fn saved<T: Serialize + DeserializeOwned>() {}
+
+saved::<lexer::TokenKind>();
+saved::<lexer::OwnedLexerError>();
+saved::<plexer::span::Spanned<lexer::Token>>();
+# }
+```
+*/
@@ -0,0 +1,280 @@
+/*!
+Module for colorizing source code with the tokens of a lexer. \
+
+A theme maps each token to a [`Style`], or to `None` to leave it plain. \
+[`ansi`] then renders the haystack with the escape codes of a terminal, and [`html`] with `<span>` elements,
+the text between tokens, like skipped whitespace or errors, is kept as is.
+
+# Example
+```
+# use plexer::lexer;
+# use plexer::highlight::{self, Color, Style};
+#
+lexer!(
+    KEYWORD {
+        "let" => |_| Token::KEYWORD,
+    },
+    IDENTIFIER(&'a str) {
+        regex!(r"[a-z]+") => |v| Token::IDENTIFIER(v.as_str()),
+    },
+    NUMBER(u32) {
+        regex!(r"[0-9]+") => |v| v.parse().map(Token::NUMBER),
+    },
+    OPERATOR(char) {
+        ['<', '='] => |v| Token::OPERATOR(v.as_str().chars().next().unwrap()),
+    },
+    #[skip]
+    WHITESPACE {
+        ' ' => |_| Token::WHITESPACE,
+    },
+);
+use lexer::{Token, TokenKind};
+
+let theme = |token: &Token| match token.kind() {
+    TokenKind::KEYWORD => Some(Style::new().fg(Color::Magenta).bold()),
+    TokenKind::NUMBER => Some(Style::new().fg(Color::Rgb(255, 128, 0))),
+    _ => None,
+};
+
+let text = "let x = 1";
+let tokens = || Token::tokenize(text).spanned().filter_map(Result::ok);
+
+assert_eq!(
+    highlight::ansi(text, tokens(), theme),
+    "\x1b[1;35mlet\x1b[0m x = \x1b[38;2;255;128;0m1\x1b[0m"
+);
+assert_eq!(
+    highlight::html("x < 1", Token::tokenize("x < 1").spanned().filter_map(Result::ok), theme),
+    "x &lt; <span style=\"color: #ff8000\">1</span>"
+);
+```
+*/
+
+use crate::span::Spanned;
+use alloc::format;
+use alloc::string::String;
+use alloc::vec::Vec;
+
+/// Color of a [`Style`], either one of the 8 basic colors of terminals or an RGB color.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Color {
+    Black,
+    Red,
+    Green,
+    Yellow,
+    Blue,
+    Magenta,
+    Cyan,
+    White,
+    /// A 24-bit color, which not all terminals support
+    Rgb(u8, u8, u8),
+}
+
+impl Color {
+    // Parameters of the SGR escape code for the foreground
+    fn ansi(self) -> String {
+        match self {
+            Color::Rgb(r, g, b) => format!("38;2;{};{};{}", r, g, b),
+            basic => format!("{}", 30 + basic.index()),
+        }
+    }
+
+    fn css(self) -> String {
+        match self {
+            Color::Rgb(r, g, b) => format!("#{:02x}{:02x}{:02x}", r, g, b),
+            basic => String::from(["black", "red", "green", "yellow", "blue", "magenta", "cyan", "white"][basic.index()]),
+        }
+    }
+
+    fn index(self) -> usize {
+        match self {
+            Color::Black => 0,
+            Color::Red => 1,
+            Color::Green => 2,
+            Color::Yellow => 3,
+            Color::Blue => 4,
+            Color::Magenta => 5,
+            Color::Cyan => 6,
+            Color::White | Color::Rgb(..) => 7,
+        }
+    }
+}
+
+/**
+Appearance of a token, built like `Style::new().fg(Color::Red).bold()`.
+
+With a class, [`html`] uses it instead of an inline style, so the colors can come from a stylesheet.
+
+# Example
+```
+# use plexer::highlight::{self, Color, Style};
+# use plexer::span::{Span, Spanned};
+#
+let tokens = [Spanned::new((), Span::new(0, 2))];
+
+assert_eq!(highlight::html("if", tokens, |_| Some(Style::new().class("keyword"))), "<span class=\"keyword\">if</span>");
+assert_eq!(highlight::ansi("if", tokens, |_| Some(Style::new().fg(Color::Blue).underline())), "\x1b[4;34mif\x1b[0m");
+```
+*/
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub struct Style {
+    /// Color of the text
+    pub foreground: Option<Color>,
+    /// Bold text
+    pub bold: bool,
+    /// Italic text
+    pub italic: bool,
+    /// Underlined text
+    pub underline: bool,
+    /// Class of the `<span>` in HTML, replacing the inline style
+    pub class: Option<&'static str>,
+}
+
+impl Style {
+    /// Create a style that doesn't change anything.
+    pub const fn new() -> Self {
+        Self {
+            foreground: None,
+            bold: false,
+            italic: false,
+            underline: false,
+            class: None,
+        }
+    }
+
+    /// Set the color of the text.
+    pub const fn fg(self, color: Color) -> Self {
+        Self {
+            foreground: Some(color),
+            ..self
+        }
+    }
+
+    /// Make the text bold.
+    pub const fn bold(self) -> Self {
+        Self { bold: true, ..self }
+    }
+
+    /// Make the text italic.
+    pub const fn italic(self) -> Self {
+        Self {
+            italic: true,
+            ..self
+        }
+    }
+
+    /// Underline the text.
+    pub const fn underline(self) -> Self {
+        Self {
+            underline: true,
+            ..self
+        }
+    }
+
+    /// Set the class of the `<span>` in HTML.
+    pub const fn class(self, class: &'static str) -> Self {
+        Self {
+            class: Some(class),
+            ..self
+        }
+    }
+
+    fn ansi(&self) -> Option<String> {
+        let mut params = Vec::new();
+        let attributes = [(self.bold, "1"), (self.italic, "3"), (self.underline, "4")];
+        params.extend(attributes.iter().filter(|(set, _)| *set).map(|(_, param)| String::from(*param)));
+        params.extend(self.foreground.map(Color::ansi));
+        (!params.is_empty()).then(|| format!("\x1b[{}m", params.join(";")))
+    }
+
+    fn html(&self) -> Option<String> {
+        if let Some(class) = self.class {
+            return Some(format!("<span class=\"{}\">", escape(class)));
+        }
+        let attributes = [
+            (self.bold, "font-weight: bold"),
+            (self.italic, "font-style: italic"),
+            (self.underline, "text-decoration: underline"),
+        ];
+        let mut properties: Vec<String> = self.foreground.map(|color| format!("color: {}", color.css())).into_iter().collect();
+        properties.extend(attributes.iter().filter(|(set, _)| *set).map(|(_, property)| String::from(*property)));
+        (!properties.is_empty()).then(|| format!("<span style=\"{}\">", properties.join("; ")))
+    }
+}
+
+/**
+Render the haystack with ANSI escape codes, for a terminal.
+
+Tokens have to be in order and not overlap, like the ones of `Lexer::spanned`.
+
+# Panics
+When a span is out of bounds or not on `char` boundaries, or when the tokens aren't in order.
+*/
+pub fn ansi<T>(
+    haystack: &str,
+    tokens: impl IntoIterator<Item = Spanned<T>>,
+    theme: impl FnMut(&T) -> Option<Style>,
+) -> String {
+    render(haystack, tokens, theme, |text, out| out.push_str(text), |style| {
+        style.ansi().map(|open| (open, "\x1b[0m"))
+    })
+}
+
+/**
+Render the haystack as HTML, with a `<span>` for each styled token and the text escaped.
+
+Tokens have to be in order and not overlap, like the ones of `Lexer::spanned`.
+
+# Panics
+When a span is out of bounds or not on `char` boundaries, or when the tokens aren't in order.
+*/
+pub fn html<T>(
+    haystack: &str,
+    tokens: impl IntoIterator<Item = Spanned<T>>,
+    theme: impl FnMut(&T) -> Option<Style>,
+) -> String {
+    render(haystack, tokens, theme, |text, out| out.push_str(&escape(text)), |style| {
+        style.html().map(|open| (open, "</span>"))
+    })
+}
+
+// Writes the text between tokens as is, and wraps the styled tokens
+fn render<T>(
+    haystack: &str,
+    tokens: impl IntoIterator<Item = Spanned<T>>,
+    mut theme: impl FnMut(&T) -> Option<Style>,
+    text: impl Fn(&str, &mut String),
+    wrap: impl Fn(&Style) -> Option<(String, &'static str)>,
+) -> String {
+    let mut out = String::with_capacity(haystack.len());
+    let mut cursor = 0;
+    for Spanned { value, span } in tokens {
+        text(&haystack[cursor..span.start], &mut out);
+        match theme(&value).as_ref().and_then(&wrap) {
+            Some((open, close)) => {
+                out.push_str(&open);
+                text(&haystack[span.as_range()], &mut out);
+                out.push_str(close);
+            }
+            None => text(&haystack[span.as_range()], &mut out),
+        }
+        cursor = span.end;
+    }
+    text(&haystack[cursor..], &mut out);
+    out
+}
+
+pub(crate) fn escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '&' => escaped.push_str("&amp;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&#39;"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
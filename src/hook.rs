@@ -0,0 +1,166 @@
+/*!
+Module for the hooks of a lexer. \
+
+`Lexer::on_token` and `Lexer::on_error` install callbacks that receive each token or error along with its span and lexeme,
+for logging or metrics without wrapping the iterator. \
+A hook returns `()`, or a [`ControlFlow`] to stop the `Lexer` early, see the trait [`Flow`]. \
+Hooks are `Send + 'static`, their state is moved into them, or shared through an `Arc` to be read afterwards,
+so that a `Lexer` with hooks can still be sent to another thread.
+
+# Example
+```
+# use plexer::lexer;
+# use std::ops::ControlFlow;
+# use std::sync::atomic::{AtomicUsize, Ordering};
+# use std::sync::Arc;
+#
+lexer!(
+    WORD(&'a str) {
+        regex!(r"[a-z]+") => |v| Token::WORD(v.as_str()),
+    },
+    #[skip]
+    WHITESPACE {
+        ' ' => |_| Token::WHITESPACE,
+    },
+);
+use lexer::Token;
+
+let words = Arc::new(AtomicUsize::new(0));
+let counter = Arc::clone(&words);
+let mut errors = 0;
+let lex = Token::tokenize("one ? two ! three ? four")
+    .on_token(move |_, _, _| {
+        counter.fetch_add(1, Ordering::Relaxed);
+    })
+    .on_error(move |_, _, _| {
+        errors += 1;
+        if errors == 2 { ControlFlow::Break(()) } else { ControlFlow::Continue(()) }
+    });
+
+// The error that stops the lexer is still yielded
+assert_eq!(lex.count(), 4);
+assert_eq!(words.load(Ordering::Relaxed), 2);
+```
+
+# Shared state
+Hooks are called when each token or error is lexed, not when it is yielded. \
+They don't borrow anything, so what they collect is shared with an [`Arc`](std::sync::Arc) to be read afterwards,
+and a `Lexer` is `Send` and `Sync`, with or without hooks, so it can run on another thread.
+```
+# use plexer::lexer;
+# use std::sync::{Arc, Mutex};
+#
+lexer!(
+    NUMBER(usize) {
+        regex!(r"[0-9]+") => |v| v.parse().map(Token::NUMBER),
+    },
+    #[skip]
+    WHITESPACE {
+        ' ' => |_| Token::WHITESPACE,
+    },
+);
+use lexer::Token;
+
+fn is_send_sync<T: Send + Sync>(_: &T) {}
+is_send_sync(&Token::tokenize("1"));
+
+let lexemes = Arc::new(Mutex::new(Vec::new()));
+let log = Arc::clone(&lexemes);
+let lexer = Token::tokenize("1 22 333").on_token(move |_, _, lexeme| log.lock().unwrap().push(lexeme.to_string()));
+is_send_sync(&lexer);
+let tokens: Vec<_> = std::thread::spawn(move || lexer.flatten().collect()).join().unwrap();
+
+assert_eq!(tokens.len(), 3);
+assert_eq!(*lexemes.lock().unwrap(), vec!["1", "22", "333"]);
+```
+
+# Filtering
+Rules marked `#[trivia]` are yielded like any other, but `TokenKind::is_trivia` is true for them, as for `#[skip]` rules. \
+`Lexer::significant` drops the trivia tokens and `Lexer::only` keeps the tokens of the given kinds,
+errors are always yielded, and peeking or adapters like `Lexer::spanned` see the filtered tokens.
+```
+# use plexer::lexer;
+#
+lexer!(
+    NUMBER(usize) {
+        regex!(r"[0-9]+") => |v| v.parse().map(Token::NUMBER),
+    },
+    OPERATOR(char) {
+        ['+', '-'] => |v| v.chars().next().map(Token::OPERATOR),
+    },
+    #[trivia]
+    COMMENT {
+        regex!(r"#[^\n]*") => |_| Token::COMMENT,
+    },
+    #[trivia]
+    WHITESPACE {
+        [' ', '\n'] => |_| Token::WHITESPACE,
+    },
+);
+use lexer::{Token, TokenKind};
+
+let haystack = "1 + 2 # sum\n- 3";
+assert_eq!(Token::tokenize(haystack).count(), 11);
+
+let mut lex = Token::tokenize(haystack).significant();
+assert_eq!(lex.peek(), Some(Ok(&Token::NUMBER(1))));
+assert_eq!(lex.count(), 5);
+
+let numbers: Vec<_> = Token::tokenize(haystack).only(&[TokenKind::NUMBER]).flatten().collect();
+assert_eq!(numbers, vec![Token::NUMBER(1), Token::NUMBER(2), Token::NUMBER(3)]);
+assert!(TokenKind::COMMENT.is_trivia());
+```
+*/
+
+use core::ops::ControlFlow;
+
+/**
+The value returned by a hook.
+
+| Flow type               | Stops the `Lexer` when |
+|-------------------------|------------------------|
+| ```()```                | never                  |
+| ```ControlFlow<()>```   | `Break(())`            |
+| ```bool```              | `false`                |
+*/
+#[diagnostic::on_unimplemented(
+    message = "`{Self}` can't be returned by a hook",
+    note = "a hook returns `()`, a `ControlFlow<()>` or a `bool`"
+)]
+pub trait Flow {
+    /**
+    Returns whether the `Lexer` should go on.
+
+    # Example
+    ```
+    # use plexer::hook::Flow;
+    # use std::ops::ControlFlow;
+    #
+    assert_eq!(().flow(), ControlFlow::Continue(()));
+    assert_eq!(false.flow(), ControlFlow::Break(()));
+    ```
+    */
+    fn flow(self) -> ControlFlow<()>;
+}
+
+impl Flow for () {
+    fn flow(self) -> ControlFlow<()> {
+        ControlFlow::Continue(())
+    }
+}
+
+impl Flow for ControlFlow<()> {
+    fn flow(self) -> ControlFlow<()> {
+        self
+    }
+}
+
+impl Flow for bool {
+    fn flow(self) -> ControlFlow<()> {
+        if self {
+            ControlFlow::Continue(())
+        } else {
+            ControlFlow::Break(())
+        }
+    }
+}
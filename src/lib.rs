@@ -5,29 +5,51 @@
 
 # Principle
 This lexer is making use of the [`Pattern`](pattern::Pattern) trait to find tokens. \
-The idea is to create `Tokens`, explain how to match them with a `Pattern` and build them from the matched `String` value.
+The idea is to create `Tokens`, explain how to match them with a `Pattern` and build them from the matched value. \
+The [`Match`](pattern::Match) holds the position of the value and borrows the input, so tokens can hold `&'a str` slices without allocating.
 
 ```ignore
 lexer!(
     // Ordered by priority
+    #[optional attributes, ...]
     NAME(optional types, ...) {
-        impl Pattern => |value: String| -> Token,
+        impl Pattern => |value: Match<'a>| -> Token,
         ...,
     },
     ...,
 );
 ```
 
-The [`lexer!`] macro generates module `lexer` which contains `Token`, `LexerError`, `LexerResult` and `Lexer`.
+The [`lexer!`] macro generates a module, `lexer` by default, which contains `Token`, `TokenKind`, `Mode`, `LexerError`, `LexerErrorKind`, `OwnedLexerError`, `LexerResult`, `LexerStats`, `Lexer`, `LexerState`, `SpannedLexer`, `LexemeLexer`, `TripleLexer`, `TriviaLexer`, `LosslessLexer` and `RevLexer`.
 
 You can now call `Token::tokenize` to tokenize a `&str`,
 it should return a `Lexer` instance that implements `Iterator`. \
-Each iteration, the `Lexer` tries to match one of the given `Pattern` and returns a `LexerResult<Token>` built from the best match.
+Each iteration, the `Lexer` tries to match one of the given `Pattern` and returns a `LexerResult<Token>` built from the best match. \
+Use `Lexer::spanned` to also get the [`Span`](span::Span) of each token in a [`Spanned`](span::Spanned).
+The [`guide`] shows what else the `Lexer` can do, like peeking, backtracking, seeking or lexing backwards.
+
+When the rules are only known at runtime, a [`LexerBuilder`](runtime::LexerBuilder) builds a lexer without the macro,
+from patterns of any type, which a [`BoxedPattern`](pattern::BoxedPattern) can hold in a list. \
+With the `cli` feature, the `plexer` binary tokenizes files with the rules of a [grammar file](build#grammar-files),
+to check how real inputs lex.
+
+With the `memmap2` feature, the `mmap` module maps a file in memory so that `Token::tokenize_mmap` lexes it without copying it.
+
+With the `chumsky` and `nom` features, the `parser` module feeds the tokens to these parser-combinator crates,
+with spans pointing back to the haystack.
+
+With the `wasm-bindgen` feature, `plexer::wasm_tokenize!()` exports a `tokenize(input)` function
+returning an array of `{kind, start, end}` objects to JavaScript, with offsets in UTF-16 code units like the indices of a JavaScript string,
+so a web playground can reuse the lexer.
+
+Without the default `std` feature, the crate is `no_std` and only needs `alloc`,
+everything but `Token::tokenize_reader` and `build::compile_grammar` is still available.
+
+The minimum supported Rust version is 1.82, with or without `std`.
 
 # Example
 Here is an example for a simple math lexer.
 ```
-# use regex::Regex;
 # use plexer::lexer;
 #
 lexer!(
@@ -42,12 +64,12 @@ lexer!(
     // Integer numbers
     NUMBER(usize) {
         |s: &str| s.chars().all(|c| c.is_digit(10))
-            => |v: String| Token::NUMBER(v.parse().unwrap()),
+            => |v| Token::NUMBER(v.parse().unwrap()),
     },
     // Variable names
-    IDENTIFIER(String) {
+    IDENTIFIER(&'a str) {
         regex!(r"[a-zA-Z_$][a-zA-Z_$0-9]*")
-            => |v: String| Token::IDENTIFIER(v),
+            => |v| Token::IDENTIFIER(v.as_str()),
     },
     WHITESPACE {
         [' ', '\n'] => |_| Token::WHITESPACE,
@@ -57,15 +79,28 @@ lexer!(
 That will expand to these enum and structs.
 ```ignore
 mod lexer {
-    pub enum Token {
+    pub enum Token<'a> {
         OPERATOR(char),
         NUMBER(usize),
-        IDENTIFIER(String),
+        IDENTIFIER(&'a str),
         WHITESPACE,
     }
 
+    pub enum TokenKind { OPERATOR, NUMBER, IDENTIFIER, WHITESPACE }
+    pub enum Mode { INITIAL }
+
     pub struct Lexer {...}
+    pub struct LexerState {...}
+    pub struct SpannedLexer {...}
+    pub struct LexemeLexer {...}
+    pub struct TripleLexer {...}
+    pub struct TriviaLexer {...}
+    pub struct LosslessLexer {...}
+    pub struct RevLexer {...}
     pub struct LexerError {...}
+    pub enum LexerErrorKind {...}
+    pub struct OwnedLexerError {...}
+    pub struct LexerStats {...}
     pub type LexerResult<T> = Result<T, LexerError>;
 }
 ```
@@ -83,11 +118,11 @@ And you can use them afterwards.
 #     },
 #     NUMBER(usize) {
 #         |s: &str| s.chars().all(|c| c.is_digit(10))
-#             => |v: String| Token::NUMBER(v.parse().unwrap()),
+#             => |v| Token::NUMBER(v.parse().unwrap()),
 #     },
-#     IDENTIFIER(String) {
+#     IDENTIFIER(&'a str) {
 #         regex!(r"[a-zA-Z_$][a-zA-Z_$0-9]*")
-#             => |v: String| Token::IDENTIFIER(v),
+#             => |v| Token::IDENTIFIER(v.as_str()),
 #     },
 #     WHITESPACE {
 #         [' ', '\n'] => |_| Token::WHITESPACE,
@@ -105,10 +140,80 @@ assert!(err.nth(4).is_some_and(|res| res.is_err()));
 ```
 */
 
+#![cfg_attr(not(feature = "std"), no_std)]
+
+extern crate alloc;
+
+pub mod arena;
+pub mod build;
+pub mod bytes;
+pub mod dump;
+pub mod guide;
+pub mod highlight;
+pub mod hook;
+#[cfg(feature = "memmap2")]
+pub mod mmap;
+#[cfg(not(feature = "std"))]
+mod once;
+#[cfg(any(feature = "chumsky", feature = "nom"))]
+pub mod parser;
 pub mod pattern;
+pub mod runtime;
+pub mod semantic;
+pub mod source;
+pub mod span;
+#[cfg(feature = "proptest")]
+pub mod strategy;
+pub mod summary;
+mod sync;
+pub mod testing;
+pub mod textmate;
+pub mod trace;
+#[cfg(feature = "wasm-bindgen")]
+pub mod wasm;
+
+// Paths used by the generated lexers, which can't rely on the prelude of a `no_std` crate
+#[doc(hidden)]
+pub mod __private {
+
+    #[cfg(not(feature = "std"))]
+    pub use crate::once::OnceLock;
+    pub use crate::build::{Keyword, KeywordField, KeywordUnit};
+    pub use alloc::borrow::ToOwned;
+    pub use alloc::boxed::Box;
+    pub use alloc::collections::{BTreeMap, VecDeque};
+    pub use alloc::sync::Arc;
+    pub use alloc::string::{String, ToString};
+    pub use alloc::vec::Vec;
+    pub use alloc::{format, vec};
+    pub use crate::sync::{lock, Mutex};
+    pub use core::ops::ControlFlow;
+    pub use regex::bytes::RegexSet as ByteRegexSet;
+    pub use regex::RegexSet;
+    #[cfg(feature = "std")]
+    pub use std::sync::OnceLock;
+    #[cfg(feature = "miette")]
+    pub use miette;
+    #[cfg(feature = "serde")]
+    pub use serde;
+    #[cfg(feature = "codespan")]
+    pub use codespan_reporting;
+    #[cfg(feature = "arbitrary")]
+    pub use arbitrary;
+    #[cfg(feature = "proptest")]
+    pub use proptest;
+    #[cfg(feature = "tokio")]
+    pub use futures_core;
+    #[cfg(feature = "tokio")]
+    pub use tokio;
+    #[cfg(feature = "wasm-bindgen")]
+    pub use js_sys;
+    #[cfg(feature = "wasm-bindgen")]
+    pub use wasm_bindgen;
+}
 
 /**
-Macro to build a [Regex](https://docs.rs/regex/latest/regex/struct.Regex.html).
+Macro to build a [`Regex`](pattern::Regex), which is fast at finding prefixes.
 
 # Panics
 If the given pattern is not `@safe` and not a valid regex.
@@ -128,138 +233,3412 @@ let re = regex!("t|e|s|t");
 // Don't unwrap
 let gex = regex!(@safe "t|e|s|t").unwrap();
 ```
+
+Several parts can be given, they are concatenated into a single pattern.
+```
+# use plexer::regex;
+#
+const DIGITS: &str = "[0-9]+";
+
+let re = regex!(DIGITS, r"\.", DIGITS);
+assert!(re.is_match("3.14"));
+```
 **/
 #[macro_export]
 macro_rules! regex {
     ($pattern:literal) => {
-        regex::Regex::new($pattern).unwrap()
+        $crate::pattern::Regex::new($pattern).unwrap()
     };
     (@safe $pattern:literal) => {
-        regex::Regex::new($pattern)
+        $crate::pattern::Regex::new($pattern)
+    };
+    (@safe $($part:expr),+ $(,)?) => {
+        $crate::pattern::Regex::new(&[$($part),+].concat())
+    };
+    ($($part:expr),+ $(,)?) => {
+        $crate::pattern::Regex::new(&[$($part),+].concat()).unwrap()
     };
 }
 
 /**
-Macro to build your own plugin-based lexer.
+Macro to build a [`ByteRegex`](bytes::ByteRegex), the same way as [`regex!`] but for `&[u8]`.
 
-# Usage
-```ignore
-lexer!(
-    // Ordered by priority
-    NAME(optional types, ...) {
-        impl Pattern => |value: String| -> Token,
-        ...,
-    },
-    ...,
-);
-```
+With `#![bytes]`, it replaces `regex!` in the rules of [`lexer!`].
 
 # Example
-Here is an example for a simple condition statement lexer.
 ```
-# use plexer::lexer;
+# use plexer::byte_regex;
 #
-lexer!(
-    DELIMITER(char) {
-        '{' => |_| Token::DELIMITER('{'),
-        '}' => |_| Token::DELIMITER('}'),
-    },
-    KEYWORD(String) {
-        "if" => |v: String| Token::KEYWORD(v),
-        "else" => |v: String| Token::KEYWORD(v),
-    },
-    IDENTIFIER(String) {
-        regex!(r"[a-zA-Z_$][a-zA-Z_$0-9]*")
-            => |v: String| Token::IDENTIFIER(v),
-    },
-    WHITESPACE {
-        [' ', '\n', '\t'] => |_| Token::WHITESPACE,
-    },
-);
+let re = byte_regex!(r"(?-u)%PDF-[0-9]\.[0-9]\n[\x80-\xFF]+");
+assert!(re.is_match(b"%PDF-1.7\n\xE2\xE3\xCF\xD3"));
 
-let mut lex = lexer::Token::tokenize("if test { one } else { two }");
-assert_eq!(lex.next(), Some(Ok(lexer::Token::KEYWORD(String::from("if")))));
+let gex = byte_regex!(@safe "(");
+assert!(gex.is_err());
 ```
 **/
 #[macro_export]
-macro_rules! lexer {
-    ($($token:ident $(($($field: ty),+))? {$( $pattern:expr => $build:expr,)+}),* $(,)*) => {
-        mod lexer {
-            use $crate::regex;
-            use $crate::pattern::Pattern;
+macro_rules! byte_regex {
+    ($pattern:literal) => {
+        $crate::bytes::ByteRegex::new($pattern).unwrap()
+    };
+    (@safe $pattern:literal) => {
+        $crate::bytes::ByteRegex::new($pattern)
+    };
+    (@safe $($part:expr),+ $(,)?) => {
+        $crate::bytes::ByteRegex::new(&[$($part),+].concat())
+    };
+    ($($part:expr),+ $(,)?) => {
+        $crate::bytes::ByteRegex::new(&[$($part),+].concat()).unwrap()
+    };
+}
+
+// Each rule is checked on its own, so that a malformed one is reported with its name
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __rule {
+    ($token:ident keywords [$($keyword:literal),+ $(,)?]) => {};
+    ($token:ident keywords [$($keyword:tt)*]) => {
+        compile_error!(concat!(
+            "keywords of rule `", stringify!($token), "` must be string literals, found `", stringify!($($keyword)*), "`"
+        ));
+    };
+    ($token:ident keywords [$($keyword:tt)*] {$($arm:tt)*}) => {
+        compile_error!(concat!("rule `", stringify!($token), "` can't have both keywords and patterns"));
+    };
+    ($token:ident) => {
+        compile_error!(concat!(
+            "rule `", stringify!($token), "` must be followed by its patterns in braces, ",
+            "like `", stringify!($token), " { pattern => builder, }`"
+        ));
+    };
+    ($token:ident {}) => {
+        compile_error!(concat!("rule `", stringify!($token), "` has no patterns"));
+    };
+    ($token:ident {$($pattern:expr $(=> $build:expr $(=> $action:ident $(($target:ident))?)?)?),+ $(,)?}) => {
+        $($crate::__arm!($token $pattern $(=> $build)?);)+
+    };
+    ($token:ident {$($arm:tt)*}) => {
+        compile_error!(concat!(
+            "malformed pattern in rule `", stringify!($token), "`, expected `pattern => builder`, found `",
+            stringify!($($arm)*), "`"
+        ));
+    };
+}
 
-            const MAX_LENGTH: usize = 1024;
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __arm {
+    ($token:ident $pattern:expr => $build:expr) => {};
+    ($token:ident $pattern:expr) => {
+        compile_error!(concat!(
+            "pattern `", stringify!($pattern), "` of rule `", stringify!($token), "` has no builder, ",
+            "expected `pattern => builder`"
+        ));
+    };
+}
 
-            #[derive(Debug, Clone, PartialEq)]
-            pub enum Token<'a> {
-                $($token$(($($field),+))?),*,
-                _phantom(std::marker::PhantomData<&'a ()>),
-            }
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __skip {
+    () => {
+        false
+    };
+    ([skip] $($attr:tt)*) => {
+        true
+    };
+    ([$($other:tt)*] $($attr:tt)*) => {
+        $crate::__skip!($($attr)*)
+    };
+}
 
-            #[allow(dead_code)]
-            impl<'a> Token<'a> {
-                pub fn tokenize(haystack: &'a str) -> Lexer<'a> {
-                    Lexer { haystack, cursor: 0 }
-                }
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __state {
+    () => {
+        type State = ();
+    };
+    ([state($state:ty)] $($option:tt)*) => {
+        type State = $state;
+    };
+    ([$($other:tt)*] $($option:tt)*) => {
+        $crate::__state!($($option)*);
+    };
+}
+
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __build {
+    () => {
+        fn build<'a, T, B>(
+            build: impl FnOnce(MatchOf<'a>) -> T,
+            mat: MatchOf<'a>,
+            _: &mut State,
+        ) -> Result<$crate::build::Built<TokenOf<'a>>, Option<String>>
+        where
+            T: $crate::build::Outcome<B>,
+            B: Into<$crate::build::Built<TokenOf<'a>>>,
+        {
+            build(mat).outcome().map(Into::into)
+        }
+    };
+    ([state($($type:tt)*)] $($option:tt)*) => {
+        fn build<'a, T, B>(
+            build: impl FnOnce(MatchOf<'a>, &mut State) -> T,
+            mat: MatchOf<'a>,
+            state: &mut State,
+        ) -> Result<$crate::build::Built<TokenOf<'a>>, Option<String>>
+        where
+            T: $crate::build::Outcome<B>,
+            B: Into<$crate::build::Built<TokenOf<'a>>>,
+        {
+            build(mat, state).outcome().map(Into::into)
+        }
+    };
+    ([$($other:tt)*] $($option:tt)*) => {
+        $crate::__build!($($option)*);
+    };
+}
+
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __trivia {
+    () => {
+        false
+    };
+    ([trivia] $($attr:tt)*) => {
+        true
+    };
+    ([skip] $($attr:tt)*) => {
+        true
+    };
+    ([$($other:tt)*] $($attr:tt)*) => {
+        $crate::__trivia!($($attr)*)
+    };
+}
+
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __in_mode {
+    ($current:expr;) => {
+        $current == Mode::INITIAL
+    };
+    ($current:expr; [mode($($mode:ident),+)] $($attr:tt)*) => {
+        matches!($current, $(Mode::$mode)|+)
+    };
+    ($current:expr; [$($other:tt)*] $($attr:tt)*) => {
+        $crate::__in_mode!($current; $($attr)*)
+    };
+}
+
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __guard {
+    ($previous:expr;) => {
+        true
+    };
+    ($previous:expr; [guard($guard:expr)] $($attr:tt)*) => {
+        guard($guard, $previous)
+    };
+    ($previous:expr; [$($other:tt)*] $($attr:tt)*) => {
+        $crate::__guard!($previous; $($attr)*)
+    };
+}
+
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __action {
+    () => {
+        None
+    };
+    (begin($mode:ident)) => {
+        Some(Action::Begin(Mode::$mode))
+    };
+    (push($mode:ident)) => {
+        Some(Action::Push(Mode::$mode))
+    };
+    (pop) => {
+        Some(Action::Pop)
+    };
+    ($($other:tt)*) => {
+        compile_error!(concat!("unknown action `", stringify!($($other)*), "`, expected `begin(MODE)`, `push(MODE)` or `pop`"))
+    };
+}
+
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __flag {
+    ($flag:ident;) => {
+        false
+    };
+    (group_errors; [group_errors] $($option:tt)*) => {
+        true
+    };
+    (eof; [eof] $($option:tt)*) => {
+        true
+    };
+    ($flag:ident; [$($other:tt)*] $($option:tt)*) => {
+        $crate::__flag!($flag; $($option)*)
+    };
+}
+
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __option {
+    ($option:ident, $default:expr;) => {
+        $default
+    };
+    (max_length, $default:expr; [max_length($value:expr)] $($option:tt)*) => {
+        $value
+    };
+    ($option:ident, $default:expr; [$($other:tt)*] $($rest:tt)*) => {
+        $crate::__option!($option, $default; $($rest)*)
+    };
+}
+
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __token_enum {
+    ([$($attr:tt)*] [] [$($kind:ident)*] [] [$($error:ident)?] $($variant:tt)*) => {
+        $($attr)*
+        pub enum Token<'a> {
+            $($variant)*
+            $($error(&'a Haystack),)?
+            _phantom(core::marker::PhantomData<&'a ()>),
+        }
+
+        type TokenOf<'a> = Token<'a>;
+
+        $crate::__token_enum!(@kind [$($kind)* $($error)?] Token::_phantom(_) => unreachable!("phantom tokens are never built"),);
+    };
+    ([$($attr:tt)*] [] [$($kind:ident)*] [owned] [$($error:ident)?] $($variant:tt)*) => {
+        $($attr)*
+        pub enum Token {
+            $($variant)*
+            $($error(String),)?
+        }
+
+        type TokenOf<'a> = Token;
+
+        $crate::__token_enum!(@kind [$($kind)* $($error)?]);
+    };
+    (@kind [$($kind:ident)*] $($extra:tt)*) => {
+        $crate::__serde! {
+            @derive
+            #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+            pub enum TokenKind {
+                $($kind),*
             }
+        }
 
-            #[derive(Debug, Clone, PartialEq)]
-            pub struct LexerError<'a> {
-                haystack: &'a str,
-                cursor: usize,
+        #[allow(dead_code)]
+        impl TokenKind {
+            pub fn all() -> &'static [TokenKind] {
+                &[$(TokenKind::$kind),*]
             }
 
-            impl<'a> LexerError<'a> {
-                 fn new(haystack: &'a str, cursor:usize) -> Self {
-                     Self { haystack, cursor }
-                 }
+            pub fn name(&self) -> &'static str {
+                match self {
+                    $(TokenKind::$kind => stringify!($kind)),*
+                }
             }
+        }
 
-            impl<'a> std::fmt::Display for LexerError<'a> {
-                fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
-                        write!(
-                            f, "unexpected character '{}' at index {}",
-                            &self.haystack[self.cursor..=self.cursor],
-                            self.cursor
-                        )
+        $crate::__arbitrary! {
+            impl<'u> $crate::__private::arbitrary::Arbitrary<'u> for TokenKind {
+                fn arbitrary(u: &mut $crate::__private::arbitrary::Unstructured<'u>) -> $crate::__private::arbitrary::Result<Self> {
+                    u.choose(TokenKind::all()).copied()
                 }
             }
+        }
 
-            pub type LexerResult<'a, T> = Result<T, LexerError<'a>>;
+        #[allow(dead_code)]
+        impl<'a> TokenOf<'a> {
+            pub fn kind(&self) -> TokenKind {
+                match self {
+                    $(Token::$kind { .. } => TokenKind::$kind,)*
+                    $($extra)*
+                }
+            }
+        }
+    };
+    ([$($attr:tt)*] [[derive($($extra:tt)*)] $($option:tt)*] $kinds:tt $owned:tt $error:tt $($variant:tt)*) => {
+        $crate::__token_enum!([$($attr)* #[derive($($extra)*)]] [$($option)*] $kinds $owned $error $($variant)*);
+    };
+    ([$($attr:tt)*] [[serde] $($option:tt)*] $($rest:tt)*) => {
+        $crate::__serde!(@token [$($attr)*] [$($option)*] $($rest)*);
+    };
+    ([$($attr:tt)*] [[eof] $($option:tt)*] [$($kind:ident)*] $owned:tt $error:tt $($variant:tt)*) => {
+        $crate::__token_enum!([$($attr)*] [$($option)*] [$($kind)* EOF] $owned $error $($variant)* EOF,);
+    };
+    ([$($attr:tt)*] [[indent] $($option:tt)*] [$($kind:ident)*] $owned:tt $error:tt $($variant:tt)*) => {
+        $crate::__token_enum!([$($attr)*] [$($option)*] [$($kind)* INDENT DEDENT] $owned $error $($variant)* INDENT, DEDENT,);
+    };
+    ([$($attr:tt)*] [[error_tokens] $($option:tt)*] $kinds:tt $owned:tt [] $($variant:tt)*) => {
+        $crate::__token_enum!([$($attr)*] [$($option)*] $kinds $owned [ERROR] $($variant)*);
+    };
+    ([$($attr:tt)*] [[owned] $($option:tt)*] $kinds:tt [] $error:tt $($variant:tt)*) => {
+        $crate::__token_enum!([$($attr)*] [$($option)*] $kinds [owned] $error $($variant)*);
+    };
+    ([$($attr:tt)*] [[$($other:tt)*] $($option:tt)*] $kinds:tt $owned:tt $error:tt $($variant:tt)*) => {
+        $crate::__token_enum!([$($attr)*] [$($option)*] $kinds $owned $error $($variant)*);
+    };
+}
 
-            #[derive(Debug)]
-            pub struct Lexer<'a> {
-                haystack: &'a str,
-                cursor: usize,
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __display {
+    ([] $($rule:tt)*) => {};
+    ([[display] $($option:tt)*] $($token:ident $(($($field:ty),+))?,)*) => {
+        impl<'a> core::fmt::Display for TokenOf<'a> {
+            fn fmt(&self, f: &mut core::fmt::Formatter) -> core::fmt::Result {
+                $($crate::__display_arm!(self, f, $token [] [a b c d e f g h i j k l] [$($($field)+)?]);)*
+                f.write_str(self.kind().name())
             }
+        }
+    };
+    ([[$($other:tt)*] $($option:tt)*] $($rule:tt)*) => {
+        $crate::__display!([$($option)*] $($rule)*);
+    };
+}
 
-            impl<'a> Iterator for Lexer<'a> {
-                type Item = LexerResult<'a, Token<'a>>;
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __display_arm {
+    ($self:ident, $f:ident, $token:ident [] $names:tt []) => {
+        if let Token::$token = $self {
+            return $f.write_str(stringify!($token));
+        }
+    };
+    ($self:ident, $f:ident, $token:ident [$($bound:ident)*] [$name:ident $($names:ident)*] [$field:tt $($rest:tt)*]) => {
+        $crate::__display_arm!($self, $f, $token [$($bound)* $name] [$($names)*] [$($rest)*]);
+    };
+    ($self:ident, $f:ident, $token:ident [$first:ident $($bound:ident)*] $names:tt []) => {
+        if let Token::$token($first $(, $bound)*) = $self {
+            write!($f, "{}", $first)?;
+            $(write!($f, " {}", $bound)?;)*
+            return Ok(());
+        }
+    };
+}
 
-                fn next(&mut self) -> Option<Self::Item> {
-                    if self.cursor < self.haystack.len() {
-                        let start = self.cursor;
-                        let end = std::cmp::min(self.haystack.len(), self.cursor + MAX_LENGTH);
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __eof {
+    () => {
+        None
+    };
+    ([eof] $($option:tt)*) => {
+        Some(Token::EOF)
+    };
+    ([$($other:tt)*] $($option:tt)*) => {
+        $crate::__eof!($($option)*)
+    };
+}
 
-                        let mut token = None;
-                        let mut len = 0;
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __indent {
+    () => {
+        None::<(Token, Token)>
+    };
+    ([indent] $($option:tt)*) => {
+        Some((Token::INDENT, Token::DEDENT))
+    };
+    ([$($other:tt)*] $($option:tt)*) => {
+        $crate::__indent!($($option)*)
+    };
+}
 
-                        $($({
-                            if let Some(mat) = $pattern.find_prefix_in(&self.haystack[start..end]) {
-                                if mat.len() > len {
-                                    token = Some($build(mat.to_string()));
-                                    len = mat.len();
-                                }
-                            }
-                        })+)*
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __error_token {
+    ($err:expr;) => {
+        Err($err)
+    };
+    ($err:expr; [error_tokens] $($option:tt)*) => {
+        Ok((Token::ERROR($err.haystack[$err.cursor..$err.end].into()), $err.span()))
+    };
+    ($err:expr; [$($other:tt)*] $($option:tt)*) => {
+        $crate::__error_token!($err; $($option)*)
+    };
+}
 
-                        self.cursor += std::cmp::max(len, 1);
-                        Some(token.ok_or(LexerError::new(self.haystack.clone(), self.cursor - 1)))
-                    } else {
-                        None
-                    }
-                }
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __error_lexer {
+    () => {};
+    ([error_tokens] $($option:tt)*) => {
+        #[allow(dead_code)]
+        impl<'a> Lexer<'a> {
+            pub fn tokens(self) -> Tokens<'a> {
+                Tokens { lexer: self }
+            }
+        }
+
+        #[derive(Debug)]
+        pub struct Tokens<'a> {
+            lexer: Lexer<'a>,
+        }
+
+        impl<'a> Iterator for Tokens<'a> {
+            type Item = TokenOf<'a>;
+
+            fn next(&mut self) -> Option<Self::Item> {
+                self.lexer.lex().map(|result| match result {
+                    Ok((token, _)) => token,
+                    Err(_) => unreachable!("errors are turned into tokens"),
+                })
             }
         }
     };
+    ([$($other:tt)*] $($option:tt)*) => {
+        $crate::__error_lexer!($($option)*);
+    };
+}
+
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __fragments {
+    () => {};
+    ([fragment($($name:ident = $value:literal),+ $(,)?)] $($option:tt)*) => {
+        $(#[allow(dead_code)]
+        const $name: &str = concat!("(?:", $value, ")");)+
+        $crate::__fragments!($($option)*);
+    };
+    ([$($other:tt)*] $($option:tt)*) => {
+        $crate::__fragments!($($option)*);
+    };
+}
+
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __haystack {
+    () => {
+        use $crate::regex;
+        use $crate::pattern::{Balanced, CharClass, Delimited, Many1, NoneOf, Not, Optional, Or, Pattern, Repeat, TakeUntil, TakeWhile, Then, Until};
+        use $crate::pattern::classes;
+
+        type Haystack = str;
+        type MatchOf<'a> = $crate::pattern::Match<'a>;
+        type PatternOf = dyn for<'h> Pattern<'h> + Send + Sync;
+        type RegexSetOf = RegexSet;
+    };
+    ([bytes] $($option:tt)*) => {
+        use $crate::byte_regex as regex;
+        use $crate::bytes::BytePattern;
+
+        type Haystack = [u8];
+        type MatchOf<'a> = $crate::bytes::ByteMatch<'a>;
+        type PatternOf = dyn for<'h> BytePattern<'h> + Send + Sync;
+        type RegexSetOf = ByteRegexSet;
+
+        $crate::__haystack!(@bytes $($option)*);
+    };
+    ([owned] $($option:tt)*) => {
+        $crate::__haystack!(@owned $($option)*);
+    };
+    ([$($other:tt)*] $($option:tt)*) => {
+        $crate::__haystack!($($option)*);
+    };
+    (@bytes) => {};
+    (@bytes [owned] $($option:tt)*) => {
+        compile_error!("`#![owned]` can't be used with `#![bytes]`");
+    };
+    (@bytes [$($other:tt)*] $($option:tt)*) => {
+        $crate::__haystack!(@bytes $($option)*);
+    };
+    (@owned) => {
+        $crate::__haystack!();
+    };
+    (@owned [bytes] $($option:tt)*) => {
+        $crate::__haystack!([bytes] [owned]);
+    };
+    (@owned [$($other:tt)*] $($option:tt)*) => {
+        $crate::__haystack!(@owned $($option)*);
+    };
+}
+
+// Items that need `std`, the feature is checked here since a `cfg` in a macro applies to the calling crate
+#[cfg(feature = "std")]
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __std {
+    ($($item:item)*) => {
+        $($item)*
+    };
+}
+
+#[cfg(not(feature = "std"))]
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __std {
+    ($($item:item)*) => {};
+}
+
+// Items for the `miette` feature, checked here like `std`
+#[cfg(feature = "miette")]
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __miette {
+    ($($item:item)*) => {
+        $($item)*
+    };
+}
+
+#[cfg(not(feature = "miette"))]
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __miette {
+    ($($item:item)*) => {};
+}
+
+// Derives for the `serde` feature, the derived code finds `serde` through the `__private` glob import
+#[cfg(feature = "serde")]
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __serde {
+    (@derive $($item:tt)*) => {
+        #[derive($crate::__private::serde::Serialize, $crate::__private::serde::Deserialize)]
+        #[serde(crate = "serde")]
+        $($item)*
+    };
+    (@token [$($attr:tt)*] $($rest:tt)*) => {
+        $crate::__token_enum!(
+            [
+                $($attr)*
+                #[derive($crate::__private::serde::Serialize, $crate::__private::serde::Deserialize)]
+                #[serde(crate = "serde")]
+            ]
+            $($rest)*
+        );
+    };
+}
+
+#[cfg(not(feature = "serde"))]
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __serde {
+    (@derive $($item:tt)*) => {
+        $($item)*
+    };
+    (@token $($rest:tt)*) => {
+        compile_error!("`#![serde]` needs the `serde` feature of plexer");
+    };
+}
+
+// Items for the `codespan` feature
+#[cfg(feature = "codespan")]
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __codespan {
+    ($($item:item)*) => {
+        $($item)*
+    };
+}
+
+#[cfg(not(feature = "codespan"))]
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __codespan {
+    ($($item:item)*) => {};
+}
+
+// Items for the `tokio` feature
+#[cfg(feature = "tokio")]
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __tokio {
+    ($($item:item)*) => {
+        $($item)*
+    };
+}
+
+#[cfg(not(feature = "tokio"))]
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __tokio {
+    ($($item:item)*) => {};
+}
+
+// Items for the `memmap2` feature
+#[cfg(feature = "memmap2")]
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __mmap {
+    ($($item:item)*) => {
+        $($item)*
+    };
+}
+
+#[cfg(not(feature = "memmap2"))]
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __mmap {
+    ($($item:item)*) => {};
+}
+
+// Items for the `chumsky` feature
+#[cfg(feature = "chumsky")]
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __chumsky {
+    ($($item:item)*) => {
+        $($item)*
+    };
+}
+
+#[cfg(not(feature = "chumsky"))]
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __chumsky {
+    ($($item:item)*) => {};
+}
+
+// Items for the `arbitrary` feature
+#[cfg(feature = "arbitrary")]
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __arbitrary {
+    ($($item:item)*) => {
+        $($item)*
+    };
+}
+
+#[cfg(not(feature = "arbitrary"))]
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __arbitrary {
+    ($($item:item)*) => {};
+}
+
+// Items for the `proptest` feature
+#[cfg(feature = "proptest")]
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __proptest {
+    ($($item:item)*) => {
+        $($item)*
+    };
+}
+
+#[cfg(not(feature = "proptest"))]
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __proptest {
+    ($($item:item)*) => {};
+}
+
+// Items for the `wasm-bindgen` feature
+#[cfg(feature = "wasm-bindgen")]
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __wasm {
+    ($($item:item)*) => {
+        $($item)*
+    };
+}
+
+#[cfg(not(feature = "wasm-bindgen"))]
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __wasm {
+    ($($item:item)*) => {};
+}
+
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __owned_lexer {
+    () => {};
+    ([owned] $($option:tt)*) => {
+        #[allow(dead_code)]
+        impl Token {
+            pub fn tokenize_chars<I: Iterator<Item = char>>(chars: I) -> CharsLexer<I> {
+                CharsLexer { chars, stream: Stream::default() }
+            }
+
+            pub fn tokenize_chunks<I>(chunks: I) -> ChunksLexer<I>
+            where
+                I: Iterator,
+                I::Item: AsRef<str>,
+            {
+                ChunksLexer { chunks, stream: Stream::default() }
+            }
+        }
+
+        fn detach(state: LexerState<'_>) -> LexerState<'static> {
+            LexerState {
+                cursor: state.cursor,
+                position: state.position,
+                mode: state.mode,
+                stack: state.stack,
+                opened: state.opened,
+                previous: state.previous,
+                indents: state.indents,
+                dedents: state.dedents,
+                queued: state.queued,
+                finished: state.finished,
+                state: state.state,
+                haystack: core::marker::PhantomData,
+            }
+        }
+
+        type Entry = (LexerState<'static>, Result<$crate::span::Spanned<Token>, $crate::span::Spanned<String>>);
+
+        #[derive(Debug, Clone, Default)]
+        pub struct TokenBuffer {
+            entries: Vec<Entry>,
+        }
+
+        #[allow(dead_code)]
+        impl TokenBuffer {
+            pub fn new(haystack: &str) -> Self {
+                let mut buffer = Self::default();
+                buffer.entries = buffer.lex(Token::tokenize(haystack), None).0;
+                buffer
+            }
+
+            pub fn len(&self) -> usize {
+                self.entries.len()
+            }
+
+            pub fn is_empty(&self) -> bool {
+                self.entries.is_empty()
+            }
+
+            pub fn tokens(&self) -> impl Iterator<Item = &Result<$crate::span::Spanned<Token>, $crate::span::Spanned<String>>> {
+                self.entries.iter().map(|(_, result)| result)
+            }
+
+            pub fn edit(&mut self, haystack: &str, replaced: core::ops::Range<usize>, inserted: usize) -> core::ops::Range<usize> {
+                // Patterns look at most MAX_LENGTH bytes ahead, tokens lexed further from the edit can't change
+                let first = self
+                    .entries
+                    .iter()
+                    .position(|(_, result)| {
+                        let end = result.as_ref().map_or_else(|err| err.span.end, |token| token.span.end);
+                        end.saturating_add(MAX_LENGTH) >= replaced.start
+                    })
+                    .unwrap_or(self.entries.len().saturating_sub(1));
+
+                let mut lexer = Token::tokenize(haystack);
+                if let Some((state, _)) = self.entries.get(first) {
+                    let mut state = state.clone();
+                    state.position = $crate::span::Position::of(haystack, state.cursor);
+                    lexer.rewind(state);
+                }
+
+                let delta = inserted as isize - replaced.len() as isize;
+                let (lexed, synced) = self.lex(lexer, Some((first, replaced.end, delta)));
+
+                let kept = self.entries.split_off(synced.unwrap_or(self.entries.len()));
+                let damaged = first..first + lexed.len();
+                self.entries.truncate(first);
+                self.entries.extend(lexed);
+
+                let shift = |span: $crate::span::Span| {
+                    $crate::span::Span::new(span.start.wrapping_add_signed(delta), span.end.wrapping_add_signed(delta))
+                };
+                self.entries.extend(kept.into_iter().map(|(mut state, result)| {
+                    state.cursor = state.cursor.wrapping_add_signed(delta);
+                    let result = match result {
+                        Ok(token) => Ok($crate::span::Spanned::new(token.value, shift(token.span))),
+                        Err(err) => Err($crate::span::Spanned::new(err.value, shift(err.span))),
+                    };
+                    (state, result)
+                }));
+
+                damaged
+            }
+
+            fn lex(&self, mut lexer: Lexer<'_>, sync: Option<(usize, usize, isize)>) -> (Vec<Entry>, Option<usize>) {
+                let mut entries = Vec::new();
+                let mut old = sync.map_or(self.entries.len(), |(first, _, _)| first);
+
+                loop {
+                    let state = detach(lexer.snapshot());
+
+                    // Stop once the lexer is back in a state it had after the edit, past its last character for `#![indent]`
+                    if let Some((_, end, delta)) = sync {
+                        let moved = |state: &LexerState| state.cursor.wrapping_add_signed(delta);
+                        while self.entries.get(old).is_some_and(|(previous, _)| previous.cursor <= end || moved(previous) < state.cursor) {
+                            old += 1;
+                        }
+                        if let Some((previous, _)) = self.entries.get(old) {
+                            if moved(previous) == state.cursor
+                                && previous.queued.is_empty()
+                                && state.queued.is_empty()
+                                && (&previous.mode, &previous.stack, &previous.previous, &previous.indents, &previous.dedents, &previous.state)
+                                    == (&state.mode, &state.stack, &state.previous, &state.indents, &state.dedents, &state.state)
+                                && previous.finished == state.finished
+                            {
+                                return (entries, Some(old));
+                            }
+                        }
+                    }
+
+                    let Some(result) = lexer.advance() else {
+                        return (entries, None);
+                    };
+                    let result = match result {
+                        Ok((token, span)) => Ok($crate::span::Spanned::new(token, span)),
+                        Err(err) => {
+                            let message = match err.reason() {
+                                Some(reason) => format!("{}: {}", err.description(), reason),
+                                None => err.description(),
+                            };
+                            Err($crate::span::Spanned::new(message, err.span()))
+                        }
+                    };
+                    entries.push((state, result));
+                }
+            }
+        }
+
+        #[derive(Debug, Default)]
+        struct Stream {
+            buffer: String,
+            state: Option<LexerState<'static>>,
+            eof: bool,
+        }
+
+        impl Stream {
+            fn wants(&self) -> bool {
+                let cursor = self.state.as_ref().map_or(0, |state| state.cursor);
+                !self.eof && self.buffer.len() - cursor < MAX_LENGTH
+            }
+
+            fn step(&mut self) -> Option<Option<Result<Token, String>>> {
+                let mut lexer = Token::tokenize(&self.buffer);
+                lexer.partial = !self.eof;
+                if let Some(state) = self.state.take() {
+                    lexer.rewind(state);
+                }
+                let result = lexer.advance().map(|result| result.map(|(token, _)| token).map_err(|err| err.to_string()));
+
+                let mut state = detach(lexer.snapshot());
+
+                // Only keep the last consumed char, which `#![indent]` looks at
+                let kept = self.buffer[..state.cursor].chars().next_back().map_or(0, char::len_utf8);
+                self.buffer.drain(..state.cursor - kept);
+                state.cursor = kept;
+                self.state = Some(state);
+
+                if result.is_some() || self.eof {
+                    Some(result)
+                } else {
+                    None
+                }
+            }
+        }
+
+        $crate::__std! {
+            #[allow(dead_code)]
+            impl Token {
+                pub fn tokenize_reader<R: std::io::BufRead>(reader: R) -> ReaderLexer<R> {
+                    ReaderLexer { reader, stream: Stream::default() }
+                }
+            }
+
+            #[derive(Debug)]
+            pub struct ReaderLexer<R> {
+                reader: R,
+                stream: Stream,
+            }
+
+            impl<R: std::io::BufRead> Iterator for ReaderLexer<R> {
+                type Item = std::io::Result<Token>;
+
+                fn next(&mut self) -> Option<Self::Item> {
+                    loop {
+                        while self.stream.wants() {
+                            match self.reader.read_line(&mut self.stream.buffer) {
+                                Ok(read) => self.stream.eof = read == 0,
+                                Err(err) => return Some(Err(err)),
+                            }
+                        }
+                        if let Some(result) = self.stream.step() {
+                            return result.map(|result| {
+                                result.map_err(|err| std::io::Error::new(std::io::ErrorKind::InvalidData, err))
+                            });
+                        }
+                    }
+                }
+            }
+        }
+
+        $crate::__tokio! {
+            #[allow(dead_code)]
+            impl Token {
+                pub fn tokenize_async<R>(reader: R) -> AsyncLexer<R>
+                where
+                    R: $crate::__private::tokio::io::AsyncBufRead + Unpin,
+                {
+                    AsyncLexer { reader, bytes: Vec::new(), stream: Stream::default() }
+                }
+            }
+
+            #[derive(Debug)]
+            pub struct AsyncLexer<R> {
+                reader: R,
+                // Bytes read after the last whole char, which can be split between two reads
+                bytes: Vec<u8>,
+                stream: Stream,
+            }
+
+            impl<R> $crate::__private::futures_core::Stream for AsyncLexer<R>
+            where
+                R: $crate::__private::tokio::io::AsyncBufRead + Unpin,
+            {
+                type Item = std::io::Result<Token>;
+
+                fn poll_next(
+                    self: core::pin::Pin<&mut Self>,
+                    cx: &mut core::task::Context<'_>,
+                ) -> core::task::Poll<Option<Self::Item>> {
+                    use core::task::Poll;
+                    use $crate::__private::tokio::io::AsyncBufRead;
+
+                    let this = self.get_mut();
+                    let invalid = || std::io::Error::new(std::io::ErrorKind::InvalidData, "stream did not contain valid UTF-8");
+                    loop {
+                        while this.stream.wants() {
+                            let available = match core::pin::Pin::new(&mut this.reader).poll_fill_buf(cx) {
+                                Poll::Ready(Ok(available)) => available,
+                                Poll::Ready(Err(err)) => return Poll::Ready(Some(Err(err))),
+                                Poll::Pending => return Poll::Pending,
+                            };
+                            if available.is_empty() {
+                                this.stream.eof = true;
+                                if !this.bytes.is_empty() {
+                                    this.bytes.clear();
+                                    return Poll::Ready(Some(Err(invalid())));
+                                }
+                                break;
+                            }
+
+                            // Like `read_line`, at most a line is read at once
+                            let len = available.iter().position(|&byte| byte == b'\n').map_or(available.len(), |i| i + 1);
+                            this.bytes.extend_from_slice(&available[..len]);
+                            core::pin::Pin::new(&mut this.reader).consume(len);
+
+                            let valid = match core::str::from_utf8(&this.bytes) {
+                                Ok(text) => text.len(),
+                                Err(err) if err.error_len().is_none() => err.valid_up_to(),
+                                Err(_) => {
+                                    // Like `read_line`, the invalid line is dropped
+                                    this.bytes.clear();
+                                    return Poll::Ready(Some(Err(invalid())));
+                                }
+                            };
+                            let text = core::str::from_utf8(&this.bytes[..valid]).expect("the bytes are valid UTF-8 up to there");
+                            this.stream.buffer.push_str(text);
+                            this.bytes.drain(..valid);
+                        }
+                        if let Some(result) = this.stream.step() {
+                            return Poll::Ready(result.map(|result| {
+                                result.map_err(|err| std::io::Error::new(std::io::ErrorKind::InvalidData, err))
+                            }));
+                        }
+                    }
+                }
+            }
+        }
+
+        #[derive(Debug)]
+        pub struct CharsLexer<I> {
+            chars: I,
+            stream: Stream,
+        }
+
+        impl<I: Iterator<Item = char>> Iterator for CharsLexer<I> {
+            type Item = Result<Token, String>;
+
+            fn next(&mut self) -> Option<Self::Item> {
+                loop {
+                    while self.stream.wants() {
+                        match self.chars.next() {
+                            Some(c) => self.stream.buffer.push(c),
+                            None => self.stream.eof = true,
+                        }
+                    }
+                    if let Some(result) = self.stream.step() {
+                        return result;
+                    }
+                }
+            }
+        }
+
+        #[derive(Debug)]
+        pub struct ChunksLexer<I> {
+            chunks: I,
+            stream: Stream,
+        }
+
+        impl<I> Iterator for ChunksLexer<I>
+        where
+            I: Iterator,
+            I::Item: AsRef<str>,
+        {
+            type Item = Result<Token, String>;
+
+            fn next(&mut self) -> Option<Self::Item> {
+                loop {
+                    while self.stream.wants() {
+                        match self.chunks.next() {
+                            Some(chunk) => self.stream.buffer.push_str(chunk.as_ref()),
+                            None => self.stream.eof = true,
+                        }
+                    }
+                    if let Some(result) = self.stream.step() {
+                        return result;
+                    }
+                }
+            }
+        }
+    };
+    ([$($other:tt)*] $($option:tt)*) => {
+        $crate::__owned_lexer!($($option)*);
+    };
+}
+
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __mode_enum {
+    () => {
+        $crate::__mode_enum!([modes()]);
+    };
+    ([modes($($mode:ident),*)] $($option:tt)*) => {
+        #[allow(dead_code)]
+        #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+        pub enum Mode {
+            INITIAL,
+            $($mode),*
+        }
+
+        #[allow(dead_code)]
+        impl Mode {
+            pub fn name(&self) -> &'static str {
+                match self {
+                    Mode::INITIAL => "INITIAL",
+                    $(Mode::$mode => stringify!($mode)),*
+                }
+            }
+        }
+
+        #[allow(dead_code)]
+        const MODES: &[Mode] = &[Mode::INITIAL, $(Mode::$mode),*];
+
+        #[allow(dead_code)]
+        #[derive(Debug, Clone, Copy)]
+        enum Action {
+            Begin(Mode),
+            Push(Mode),
+            Pop,
+        }
+    };
+    ([$($other:tt)*] $($option:tt)*) => {
+        $crate::__mode_enum!($($option)*);
+    };
+}
+
+/**
+Macro to build your own plugin-based lexer.
+
+# Usage
+```ignore
+lexer!(
+    // Optional, defaults to `mod lexer;`
+    pub mod name;
+
+    // Optional lexer attributes
+    #![attribute]
+    ...
+
+    // Ordered by priority
+    #[optional attributes, ...]
+    NAME(optional types, ...) {
+        impl Pattern => |value: Match<'a>| -> Token (=> optional action),
+        ...,
+    },
+    // Shorthand for a set of keywords
+    #[optional attributes, ...]
+    NAME(optional type) keywords ["keyword", ...],
+    ...,
+);
+```
+
+Patterns are only built once, the first time a `Lexer` needs them, so expensive ones like regexes aren't compiled for every token.
+This means they have to be `Send + Sync + 'static`. \
+When there are several regex patterns, they're also gathered in a `RegexSet` that is run once per token,
+so regexes that can't match at the current position are skipped instead of being tried one by one. \
+Patterns also tell which bytes their matches can start with, see [`Pattern::first_bytes`](pattern::Pattern::first_bytes),
+and a table indexed by the byte under the cursor keeps the lexer from trying the ones that can't match there. \
+The combinators [`Then`](pattern::Then), [`Or`](pattern::Or), [`Not`](pattern::Not), [`Optional`](pattern::Optional),
+[`Repeat`](pattern::Repeat), [`Many1`](pattern::Many1), [`CharClass`](pattern::CharClass), [`Until`](pattern::Until),
+[`TakeUntil`](pattern::TakeUntil), [`Delimited`](pattern::Delimited), [`Balanced`](pattern::Balanced),
+[`TakeWhile`](pattern::TakeWhile) and [`NoneOf`](pattern::NoneOf) are in scope like `regex!`, with the [`classes`](pattern::classes) module,
+to compose patterns without a regex, like `Then(Optional('-'), Many1(CharClass::Digit))`, `Then("//", Until('\n'))`
+or `Then(classes::XidStart, Repeat::new(classes::XidContinue, ..))`.
+
+# Example
+Here is an example for a simple condition statement lexer.
+```
+# use plexer::lexer;
+#
+lexer!(
+    DELIMITER(char) {
+        '{' => |_| Token::DELIMITER('{'),
+        '}' => |_| Token::DELIMITER('}'),
+    },
+    KEYWORD(&'a str) {
+        "if" => |v| Token::KEYWORD(v.as_str()),
+        "else" => |v| Token::KEYWORD(v.as_str()),
+    },
+    IDENTIFIER(&'a str) {
+        regex!(r"[a-zA-Z_$][a-zA-Z_$0-9]*")
+            => |v| Token::IDENTIFIER(v.as_str()),
+    },
+    WHITESPACE {
+        [' ', '\n', '\t'] => |_| Token::WHITESPACE,
+    },
+);
+
+let mut lex = lexer::Token::tokenize("if test { one } else { two }");
+assert_eq!(lex.next(), Some(Ok(lexer::Token::KEYWORD("if"))));
+```
+
+# Guides
+The generated items are documented with the modules they work with:
+
+| Module               | Topics                                                                  |
+|----------------------|-------------------------------------------------------------------------|
+| [`span`]             | spans and positions of tokens, trivia and lossless lexing               |
+| [`build`]            | fallible builders, empty matches, multiple tokens and `#![state(T)]`    |
+| [`hook`]             | hooks and filtering out trivia                                          |
+| [`trace`]            | tracing the patterns tried and finding shadowed rules                   |
+| [`testing`]          | assertions, snapshots, statistics and fuzzing                           |
+| [`source`]           | errors in several files                                                 |
+| [`guide`]            | kinds, peeking, backtracking, seeking, reverse lexing, errors and serde |
+
+# Diagnostics
+Malformed rules are reported with the name of the offending rule,
+like a pattern without a builder, a rule without patterns, an unknown action or a rule defined twice. \
+A builder or a pattern of the wrong type is reported as such, along with the types that are expected.
+```compile_fail
+# use plexer::lexer;
+#
+lexer!(
+    WORD(&'a str) {
+        // error: pattern `regex!(r"[a-z]+")` of rule `WORD` has no builder, expected `pattern => builder`
+        regex!(r"[a-z]+"),
+    },
+);
+```
+
+Rules are expanded without recursing, there is no limit on their number,
+like for a lexer generated from a large [grammar file](build#grammar-files).
+```
+# use plexer::lexer;
+#
+// 200 rules `R0 { "R0" => |_| Token::R0 }` to `R199 { "R199" => |_| Token::R199 }`
+# macro_rules! rules {
+#     ($($name:ident)*) => {
+#         lexer!($($name { stringify!($name) => |_| Token::$name },)*);
+#     };
+# }
+# rules!(
+#     R0 R1 R2 R3 R4 R5 R6 R7 R8 R9 R10 R11 R12 R13 R14 R15 R16 R17 R18 R19
+#     R20 R21 R22 R23 R24 R25 R26 R27 R28 R29 R30 R31 R32 R33 R34 R35 R36 R37 R38 R39
+#     R40 R41 R42 R43 R44 R45 R46 R47 R48 R49 R50 R51 R52 R53 R54 R55 R56 R57 R58 R59
+#     R60 R61 R62 R63 R64 R65 R66 R67 R68 R69 R70 R71 R72 R73 R74 R75 R76 R77 R78 R79
+#     R80 R81 R82 R83 R84 R85 R86 R87 R88 R89 R90 R91 R92 R93 R94 R95 R96 R97 R98 R99
+#     R100 R101 R102 R103 R104 R105 R106 R107 R108 R109 R110 R111 R112 R113 R114 R115 R116 R117 R118 R119
+#     R120 R121 R122 R123 R124 R125 R126 R127 R128 R129 R130 R131 R132 R133 R134 R135 R136 R137 R138 R139
+#     R140 R141 R142 R143 R144 R145 R146 R147 R148 R149 R150 R151 R152 R153 R154 R155 R156 R157 R158 R159
+#     R160 R161 R162 R163 R164 R165 R166 R167 R168 R169 R170 R171 R172 R173 R174 R175 R176 R177 R178 R179
+#     R180 R181 R182 R183 R184 R185 R186 R187 R188 R189 R190 R191 R192 R193 R194 R195 R196 R197 R198 R199
+# );
+let tokens: Vec<_> = lexer::Token::tokenize("R7R199R0").map(|token| token.unwrap().kind()).collect();
+assert_eq!(tokens, [lexer::TokenKind::R7, lexer::TokenKind::R199, lexer::TokenKind::R0]);
+```
+
+# Keywords
+A rule can be given a list of keywords instead of patterns, the matched keyword is converted into the type of the variant if there is one.
+Keywords are matched in a single pass by a [`Literals`](pattern::Literals), even for large sets.
+Since the first declared rule wins between matches of the same length, keywords should come before identifiers.
+```
+# use plexer::lexer;
+#
+lexer!(
+    KEYWORD(&'a str) keywords ["if", "else", "while", "for"],
+    BOOLEAN keywords ["true", "false"],
+    IDENTIFIER(String) {
+        regex!(r"[a-z]+") => |v| Token::IDENTIFIER(v.to_string()),
+    },
+    #[skip]
+    WHITESPACE {
+        ' ' => |_| Token::WHITESPACE,
+    },
+);
+use lexer::Token;
+
+let tokens: Vec<_> = Token::tokenize("if iffy true").map(Result::unwrap).collect();
+assert_eq!(tokens, vec![Token::KEYWORD("if"), Token::IDENTIFIER("iffy".into()), Token::BOOLEAN]);
+```
+
+# Module
+By default the generated items are put in a private `mod lexer`,
+the name and visibility of the module can be chosen with a leading `mod` declaration.
+```
+# use plexer::lexer;
+#
+lexer!(
+    pub mod binary;
+
+    DIGIT(char) {
+        '0' => |_| Token::DIGIT('0'),
+        '1' => |_| Token::DIGIT('1'),
+    },
+);
+
+lexer!(
+    pub(crate) mod ternary;
+
+    DIGIT(u8) {
+        ['0', '1', '2'] => |v| Token::DIGIT(v.parse().unwrap()),
+    },
+);
+
+assert_eq!(binary::Token::tokenize("10").count(), 2);
+assert_eq!(ternary::Token::tokenize("2").next(), Some(Ok(ternary::Token::DIGIT(2))));
+```
+
+# Attributes
+Rules can be given attributes to change how the `Lexer` treats them.
+
+| Attribute          | Effect                                                  |
+|--------------------|---------------------------------------------------------|
+| `#[skip]`          | matches are consumed but never yielded by the `Lexer`   |
+| `#[trivia]`        | matches are yielded but dropped by `Lexer::significant` |
+| `#[mode(NAME...)]` | the rule is only tried in the given modes               |
+| `#[guard(F)]`      | the rule is only tried when `F(previous)` returns true  |
+
+```
+# use plexer::lexer;
+#
+lexer!(
+    NUMBER(usize) {
+        |s: &str| s.chars().all(|c| c.is_ascii_digit())
+            => |v| Token::NUMBER(v.parse().unwrap()),
+    },
+    #[skip]
+    WHITESPACE {
+        [' ', '\n', '\t'] => |_| Token::WHITESPACE,
+    },
+);
+
+let tokens: Vec<_> = lexer::Token::tokenize(" 1  2\n3 ").collect();
+assert_eq!(
+    tokens,
+    vec![Ok(lexer::Token::NUMBER(1)), Ok(lexer::Token::NUMBER(2)), Ok(lexer::Token::NUMBER(3))]
+);
+```
+
+A guard receives the `TokenKind` of the last yielded token, or `None` at the start,
+which tells apart tokens that look the same, like a regex literal and a division.
+```
+# use plexer::lexer;
+#
+lexer!(
+    NUMBER(usize) {
+        regex!(r"[0-9]+") => |v| v.parse().map(Token::NUMBER),
+    },
+    #[guard(|previous| previous == Some(TokenKind::NUMBER))]
+    DIVIDE {
+        '/' => |_| Token::DIVIDE,
+    },
+    #[guard(|previous| previous != Some(TokenKind::NUMBER))]
+    REGEX(&'a str) {
+        regex!(r"/[^/]+/") => |v| Token::REGEX(&v.as_str()[1..v.len() - 1]),
+    },
+    #[skip]
+    WHITESPACE {
+        ' ' => |_| Token::WHITESPACE,
+    },
+);
+use lexer::Token;
+
+let tokens: Vec<_> = Token::tokenize("/a/ 4 / 2").map(Result::unwrap).collect();
+assert_eq!(tokens, vec![Token::REGEX("a"), Token::NUMBER(4), Token::DIVIDE, Token::NUMBER(2)]);
+```
+
+# Options
+The `Lexer` itself can be given attributes, written as inner attributes `#![...]` before the rules.
+
+| Attribute             | Effect                                                                                            |
+|-----------------------|---------------------------------------------------------------------------------------------------|
+| `#![modes(NAME...)]`  | declares the modes of the `Lexer`, see [Modes](#modes)                                            |
+| `#![group_errors]`    | a run of unexpected characters is returned as a single error                                      |
+| `#![max_length(N)]`   | patterns only see the next `N` bytes, `1024` by default                                           |
+| `#![derive(...)]`     | adds derives to `Token`, which always derives `Debug, Clone, PartialEq`                           |
+| `#![eof]`             | the `Lexer` yields a final `Token::EOF` at the end of the haystack                                |
+| `#![error_tokens]`    | errors are yielded as a `Token::ERROR`, see [Errors](guide#errors)                                |
+| `#![indent]`          | the `Lexer` yields `Token::INDENT` and `Token::DEDENT` at line starts                             |
+| `#![fragment(N = P)]` | declares a regex fragment `N` that can be used in `regex!(...)`                                   |
+| `#![display]`         | implements `Display` for `Token`, which prints the fields of the token                            |
+| `#![owned]`           | `Token` has no lifetime, and a `TokenBuffer` is generated, see [Owned inputs](guide#owned-inputs) |
+| `#![bytes]`           | the `Lexer` lexes a `&[u8]` with [byte patterns](bytes::BytePattern)                              |
+| `#![state(T)]`        | builders are also given a `&mut T`, see [State](build#state)                                      |
+| `#![serde]`           | derives serde traits for `Token`, see [Saving](guide#saving)                                      |
+
+```
+# use plexer::lexer;
+# use plexer::span::Span;
+#
+lexer!(
+    #![group_errors]
+
+    NUMBER(usize) {
+        regex!(r"[0-9]+") => |v| v.parse().map(Token::NUMBER),
+    },
+);
+
+let mut lex = lexer::Token::tokenize("12abc34").spanned();
+lex.next();
+
+let err = lex.next().unwrap().unwrap_err();
+assert_eq!(err.span(), Span::new(2, 5));
+assert_eq!(err.to_string(), "unexpected characters 'abc' at line 1, column 3");
+assert!(lex.next().is_some_and(|res| res.is_ok()));
+```
+
+Extra derives make it possible to put tokens in a `HashMap` or to serialize them.
+```
+# use plexer::lexer;
+# use std::collections::HashSet;
+#
+lexer!(
+    #![derive(Eq, Hash)]
+
+    WORD(&'a str) {
+        regex!(r"[a-z]+") => |v| Token::WORD(v.as_str()),
+    },
+    #[skip]
+    WHITESPACE {
+        ' ' => |_| Token::WHITESPACE,
+    },
+);
+
+let words: HashSet<_> = lexer::Token::tokenize("to be or not to be").map(Result::unwrap).collect();
+assert_eq!(words.len(), 4);
+```
+
+With `#![display]`, a token is displayed as its fields separated by spaces, or as its name when it has none,
+which reads well in errors like "expected X, found Y". All field types must then implement `Display`.
+```
+# use plexer::lexer;
+#
+lexer!(
+    #![display]
+
+    OPERATOR(char) {
+        ['+', '-'] => |v| Token::OPERATOR(v.chars().next().unwrap()),
+    },
+    IDENTIFIER(&'a str) {
+        regex!(r"[a-z]+") => |v| Token::IDENTIFIER(v.as_str()),
+    },
+    WHITESPACE {
+        ' ' => |_| Token::WHITESPACE,
+    },
+);
+use lexer::Token;
+
+assert_eq!(Token::OPERATOR('+').to_string(), "+");
+assert_eq!(Token::IDENTIFIER("x").to_string(), "x");
+assert_eq!(Token::WHITESPACE.to_string(), "WHITESPACE");
+```
+
+With `#![owned]`, `Token` doesn't borrow the haystack and has no lifetime, so it can be stored anywhere.
+`Token::ERROR` then holds a `String`.
+```
+# use plexer::lexer;
+#
+lexer!(
+    #![owned]
+
+    WORD(String) {
+        regex!(r"[a-z]+") => |v| Token::WORD(v.to_string()),
+    },
+);
+
+fn words(input: &str) -> Vec<lexer::Token> {
+    lexer::Token::tokenize(input).map(Result::unwrap).collect()
+}
+
+assert_eq!(words("hello"), vec![lexer::Token::WORD(String::from("hello"))]);
+```
+
+With `#![bytes]`, `Token::tokenize` takes a `&[u8]` that doesn't have to be valid UTF-8,
+the rules use [`BytePattern`](bytes::BytePattern) and builders receive a [`ByteMatch`](bytes::ByteMatch). \
+`regex!` then builds a [`ByteRegex`](bytes::ByteRegex), where `(?-u)` allows matching any byte,
+and columns are counted in bytes. It can't be used with `#![owned]`.
+```
+# use plexer::lexer;
+#
+lexer!(
+    #![bytes]
+
+    HEADER(&'a [u8]) {
+        regex!(r"%PDF-[0-9]\.[0-9]") => |v| Token::HEADER(&v.as_bytes()[5..]),
+    },
+    BINARY(usize) {
+        regex!(r"(?-u)[\x80-\xFF]+") => |v| Token::BINARY(v.len()),
+    },
+    #[skip]
+    NEWLINE {
+        b'\n' => |_| Token::NEWLINE,
+    },
+);
+use lexer::Token;
+
+let tokens: Vec<_> = Token::tokenize(b"%PDF-1.7\n\xE2\xE3\xCF\xD3").map(Result::unwrap).collect();
+assert_eq!(tokens, vec![Token::HEADER(b"1.7"), Token::BINARY(4)]);
+
+let err = Token::tokenize(b"\x00").next().unwrap().unwrap_err();
+assert_eq!(err.to_string(), "unexpected character '\\x00' at line 1, column 1");
+```
+
+With `#![eof]`, an `EOF` variant is added to `Token` and `TokenKind`, and it is the last token yielded by the `Lexer`.
+```
+# use plexer::lexer;
+# use plexer::span::{Span, Spanned};
+#
+lexer!(
+    #![eof]
+
+    NUMBER(usize) {
+        regex!(r"[0-9]+") => |v| v.parse().map(Token::NUMBER),
+    },
+);
+use lexer::Token;
+
+let mut lex = Token::tokenize("42").spanned();
+assert_eq!(lex.next(), Some(Ok(Spanned::new(Token::NUMBER(42), Span::new(0, 2)))));
+assert_eq!(lex.next(), Some(Ok(Spanned::new(Token::EOF, Span::new(2, 2)))));
+assert_eq!(lex.next(), None);
+```
+
+Tokens can't be longer than the maximum length, which keeps closure patterns from scanning the whole input.
+It can be raised for long tokens like string literals, or set to `usize::MAX` to remove the limit
+at the cost of slower closure patterns.
+```
+# use plexer::lexer;
+#
+lexer!(
+    #![max_length(4)]
+
+    WORD(&'a str) {
+        regex!(r"[a-z]+") => |v| Token::WORD(v.as_str()),
+    },
+);
+
+let words: Vec<_> = lexer::Token::tokenize("abcdef").map(Result::unwrap).collect();
+assert_eq!(words, vec![lexer::Token::WORD("abcd"), lexer::Token::WORD("ef")]);
+```
+
+With `#![indent]`, the `Lexer` tracks the indentation of lines in `INITIAL` mode, like Python does.
+The leading spaces and tabs of a line are consumed, and yield an `INDENT` when they are deeper than the current block,
+or a `DEDENT` for each block they close. Blank lines are ignored, and the remaining blocks are closed at the end. \
+A tab counts as a single column, like a space, so a block should be indented with only one of them.
+`INDENT` and `DEDENT` are the previous token of the next guard, like any other token.
+```
+# use plexer::lexer;
+#
+lexer!(
+    #![indent]
+
+    WORD(&'a str) {
+        regex!(r"[a-z]+") => |v| Token::WORD(v.as_str()),
+    },
+    #[skip]
+    NEWLINE {
+        '\n' => |_| Token::NEWLINE,
+    },
+);
+use lexer::Token;
+
+let tokens: Vec<_> = Token::tokenize("if\n  then\n\n    deeper\nend").map(Result::unwrap).collect();
+assert_eq!(
+    tokens,
+    vec![
+        Token::WORD("if"),
+        Token::INDENT,
+        Token::WORD("then"),
+        Token::INDENT,
+        Token::WORD("deeper"),
+        Token::DEDENT,
+        Token::DEDENT,
+        Token::WORD("end"),
+    ]
+);
+assert_eq!(Token::tokenize("a\n  b").last(), Some(Ok(Token::DEDENT)));
+```
+
+A line that goes back to a column between two open blocks is an error, the blocks it went past are still closed.
+```
+# use plexer::lexer;
+#
+# lexer!(
+#     #![indent]
+#
+#     WORD(&'a str) {
+#         regex!(r"[a-z]+") => |v| Token::WORD(v.as_str()),
+#     },
+#     #[skip]
+#     NEWLINE {
+#         '\n' => |_| Token::NEWLINE,
+#     },
+# );
+use lexer::Token;
+
+let mut lex = Token::tokenize("a\n    b\n  c");
+assert_eq!(lex.nth(2), Some(Ok(Token::WORD("b"))));
+
+let err = lex.next().unwrap().unwrap_err();
+assert_eq!(err.reason(), Some("dedent doesn't match any outer indentation level"));
+assert_eq!(err.to_string(), "invalid token '  ' at line 3, column 1: dedent doesn't match any outer indentation level");
+
+let rest: Vec<_> = lex.map(Result::unwrap).collect();
+assert_eq!(rest, vec![Token::DEDENT, Token::WORD("c")]);
+```
+
+Fragments avoid repeating the same regex in several rules, like definitions in flex.
+They are declared with `#![fragment(NAME = "pattern", ...)]`, wrapped in a non-capturing group,
+and combined with other parts in `regex!(...)`.
+```
+# use plexer::lexer;
+#
+lexer!(
+    #![fragment(DIGITS = "[0-9]+", EXPONENT = "[eE][+-]?[0-9]+")]
+
+    FLOAT(f64) {
+        regex!(DIGITS, r"\.", DIGITS, EXPONENT, "?") => |v| v.parse().map(Token::FLOAT),
+        regex!(DIGITS, EXPONENT) => |v| v.parse().map(Token::FLOAT),
+    },
+    INTEGER(u64) {
+        regex!(DIGITS) => |v| v.parse().map(Token::INTEGER),
+    },
+);
+use lexer::Token;
+
+let mut lex = Token::tokenize("1.5e3");
+assert_eq!(lex.next(), Some(Ok(Token::FLOAT(1500.0))));
+assert_eq!(Token::tokenize("2e2").next(), Some(Ok(Token::FLOAT(200.0))));
+assert_eq!(Token::tokenize("42").next(), Some(Ok(Token::INTEGER(42))));
+```
+
+# Modes
+Like start conditions in flex, the `Lexer` can switch between modes to use different sets of rules. \
+Modes are declared with `#![modes(NAME, ...)]`, the `Lexer` starts in the implicit `INITIAL` mode.
+Rules without a `#[mode(...)]` attribute are only tried in `INITIAL`,
+and any arm can be followed by an action to change mode when it is the best match.
+
+| Action        | Effect                                                             |
+|---------------|--------------------------------------------------------------------|
+| `begin(NAME)` | switches to the mode                                               |
+| `push(NAME)`  | saves the current mode on a stack and switches to the mode         |
+| `pop`         | goes back to the last saved mode, or to `INITIAL` if there is none |
+
+```
+# use plexer::lexer;
+#
+lexer!(
+    #![modes(STRING)]
+
+    QUOTE {
+        '"' => |_| Token::QUOTE => begin(STRING),
+    },
+    WORD(&'a str) {
+        regex!(r"[a-z]+") => |v| Token::WORD(v.as_str()),
+    },
+    #[skip]
+    WHITESPACE {
+        ' ' => |_| Token::WHITESPACE,
+    },
+    #[mode(STRING)]
+    TEXT(&'a str) {
+        regex!(r#"[^"\\]+"#) => |v| Token::TEXT(v.as_str()),
+        regex!(r#"\\."#) => |v| Token::TEXT(&v.as_str()[1..]),
+        '"' => |_| Token::QUOTE => begin(INITIAL),
+    },
+);
+use lexer::Token;
+
+let tokens: Vec<_> = Token::tokenize(r#"say "hi \"you\"""#).map(Result::unwrap).collect();
+assert_eq!(
+    tokens,
+    vec![
+        Token::WORD("say"),
+        Token::QUOTE,
+        Token::TEXT("hi "),
+        Token::TEXT("\""),
+        Token::TEXT("you"),
+        Token::TEXT("\""),
+        Token::QUOTE,
+    ]
+);
+```
+
+Actions can also save modes on a stack, which makes nested constructs like string interpolation possible,
+`Lexer::mode_stack` gives the saved modes.
+```
+# use plexer::lexer;
+#
+lexer!(
+    #![modes(STRING, INTERPOLATION)]
+
+    #[mode(INITIAL, INTERPOLATION)]
+    QUOTE {
+        '"' => |_| Token::QUOTE => push(STRING),
+    },
+    #[mode(INTERPOLATION)]
+    CLOSE {
+        '}' => |_| Token::CLOSE => pop,
+    },
+    #[mode(STRING)]
+    TEXT(&'a str) {
+        regex!(r#"[^"$]+"#) => |v| Token::TEXT(v.as_str()),
+        '"' => |_| Token::QUOTE => pop,
+    },
+    #[mode(STRING)]
+    OPEN {
+        "${" => |_| Token::OPEN => push(INTERPOLATION),
+    },
+);
+use lexer::{Mode, Token};
+
+let mut lex = Token::tokenize(r#""a${"b"}c""#);
+assert_eq!(lex.nth(2), Some(Ok(Token::OPEN)));
+assert_eq!(lex.mode(), Mode::INTERPOLATION);
+assert_eq!(lex.mode_stack(), &[Mode::INITIAL, Mode::STRING]);
+
+let rest: Vec<_> = lex.map(Result::unwrap).collect();
+assert_eq!(
+    rest,
+    vec![Token::QUOTE, Token::TEXT("b"), Token::QUOTE, Token::CLOSE, Token::TEXT("c"), Token::QUOTE]
+);
+```
+
+When the haystack ends in another mode than `INITIAL`, like in an unterminated string,
+the `Lexer` yields a last error of kind `LexerErrorKind::Unterminated`, see `LexerError::kind`. \
+It has the span of the token that entered the mode, and the kinds of the rules that would have left it.
+```
+# use plexer::lexer;
+# use plexer::span::Span;
+#
+lexer!(
+    #![modes(COMMENT)]
+
+    OPEN {
+        "(*" => |_| Token::OPEN => push(COMMENT),
+    },
+    #[mode(COMMENT)]
+    TEXT {
+        regex!(r"[^*]+|\*") => |_| Token::TEXT,
+    },
+    #[mode(COMMENT)]
+    CLOSE {
+        "*)" => |_| Token::CLOSE => pop,
+    },
+);
+use lexer::{LexerErrorKind, Token, TokenKind};
+
+let mut lex = Token::tokenize("(* never closed");
+assert_eq!(lex.nth(1), Some(Ok(Token::TEXT)));
+assert_eq!(lex.size_hint(), (1, Some(1)));
+
+let err = lex.next().unwrap().unwrap_err();
+assert_eq!(
+    err.kind(),
+    &LexerErrorKind::Unterminated { open_span: Span::new(0, 2), expected: vec![TokenKind::CLOSE] }
+);
+assert_eq!(err.to_string(), "unexpected end of input, expected CLOSE at line 1, column 16");
+assert_eq!(lex.next(), None);
+```
+**/
+#[macro_export]
+macro_rules! lexer {
+    // Options and rules are read at once, a rule of keywords becomes a rule with a single pattern,
+    // rules are checked without recursing so that there is no limit on their number
+    (
+        @rules [$($head:tt)*]
+        $(#![$($option:tt)*])*
+        $(
+            $(#[$($attr:tt)*])*
+            $token:ident $(($($field:ty),+))? $(keywords [$($keyword:tt)*])? $({$($arm:tt)*})?
+        ),* $(,)?
+    ) => {
+        $($crate::__rule!($token $(keywords [$($keyword)*])? $({$($arm)*})?);)*
+
+        $crate::lexer!(
+            @expand $($head)*
+            $(#![$($option)*])*
+            $(
+                $(#[$($attr)*])*
+                $token $(($($field),+))? {
+                    $($crate::pattern::Literals::new([$($keyword)*]).unwrap() => |v| (&&Keyword(Token::$token)).keyword(v),)?
+                    $($($arm)*)?
+                }
+            ),*
+        );
+    };
+    (@rules $head:tt $($rest:tt)*) => {
+        compile_error!(concat!(
+            "expected rules like `NAME { pattern => builder, }`, found `", stringify!($($rest)*), "`"
+        ));
+    };
+    (
+        @expand
+        $vis:vis mod $name:ident;
+        $(#![$($option:tt)*])*
+        $(
+            $(#[$($attr:tt)*])*
+            $token:ident $(($($field: ty),+))? {
+                $($pattern:expr => $build:expr $(=> $action:ident $(($target:ident))?)?),+ $(,)?
+            }
+        ),* $(,)?
+    ) => {
+        $vis mod $name {
+            use $crate::__private::*;
+
+            $crate::__haystack!($([$($option)*])*);
+
+            const MAX_LENGTH: usize = $crate::__option!(max_length, 1024; $([$($option)*])*);
+            const GROUP_ERRORS: bool = $crate::__flag!(group_errors; $([$($option)*])*);
+
+            $crate::__mode_enum!($([$($option)*])*);
+            $crate::__fragments!($([$($option)*])*);
+            $crate::__state!($([$($option)*])*);
+
+            struct Arm {
+                pattern: Box<PatternOf>,
+                // Index in the prefilter if the pattern is a regex
+                regex: Option<usize>,
+                // Index in the rows of the dispatch table
+                index: usize,
+            }
+
+            struct Rules {
+                arms: Vec<Vec<Arm>>,
+                prefilter: Option<RegexSetOf>,
+                // For each byte, whether each pattern can match when it is under the cursor
+                dispatch: Vec<Vec<bool>>,
+            }
+
+            // Patterns are built once, the first time they are needed
+            fn rules() -> &'static Rules {
+                static RULES: OnceLock<Rules> = OnceLock::new();
+                RULES.get_or_init(|| {
+                    let patterns: Vec<Vec<Box<PatternOf>>> = vec![$(vec![$(Box::new($pattern) as Box<PatternOf>),+]),*];
+
+                    let mut regexes = Vec::new();
+                    let mut first_bytes = Vec::new();
+                    let arms = patterns
+                        .into_iter()
+                        .map(|arms| {
+                            arms.into_iter()
+                                .map(|pattern| {
+                                    let regex = pattern.as_regex().map(|regex| {
+                                        regexes.push(format!("^(?:{})", regex));
+                                        regexes.len() - 1
+                                    });
+                                    first_bytes.push(pattern.first_bytes());
+                                    Arm { pattern, regex, index: first_bytes.len() - 1 }
+                                })
+                                .collect()
+                        })
+                        .collect();
+
+                    // A single regex is as fast to try directly
+                    let prefilter = if regexes.len() > 1 { RegexSetOf::new(regexes).ok() } else { None };
+                    let dispatch = (0..=u8::MAX)
+                        .map(|byte| first_bytes.iter().map(|set| set.map_or(true, |set| set.contains(byte))).collect())
+                        .collect();
+                    Rules { arms, prefilter, dispatch }
+                })
+            }
+
+            // Builders are given the state with `#![state(...)]`
+            $crate::__build!($([$($option)*])*);
+
+            #[allow(dead_code)]
+            fn guard(guard: impl FnOnce(Option<TokenKind>) -> bool, previous: Option<TokenKind>) -> bool {
+                guard(previous)
+            }
+
+            #[allow(dead_code)]
+            impl TokenKind {
+                pub fn is_trivia(&self) -> bool {
+                    // Tokens added by options, like `EOF`, are never trivia
+                    #[allow(unreachable_patterns)]
+                    match self {
+                        $(TokenKind::$token => $crate::__trivia!($([$($attr)*])*),)*
+                        _ => false,
+                    }
+                }
+            }
+
+            $crate::__token_enum!(
+                [#[derive(Debug, Clone, PartialEq)]]
+                [$([$($option)*])*]
+                [$($token)*]
+                []
+                []
+                $($token$(($($field),+))?,)*
+            );
+            $crate::__display!([$([$($option)*])*] $($token$(($($field),+))?,)*);
+
+            impl<'a> $crate::build::Outcome<TokenOf<'a>> for TokenOf<'a> {
+                fn outcome(self) -> Result<TokenOf<'a>, Option<String>> {
+                    Ok(self)
+                }
+            }
+
+            #[allow(dead_code)]
+            impl<'a> TokenOf<'a> {
+                pub fn tokenize(haystack: &'a Haystack) -> Lexer<'a> {
+                    Lexer {
+                        haystack,
+                        arena: None,
+                        cursor: 0,
+                        position: $crate::span::Position::default(),
+                        mode: Mode::INITIAL,
+                        stack: Vec::new(),
+                        opened: Vec::new(),
+                        previous: None,
+                        indents: Vec::new(),
+                        dedents: 0,
+                        queued: VecDeque::new(),
+                        finished: false,
+                        partial: false,
+                        peeked: VecDeque::new(),
+                        trivia: None,
+                        kinds: None,
+                        hooks: Hooks::default(),
+                        aborted: false,
+                        state: State::default(),
+                        trace: None,
+                    }
+                }
+
+                pub fn tokenize_with(haystack: &'a Haystack, state: State) -> Lexer<'a> {
+                    Lexer { state, ..Self::tokenize(haystack) }
+                }
+
+                pub fn shadowed_rules() -> Vec<$crate::trace::Shadowed<TokenKind>> {
+                    let mut shadowed = Vec::new();
+                    let mut rules = rules().arms.iter();
+                    $(let arms = rules.next().expect("one pattern list per rule");
+                    let modes = MODES.iter().filter(|&&mode| $crate::__in_mode!(mode; $([$($attr)*])*));
+                    for (index, arm) in arms.iter().enumerate() {
+                        let literals = arm.pattern.literals().unwrap_or_default();
+                        // Each literal is lexed on its own, the arm is shadowed if it matches but an earlier rule wins every time
+                        let mut by = None;
+                        let always = !literals.is_empty() && literals.iter().all(|literal| modes.clone().all(|&mode| {
+                            let mut lexer = Token::tokenize(&literal[..]).with_trace();
+                            lexer.mode = mode;
+                            let _ = lexer.traced_match(false);
+                            let trace = lexer.take_trace();
+                            let matched = trace.iter().any(|attempt| {
+                                attempt.kind == TokenKind::$token && attempt.arm == index && attempt.len == Some(literal.len())
+                            });
+                            let winner = trace.iter().find(|attempt| attempt.verdict == $crate::trace::Verdict::Won);
+                            match winner {
+                                Some(winner) if matched && winner.kind != TokenKind::$token => {
+                                    by.get_or_insert(winner.kind);
+                                    true
+                                }
+                                _ => false,
+                            }
+                        }));
+                        if let (true, Some(by)) = (always, by) {
+                            shadowed.push($crate::trace::Shadowed { kind: TokenKind::$token, arm: index, by });
+                        }
+                    })*
+                    shadowed
+                }
+
+                pub fn patterns() -> Vec<$crate::summary::RulePattern<TokenKind>> {
+                    let mut patterns = Vec::new();
+                    let mut rules = rules().arms.iter().enumerate();
+                    $(let (priority, arms) = rules.next().expect("one pattern list per rule");
+                    let modes = MODES.iter().filter(|&&mode| $crate::__in_mode!(mode; $([$($attr)*])*));
+                    for (index, arm) in arms.iter().enumerate() {
+                        let literals = arm.pattern.literals().and_then(|literals| {
+                            $crate::summary::utf8_literals(literals.iter().map(|literal| $crate::bytes::Haystack::as_bytes(&literal[..])))
+                        });
+                        patterns.push($crate::summary::RulePattern {
+                            kind: TokenKind::$token,
+                            priority,
+                            arm: index,
+                            regex: arm.pattern.as_regex().map(String::from),
+                            literals,
+                            modes: modes.clone().map(Mode::name).collect(),
+                        });
+                    })*
+                    patterns
+                }
+
+                pub fn assert_invariants(haystack: &'a Haystack) {
+                    let len = haystack.len();
+                    let mut lexer = Self::tokenize(haystack);
+                    let (mut start, mut cursor) = (0, 0);
+                    while let Some(result) = lexer.lex() {
+                        let span = match &result {
+                            Ok((_, span)) => *span,
+                            Err(err) => err.span(),
+                        };
+                        assert!(span.end <= len, "span {} is out of bounds 0..{}", span, len);
+                        assert!(
+                            $crate::bytes::Haystack::is_boundary(haystack, span.start) && $crate::bytes::Haystack::is_boundary(haystack, span.end),
+                            "span {} splits a character",
+                            span
+                        );
+                        assert!(span.start >= start, "span {} starts before the previous one at {}", span, start);
+                        assert!(lexer.cursor >= cursor, "the cursor went back from {} to {}", cursor, lexer.cursor);
+                        assert!(span.end <= lexer.cursor, "span {} ends after the cursor at {}", span, lexer.cursor);
+                        (start, cursor) = (span.start, lexer.cursor);
+                    }
+                    assert_eq!(lexer.cursor, len, "the lexer stopped before the end of the input");
+                    assert!(
+                        Self::tokenize(haystack).lossless().reconstruct()[..] == *haystack,
+                        "the lexemes don't give back the input"
+                    );
+                }
+
+                pub fn tokenize_in(haystack: &'a Haystack, arena: &'a $crate::arena::Arena) -> Lexer<'a> {
+                    Lexer { arena: Some(arena), ..Self::tokenize(haystack) }
+                }
+
+                pub fn tokenize_range(haystack: &'a Haystack, range: core::ops::Range<usize>) -> Lexer<'a> {
+                    let mut lexer = Self::tokenize(&haystack[..range.end]);
+                    lexer.seek(range.start);
+                    lexer
+                }
+
+                pub fn tokenize_rev(haystack: &'a Haystack) -> RevLexer<'a> {
+                    let lexer = Lexer { cursor: haystack.len(), ..Self::tokenize(haystack) };
+                    RevLexer { lexer, queued: Vec::new() }
+                }
+
+                pub fn tokenize_all(haystack: &'a Haystack) -> Result<Vec<TokenOf<'a>>, Vec<LexerError<'a>>> {
+                    let mut tokens = Vec::new();
+                    Self::tokenize_into(haystack, &mut tokens).map(|()| tokens)
+                }
+
+                pub fn tokenize_into(haystack: &'a Haystack, tokens: &mut Vec<TokenOf<'a>>) -> Result<(), Vec<LexerError<'a>>> {
+                    let mut errors = Vec::new();
+
+                    for result in Self::tokenize(haystack) {
+                        match result {
+                            Ok(token) => tokens.push(token),
+                            Err(err) => errors.push(err),
+                        }
+                    }
+
+                    if errors.is_empty() {
+                        Ok(())
+                    } else {
+                        Err(errors)
+                    }
+                }
+            }
+
+            $crate::__mmap! {
+                #[allow(dead_code)]
+                impl<'a> TokenOf<'a> {
+                    pub fn tokenize_mmap(file: &'a $crate::mmap::MappedFile) -> std::io::Result<Lexer<'a>> {
+                        file.haystack().map(Self::tokenize)
+                    }
+                }
+            }
+
+            $crate::__proptest! {
+                #[allow(dead_code)]
+                impl<'a> TokenOf<'a> {
+                    pub fn strategy() -> $crate::__private::proptest::strategy::BoxedStrategy<(TokenKind, String)> {
+                        use $crate::__private::proptest::strategy::Strategy;
+
+                        let patterns: Vec<_> = Token::patterns().into_iter().filter(|pattern| !pattern.kind.is_trivia()).collect();
+                        let kinds: Vec<TokenKind> = patterns.iter().map(|pattern| pattern.kind).collect();
+                        $crate::strategy::token(&patterns)
+                            .prop_filter("the lexeme doesn't lex back as its kind", move |(kind, lexeme)| {
+                                let haystack: &Haystack = lexeme.as_ref();
+                                let mut lexer = Token::tokenize(haystack);
+                                // Tokens added by options, like `EOF`, can follow
+                                let alone = matches!(
+                                    lexer.lex(),
+                                    Some(Ok((token, span))) if token.kind() == *kind && span.end == haystack.len()
+                                ) && lexer.by_ref().all(|result| matches!(result, Ok(token) if !kinds.contains(&token.kind())));
+                                alone && lexer.mode == Mode::INITIAL && lexer.stack.is_empty()
+                            })
+                            .boxed()
+                    }
+
+                    pub fn sequence_strategy(
+                        size: impl Into<$crate::__private::proptest::collection::SizeRange>,
+                        separator: &'static str,
+                    ) -> $crate::__private::proptest::strategy::BoxedStrategy<(Vec<TokenKind>, String)> {
+                        $crate::strategy::sequence(Token::strategy(), size, separator)
+                    }
+                }
+            }
+
+            $crate::__serde! {
+                @derive
+                #[derive(Debug, Clone, PartialEq)]
+                pub enum LexerErrorKind {
+                    Unexpected,
+                    Rejected(Option<String>),
+                    Empty(TokenKind),
+                    Unterminated {
+                        open_span: $crate::span::Span,
+                        expected: Vec<TokenKind>,
+                    },
+                }
+            }
+
+            #[derive(Debug, Clone, PartialEq)]
+            pub struct LexerError<'a> {
+                haystack: &'a Haystack,
+                cursor: usize,
+                end: usize,
+                position: $crate::span::Position,
+                kind: LexerErrorKind,
+                // Kinds of the rules that were tried where the error is
+                expected: Vec<TokenKind>,
+            }
+
+            #[allow(dead_code)]
+            impl<'a> LexerError<'a> {
+                fn new(haystack: &'a Haystack, cursor: usize, end: usize, position: $crate::span::Position) -> Self {
+                    Self { haystack, cursor, end, position, kind: LexerErrorKind::Unexpected, expected: Vec::new() }
+                }
+
+                fn rejected(
+                    haystack: &'a Haystack,
+                    cursor: usize,
+                    end: usize,
+                    position: $crate::span::Position,
+                    reason: Option<String>,
+                ) -> Self {
+                    Self { haystack, cursor, end, position, kind: LexerErrorKind::Rejected(reason), expected: Vec::new() }
+                }
+
+                fn unterminated(
+                    haystack: &'a Haystack,
+                    cursor: usize,
+                    position: $crate::span::Position,
+                    open_span: $crate::span::Span,
+                    expected: Vec<TokenKind>,
+                ) -> Self {
+                    let kind = LexerErrorKind::Unterminated { open_span, expected: expected.clone() };
+                    Self { haystack, cursor, end: cursor, position, kind, expected }
+                }
+
+                fn with_empty(self, empty: Option<TokenKind>) -> Self {
+                    match empty {
+                        Some(kind) => Self { kind: LexerErrorKind::Empty(kind), ..self },
+                        None => self,
+                    }
+                }
+
+                fn with_expected(self, expected: Vec<TokenKind>) -> Self {
+                    Self { expected, ..self }
+                }
+
+                pub fn kind(&self) -> &LexerErrorKind {
+                    &self.kind
+                }
+
+                pub fn expected(&self) -> &[TokenKind] {
+                    &self.expected
+                }
+
+                pub fn expectation(&self) -> Option<String> {
+                    expectation(&self.expected)
+                }
+
+                pub fn span(&self) -> $crate::span::Span {
+                    $crate::span::Span::new(self.cursor, self.end)
+                }
+
+                pub fn empty_rule(&self) -> Option<TokenKind> {
+                    self.kind.empty_rule()
+                }
+
+                pub fn position(&self) -> $crate::span::Position {
+                    self.position
+                }
+
+                pub fn reason(&self) -> Option<&str> {
+                    self.kind.reason()
+                }
+
+                pub fn render(&self) -> String {
+                    $crate::bytes::Haystack::render(self.haystack, self.span(), &self.message())
+                }
+
+                pub fn into_owned(self) -> OwnedLexerError {
+                    OwnedLexerError {
+                        description: self.description(),
+                        rendered: self.render(),
+                        span: self.span(),
+                        position: self.position,
+                        kind: self.kind,
+                        expected: self.expected,
+                    }
+                }
+
+                pub fn render_in(&self, sources: &$crate::source::SourceMap, file: $crate::source::FileId) -> Option<String> {
+                    sources.render($crate::source::FileSpan::new(file, self.span()), &self.message())
+                }
+
+                fn message(&self) -> String {
+                    match self.reason() {
+                        Some(reason) => format!("{}: {}", self.description(), reason),
+                        None => self.description(),
+                    }
+                }
+
+                fn description(&self) -> String {
+                    let value = &self.haystack[self.cursor..self.end];
+                    let escaped = $crate::bytes::Haystack::escape(value);
+                    match &self.kind {
+                        LexerErrorKind::Unexpected if $crate::bytes::Haystack::units(value) > 1 => format!("unexpected characters '{}'", escaped),
+                        LexerErrorKind::Unexpected => format!("unexpected character '{}'", escaped),
+                        LexerErrorKind::Rejected(_) => format!("invalid token '{}'", escaped),
+                        LexerErrorKind::Empty(kind) => format!("rule {} matched the empty string at '{}'", kind.name(), escaped),
+                        LexerErrorKind::Unterminated { .. } => match self.expectation() {
+                            Some(expectation) => format!("unexpected end of input, {}", expectation),
+                            None => String::from("unexpected end of input"),
+                        },
+                    }
+                }
+            }
+
+            impl<'a> core::fmt::Display for LexerError<'a> {
+                fn fmt(&self, f: &mut core::fmt::Formatter) -> core::fmt::Result {
+                    if f.alternate() {
+                        write!(f, "{}", self.render())
+                    } else {
+                        write!(f, "{} at {}", self.description(), self.position)?;
+                        match self.reason() {
+                            Some(reason) => write!(f, ": {}", reason),
+                            None => Ok(()),
+                        }
+                    }
+                }
+            }
+
+            impl<'a> core::error::Error for LexerError<'a> {}
+
+            $crate::__serde! {
+                @derive
+                #[derive(Debug, Clone, PartialEq)]
+                pub struct OwnedLexerError {
+                    // The haystack isn't kept, only what the messages need from it
+                    description: String,
+                    rendered: String,
+                    span: $crate::span::Span,
+                    position: $crate::span::Position,
+                    kind: LexerErrorKind,
+                    expected: Vec<TokenKind>,
+                }
+            }
+
+            #[allow(dead_code)]
+            impl OwnedLexerError {
+                pub fn kind(&self) -> &LexerErrorKind {
+                    &self.kind
+                }
+
+                pub fn expected(&self) -> &[TokenKind] {
+                    &self.expected
+                }
+
+                pub fn expectation(&self) -> Option<String> {
+                    expectation(&self.expected)
+                }
+
+                pub fn span(&self) -> $crate::span::Span {
+                    self.span
+                }
+
+                pub fn empty_rule(&self) -> Option<TokenKind> {
+                    self.kind.empty_rule()
+                }
+
+                pub fn position(&self) -> $crate::span::Position {
+                    self.position
+                }
+
+                pub fn reason(&self) -> Option<&str> {
+                    self.kind.reason()
+                }
+
+                pub fn render(&self) -> String {
+                    self.rendered.clone()
+                }
+
+                pub fn render_in(&self, sources: &$crate::source::SourceMap, file: $crate::source::FileId) -> Option<String> {
+                    sources.render($crate::source::FileSpan::new(file, self.span), &self.message())
+                }
+
+                fn message(&self) -> String {
+                    match self.reason() {
+                        Some(reason) => format!("{}: {}", self.description, reason),
+                        None => self.description.clone(),
+                    }
+                }
+            }
+
+            impl core::fmt::Display for OwnedLexerError {
+                fn fmt(&self, f: &mut core::fmt::Formatter) -> core::fmt::Result {
+                    if f.alternate() {
+                        write!(f, "{}", self.rendered)
+                    } else {
+                        write!(f, "{} at {}", self.description, self.position)?;
+                        match self.reason() {
+                            Some(reason) => write!(f, ": {}", reason),
+                            None => Ok(()),
+                        }
+                    }
+                }
+            }
+
+            impl core::error::Error for OwnedLexerError {}
+
+            #[allow(dead_code)]
+            impl LexerErrorKind {
+                pub fn code(&self) -> &'static str {
+                    match self {
+                        LexerErrorKind::Unexpected => "plexer::unexpected",
+                        LexerErrorKind::Rejected(_) => "plexer::rejected",
+                        LexerErrorKind::Empty(_) => "plexer::empty",
+                        LexerErrorKind::Unterminated { .. } => "plexer::unterminated",
+                    }
+                }
+
+                // The span of the error itself comes first
+                pub fn labels(&self, span: $crate::span::Span) -> Vec<($crate::span::Span, String)> {
+                    match self {
+                        LexerErrorKind::Unexpected => vec![(span, String::from("no rule matches here"))],
+                        LexerErrorKind::Rejected(reason) => {
+                            vec![(span, String::from(reason.as_deref().unwrap_or("rejected by its builder")))]
+                        }
+                        LexerErrorKind::Empty(kind) => {
+                            vec![(span, format!("rule {} matched the empty string here", kind.name()))]
+                        }
+                        LexerErrorKind::Unterminated { open_span, .. } => vec![
+                            (span, String::from("input ends here")),
+                            (*open_span, String::from("opened here")),
+                        ],
+                    }
+                }
+
+                fn reason(&self) -> Option<&str> {
+                    match self {
+                        LexerErrorKind::Rejected(reason) => reason.as_deref(),
+                        _ => None,
+                    }
+                }
+
+                fn empty_rule(&self) -> Option<TokenKind> {
+                    match self {
+                        LexerErrorKind::Empty(kind) => Some(*kind),
+                        _ => None,
+                    }
+                }
+            }
+
+            $crate::__miette! {
+                impl LexerErrorKind {
+                    fn labeled_spans(&self, span: $crate::span::Span) -> Vec<$crate::__private::miette::LabeledSpan> {
+                        self.labels(span)
+                            .into_iter()
+                            .map(|(span, label)| $crate::__private::miette::LabeledSpan::at(span, label))
+                            .collect()
+                    }
+                }
+
+                impl<'a> $crate::__private::miette::Diagnostic for LexerError<'a> {
+                    fn code<'b>(&'b self) -> Option<Box<dyn core::fmt::Display + 'b>> {
+                        Some(Box::new(self.kind.code()))
+                    }
+
+                    fn help<'b>(&'b self) -> Option<Box<dyn core::fmt::Display + 'b>> {
+                        // An unterminated error already tells what it expected
+                        match self.kind {
+                            LexerErrorKind::Unterminated { .. } => None,
+                            _ => self.expectation().map(|help| Box::new(help) as Box<dyn core::fmt::Display>),
+                        }
+                    }
+
+                    fn source_code(&self) -> Option<&dyn $crate::__private::miette::SourceCode> {
+                        Some(&self.haystack)
+                    }
+
+                    fn labels(&self) -> Option<Box<dyn Iterator<Item = $crate::__private::miette::LabeledSpan> + '_>> {
+                        Some(Box::new(self.kind.labeled_spans(self.span()).into_iter()))
+                    }
+                }
+
+                // The haystack isn't kept, `miette::Report::with_source_code` can give it back
+                impl $crate::__private::miette::Diagnostic for OwnedLexerError {
+                    fn code<'b>(&'b self) -> Option<Box<dyn core::fmt::Display + 'b>> {
+                        Some(Box::new(self.kind.code()))
+                    }
+
+                    fn help<'b>(&'b self) -> Option<Box<dyn core::fmt::Display + 'b>> {
+                        // An unterminated error already tells what it expected
+                        match self.kind {
+                            LexerErrorKind::Unterminated { .. } => None,
+                            _ => self.expectation().map(|help| Box::new(help) as Box<dyn core::fmt::Display>),
+                        }
+                    }
+
+                    fn labels(&self) -> Option<Box<dyn Iterator<Item = $crate::__private::miette::LabeledSpan> + '_>> {
+                        Some(Box::new(self.kind.labeled_spans(self.span).into_iter()))
+                    }
+                }
+            }
+
+            $crate::__codespan! {
+                #[allow(dead_code)]
+                impl<'a> LexerError<'a> {
+                    pub fn to_diagnostic<F: Copy>(&self, file: F) -> $crate::__private::codespan_reporting::diagnostic::Diagnostic<F> {
+                        diagnostic(self.message(), &self.kind, self.span(), &self.expected, file)
+                    }
+                }
+
+                #[allow(dead_code)]
+                impl OwnedLexerError {
+                    pub fn to_diagnostic<F: Copy>(&self, file: F) -> $crate::__private::codespan_reporting::diagnostic::Diagnostic<F> {
+                        diagnostic(self.message(), &self.kind, self.span, &self.expected, file)
+                    }
+                }
+
+                // The first label is the primary one, on the error itself
+                fn diagnostic<F: Copy>(
+                    message: String,
+                    kind: &LexerErrorKind,
+                    span: $crate::span::Span,
+                    expected: &[TokenKind],
+                    file: F,
+                ) -> $crate::__private::codespan_reporting::diagnostic::Diagnostic<F> {
+                    use $crate::__private::codespan_reporting::diagnostic::{Diagnostic, Label};
+
+                    let labels = kind.labels(span).into_iter().enumerate().map(|(i, (span, text))| {
+                        let label = if i == 0 { Label::primary(file, span) } else { Label::secondary(file, span) };
+                        label.with_message(text)
+                    });
+                    let diagnostic = Diagnostic::error().with_message(message).with_code(kind.code()).with_labels_iter(labels);
+                    // An unterminated error already tells what it expected
+                    match (kind, expectation(expected)) {
+                        (LexerErrorKind::Unterminated { .. }, _) | (_, None) => diagnostic,
+                        (_, Some(help)) => diagnostic.with_note(help),
+                    }
+                }
+            }
+
+            // Puts the kinds an error expected in words, like "expected NUMBER, IDENTIFIER or OPERATOR"
+            fn expectation(expected: &[TokenKind]) -> Option<String> {
+                let (last, others) = expected.split_last()?;
+                let others: Vec<&str> = others.iter().map(TokenKind::name).collect();
+                if others.is_empty() {
+                    Some(format!("expected {}", last.name()))
+                } else {
+                    Some(format!("expected {} or {}", others.join(", "), last.name()))
+                }
+            }
+
+            pub type LexerResult<'a, T> = Result<T, LexerError<'a>>;
+
+            #[derive(Debug, Clone, Default, PartialEq, Eq)]
+            pub struct LexerStats {
+                counts: BTreeMap<TokenKind, usize>,
+                errors: usize,
+                bytes: usize,
+            }
+
+            #[allow(dead_code)]
+            impl LexerStats {
+                pub fn count(&self, kind: TokenKind) -> usize {
+                    self.counts.get(&kind).copied().unwrap_or(0)
+                }
+
+                pub fn counts(&self) -> impl Iterator<Item = (TokenKind, usize)> + '_ {
+                    self.counts.iter().map(|(&kind, &count)| (kind, count))
+                }
+
+                pub fn tokens(&self) -> usize {
+                    self.counts.values().sum()
+                }
+
+                pub fn errors(&self) -> usize {
+                    self.errors
+                }
+
+                pub fn bytes(&self) -> usize {
+                    self.bytes
+                }
+            }
+
+            #[derive(Debug, Clone)]
+            pub struct Lexer<'a> {
+                haystack: &'a Haystack,
+                arena: Option<&'a $crate::arena::Arena>,
+                cursor: usize,
+                position: $crate::span::Position,
+                mode: Mode,
+                stack: Vec<Mode>,
+                // Span of the token that entered each mode of the stack, and the current one, that isn't `INITIAL`
+                opened: Vec<$crate::span::Span>,
+                previous: Option<TokenKind>,
+                indents: Vec<usize>,
+                dedents: usize,
+                queued: VecDeque<(TokenOf<'a>, $crate::span::Span)>,
+                finished: bool,
+                partial: bool,
+                peeked: VecDeque<(LexerState<'a>, LexerResult<'a, (TokenOf<'a>, $crate::span::Span)>)>,
+                // Skipped tokens are kept here for a `TriviaLexer`
+                trivia: Option<Vec<(TokenOf<'a>, $crate::span::Span)>>,
+                // Only tokens of these kinds are yielded, if set
+                kinds: Option<Vec<TokenKind>>,
+                hooks: Hooks,
+                // Set when a hook stops the lexer, until it is rewound
+                aborted: bool,
+                state: State,
+                // Every pattern tried is recorded here when tracing
+                trace: Option<Vec<$crate::trace::Attempt<TokenKind>>>,
+            }
+
+            // Hooks don't borrow anything, so they don't change the variance of the lexer or when it can be dropped
+            // They are `Send` and behind a `Mutex`, so that the lexer stays `Send` and `Sync`
+            type TokenHook = Arc<Mutex<dyn for<'t> FnMut(&TokenOf<'t>, $crate::span::Span, &'t Haystack) -> ControlFlow<()> + Send>>;
+            type ErrorHook = Arc<Mutex<dyn for<'t> FnMut(&LexerError<'t>, $crate::span::Span, &'t Haystack) -> ControlFlow<()> + Send>>;
+
+            #[derive(Clone, Default)]
+            struct Hooks {
+                on_token: Option<TokenHook>,
+                on_error: Option<ErrorHook>,
+            }
+
+            impl Hooks {
+                fn call<'a>(&self, result: &LexerResult<'a, (TokenOf<'a>, $crate::span::Span)>, haystack: &'a Haystack) -> ControlFlow<()> {
+                    match result {
+                        Ok((token, span)) => match &self.on_token {
+                            Some(hook) => (lock(hook))(token, *span, &haystack[span.as_range()]),
+                            None => ControlFlow::Continue(()),
+                        },
+                        Err(err) => match &self.on_error {
+                            Some(hook) => (lock(hook))(err, err.span(), &haystack[err.span().as_range()]),
+                            None => ControlFlow::Continue(()),
+                        },
+                    }
+                }
+            }
+
+            impl core::fmt::Debug for Hooks {
+                fn fmt(&self, f: &mut core::fmt::Formatter) -> core::fmt::Result {
+                    f.debug_struct("Hooks")
+                        .field("on_token", &self.on_token.is_some())
+                        .field("on_error", &self.on_error.is_some())
+                        .finish()
+                }
+            }
+
+            #[derive(Debug, Clone, PartialEq)]
+            pub struct LexerState<'a> {
+                cursor: usize,
+                position: $crate::span::Position,
+                mode: Mode,
+                stack: Vec<Mode>,
+                opened: Vec<$crate::span::Span>,
+                previous: Option<TokenKind>,
+                indents: Vec<usize>,
+                dedents: usize,
+                queued: VecDeque<(TokenOf<'a>, $crate::span::Span)>,
+                finished: bool,
+                state: State,
+                haystack: core::marker::PhantomData<&'a str>,
+            }
+
+            #[allow(dead_code)]
+            impl<'a> Lexer<'a> {
+                pub fn position(&self) -> $crate::span::Position {
+                    self.peeked.front().map_or(self.position, |(state, _)| state.position)
+                }
+
+                pub fn cursor(&self) -> usize {
+                    self.peeked.front().map_or(self.cursor, |(state, _)| state.cursor)
+                }
+
+                pub fn remaining(&self) -> &'a Haystack {
+                    &self.haystack[self.cursor()..]
+                }
+
+                pub fn seek(&mut self, cursor: usize) {
+                    assert!(
+                        cursor <= self.haystack.len() && $crate::bytes::Haystack::is_boundary(self.haystack, cursor),
+                        "can't seek to {} in a haystack of length {}", cursor, self.haystack.len(),
+                    );
+                    let state = self.checkpoint();
+                    self.rewind(state);
+
+                    // The position is counted again from the start when seeking backwards
+                    if cursor < self.cursor {
+                        self.cursor = 0;
+                        self.position = $crate::span::Position::default();
+                    }
+                    $crate::bytes::Haystack::advance(&self.haystack[self.cursor..cursor], &mut self.position);
+                    self.cursor = cursor;
+                    self.dedents = 0;
+                    self.queued.clear();
+                    self.finished = false;
+                }
+
+                pub fn mode(&self) -> Mode {
+                    self.peeked.front().map_or(self.mode, |(state, _)| state.mode)
+                }
+
+                pub fn arena(&self) -> Option<&'a $crate::arena::Arena> {
+                    self.arena
+                }
+
+                pub fn state(&self) -> &State {
+                    self.peeked.front().map_or(&self.state, |(state, _)| &state.state)
+                }
+
+                pub fn state_mut(&mut self) -> &mut State {
+                    // Peeked tokens were built from the state before it is changed
+                    let state = self.checkpoint();
+                    self.rewind(state);
+                    &mut self.state
+                }
+
+                pub fn mode_stack(&self) -> &[Mode] {
+                    self.peeked.front().map_or(&self.stack, |(state, _)| &state.stack)
+                }
+
+                pub fn checkpoint(&self) -> LexerState<'a> {
+                    match self.peeked.front() {
+                        Some((state, _)) => state.clone(),
+                        None => self.snapshot(),
+                    }
+                }
+
+                pub fn rewind(&mut self, state: LexerState<'a>) {
+                    self.cursor = state.cursor;
+                    self.position = state.position;
+                    self.mode = state.mode;
+                    self.stack = state.stack;
+                    self.opened = state.opened;
+                    self.previous = state.previous;
+                    self.indents = state.indents;
+                    self.dedents = state.dedents;
+                    self.queued = state.queued;
+                    self.finished = state.finished;
+                    self.state = state.state;
+                    self.peeked.clear();
+                    self.aborted = false;
+                }
+
+                fn snapshot(&self) -> LexerState<'a> {
+                    LexerState {
+                        cursor: self.cursor,
+                        position: self.position,
+                        mode: self.mode,
+                        stack: self.stack.clone(),
+                        opened: self.opened.clone(),
+                        previous: self.previous,
+                        indents: self.indents.clone(),
+                        dedents: self.dedents,
+                        queued: self.queued.clone(),
+                        finished: self.finished,
+                        state: self.state.clone(),
+                        haystack: core::marker::PhantomData,
+                    }
+                }
+
+                pub fn peek(&mut self) -> Option<Result<&TokenOf<'a>, &LexerError<'a>>> {
+                    self.peek_n(0)
+                }
+
+                pub fn peek_n(&mut self, n: usize) -> Option<Result<&TokenOf<'a>, &LexerError<'a>>> {
+                    while self.peeked.len() <= n {
+                        let state = self.snapshot();
+                        let result = self.advance()?;
+                        self.peeked.push_back((state, result));
+                    }
+                    self.peeked.get(n).map(|(_, result)| result.as_ref().map(|(token, _)| token))
+                }
+
+                pub fn stats(mut self) -> LexerStats {
+                    let start = self.cursor();
+                    let mut stats = LexerStats::default();
+                    for result in self.by_ref() {
+                        match result {
+                            Ok(token) => *stats.counts.entry(token.kind()).or_insert(0) += 1,
+                            Err(_) => stats.errors += 1,
+                        }
+                    }
+                    stats.bytes = self.cursor() - start;
+                    stats
+                }
+
+                pub fn to_json(self) -> String {
+                    $crate::dump::json(self.entries())
+                }
+
+                pub fn to_snapshot(self) -> String {
+                    $crate::testing::snapshot(self.spanned().map(|result| result.map_err(|err| (err.span(), err.message()))))
+                }
+
+                fn entries(self) -> impl Iterator<Item = $crate::dump::Entry> + 'a {
+                    let haystack = self.haystack;
+                    self.spanned().map(move |result| match result {
+                        Ok($crate::span::Spanned { value, span }) => $crate::dump::Entry {
+                            kind: Some(value.kind().name()),
+                            lexeme: $crate::bytes::Haystack::escape(&haystack[span.as_range()]),
+                            span,
+                            error: None,
+                        },
+                        Err(err) => $crate::dump::Entry {
+                            kind: None,
+                            lexeme: $crate::bytes::Haystack::escape(&haystack[err.span().as_range()]),
+                            span: err.span(),
+                            error: Some(err.message()),
+                        },
+                    })
+                }
+
+                pub fn spanned(self) -> SpannedLexer<'a> {
+                    SpannedLexer { lexer: self }
+                }
+
+                pub fn lexemes(self) -> LexemeLexer<'a> {
+                    LexemeLexer { lexer: self }
+                }
+
+                pub fn triples(self) -> TripleLexer<'a> {
+                    TripleLexer { lexer: self }
+                }
+
+                pub fn lossless(self) -> LosslessLexer<'a> {
+                    LosslessLexer {
+                        lexer: self.with_trivia(),
+                        end: 0,
+                        pending: VecDeque::new(),
+                        exhausted: false,
+                    }
+                }
+
+                pub fn significant(self) -> Lexer<'a> {
+                    let kinds = TokenKind::all().iter().filter(|kind| !kind.is_trivia()).copied().collect();
+                    self.filter_kinds(kinds)
+                }
+
+                pub fn only(self, kinds: &[TokenKind]) -> Lexer<'a> {
+                    self.filter_kinds(kinds.to_vec())
+                }
+
+                fn filter_kinds(mut self, kinds: Vec<TokenKind>) -> Lexer<'a> {
+                    // Peeked tokens weren't filtered with these kinds
+                    let state = self.checkpoint();
+                    self.rewind(state);
+                    self.kinds = Some(kinds);
+                    self
+                }
+
+                pub fn on_token<R: $crate::hook::Flow>(
+                    mut self,
+                    mut hook: impl for<'t> FnMut(&TokenOf<'t>, $crate::span::Span, &'t Haystack) -> R + Send + 'static,
+                ) -> Lexer<'a> {
+                    // Peeked tokens were lexed without calling the hook
+                    let state = self.checkpoint();
+                    self.rewind(state);
+                    let hook: TokenHook = Arc::new(Mutex::new(
+                        move |token: &TokenOf<'_>, span, lexeme: &Haystack| hook(token, span, lexeme).flow(),
+                    ));
+                    self.hooks.on_token = Some(hook);
+                    self
+                }
+
+                pub fn on_error<R: $crate::hook::Flow>(
+                    mut self,
+                    mut hook: impl for<'t> FnMut(&LexerError<'t>, $crate::span::Span, &'t Haystack) -> R + Send + 'static,
+                ) -> Lexer<'a> {
+                    // Peeked errors were lexed without calling the hook
+                    let state = self.checkpoint();
+                    self.rewind(state);
+                    let hook: ErrorHook = Arc::new(Mutex::new(
+                        move |err: &LexerError<'_>, span, lexeme: &Haystack| hook(err, span, lexeme).flow(),
+                    ));
+                    self.hooks.on_error = Some(hook);
+                    self
+                }
+
+                pub fn with_trace(mut self) -> Lexer<'a> {
+                    // Peeked tokens were lexed without tracing
+                    let state = self.checkpoint();
+                    self.rewind(state);
+                    self.trace = Some(Vec::new());
+                    self
+                }
+
+                pub fn take_trace(&mut self) -> Vec<$crate::trace::Attempt<TokenKind>> {
+                    self.trace.as_mut().map(core::mem::take).unwrap_or_default()
+                }
+
+                pub fn with_trivia(mut self) -> TriviaLexer<'a> {
+                    // Peeked tokens were lexed without keeping their trivia
+                    let state = self.checkpoint();
+                    self.rewind(state);
+                    self.trivia = Some(Vec::new());
+                    TriviaLexer { lexer: self }
+                }
+
+                // Like `best_match`, but adds the patterns tried to the trace
+                fn traced_match(&mut self, rev: bool) -> Result<($crate::build::Built<TokenOf<'a>>, usize, bool, Option<Action>, State), Option<(usize, Option<String>)>> {
+                    let (best, attempts) = self.best_match(rev);
+                    if let Some(trace) = &mut self.trace {
+                        trace.extend(attempts);
+                    }
+                    best
+                }
+
+                // Matches start at the cursor, or end at it when lexing backwards with `rev`,
+                // the patterns tried are returned when tracing
+                fn best_match(&self, rev: bool) -> (
+                    Result<($crate::build::Built<TokenOf<'a>>, usize, bool, Option<Action>, State), Option<(usize, Option<String>)>>,
+                    Vec<$crate::trace::Attempt<TokenKind>>,
+                ) {
+                    let (start, end) = if rev {
+                        let mut start = self.cursor.saturating_sub(MAX_LENGTH);
+                        while !$crate::bytes::Haystack::is_boundary(self.haystack, start) {
+                            start += 1;
+                        }
+                        (start, self.cursor)
+                    } else {
+                        let mut end = core::cmp::min(self.haystack.len(), self.cursor.saturating_add(MAX_LENGTH));
+                        while !$crate::bytes::Haystack::is_boundary(self.haystack, end) {
+                            end -= 1;
+                        }
+                        (self.cursor, end)
+                    };
+
+                    let mut token = None;
+                    let mut len = 0;
+                    let mut rejection: Option<(usize, Option<String>)> = None;
+                    let tracing = self.trace.is_some();
+                    let mut trace = Vec::new();
+                    // Index of the attempt of the best match in the trace
+                    let mut winner: Option<usize> = None;
+
+                    // Patterns that can't start with the byte under the cursor, and regexes that can't match here,
+                    // are skipped without being tried
+                    let rules = rules();
+                    let dispatch = $crate::bytes::Haystack::as_bytes(self.haystack).get(start).filter(|_| !rev).map(|&byte| &rules.dispatch[usize::from(byte)]);
+                    let matched = rules.prefilter.as_ref().filter(|_| !rev).map(|set| set.matches(&self.haystack[start..end]));
+
+                    let mut rules = rules.arms.iter();
+                    $(let arms = rules.next().expect("one pattern list per rule");
+                    if $crate::__in_mode!(self.mode; $([$($attr)*])*) && (rev || $crate::__guard!(self.previous; $([$($attr)*])*)) {
+                        let skip = $crate::__skip!($([$($attr)*])*);
+                        let mut arms = arms.iter().enumerate();
+                        $({
+                            let (index, arm) = arms.next().expect("one pattern per arm");
+                            let found = match (dispatch, arm.regex, &matched) {
+                                (Some(dispatch), _, _) if !dispatch[arm.index] => None,
+                                (_, Some(regex), Some(matched)) if !matched.matched(regex) => None,
+                                _ if rev => arm.pattern.find_suffix_in(&self.haystack[start..end]),
+                                _ => arm.pattern.find_prefix_in(&self.haystack[start..end]),
+                            };
+                            if tracing {
+                                trace.push($crate::trace::Attempt {
+                                    cursor: self.cursor,
+                                    kind: TokenKind::$token,
+                                    arm: index,
+                                    len: found.as_ref().map(|mat| mat.len()),
+                                    verdict: if found.is_some() { $crate::trace::Verdict::Shorter } else { $crate::trace::Verdict::NoMatch },
+                                });
+                            }
+                            if let Some(mat) = found {
+                                if mat.len() > len {
+                                    let (start, end) = if rev { (end - mat.len(), end) } else { (start, start + mat.len()) };
+                                    let mat = MatchOf::new(self.haystack, start, end).in_arena(self.arena);
+                                    let mat_len = mat.len();
+                                    // Each builder changes its own copy of the state, only the one of the best match is kept
+                                    #[allow(unused_mut)]
+                                    let mut state = self.state.clone();
+                                    match build($build, mat, &mut state) {
+                                        Ok(built) => {
+                                            let action = $crate::__action!($($action $(($target))?)?);
+                                            token = Some((built, skip, action, state));
+                                            len = mat_len;
+                                            if tracing {
+                                                if let Some(beaten) = winner {
+                                                    trace[beaten].verdict = $crate::trace::Verdict::Beaten;
+                                                }
+                                                let last = trace.len() - 1;
+                                                trace[last].verdict = $crate::trace::Verdict::Won;
+                                                winner = Some(last);
+                                            }
+                                        }
+                                        Err(reason) => {
+                                            if let Some(last) = trace.last_mut() {
+                                                last.verdict = $crate::trace::Verdict::Rejected(reason.clone());
+                                            }
+                                            if rejection.as_ref().map_or(true, |(rejected, _)| mat_len > *rejected) {
+                                                rejection = Some((mat_len, reason));
+                                            }
+                                        }
+                                    }
+                                }
+                            }
+                        })+
+                    })*
+
+                    let best = match token {
+                        Some((token, skip, action, state)) => Ok((token, len, skip, action, state)),
+                        None => Err(rejection),
+                    };
+                    (best, trace)
+                }
+
+                fn empty_match(&self) -> Option<TokenKind> {
+                    let mut end = core::cmp::min(self.haystack.len(), self.cursor.saturating_add(MAX_LENGTH));
+                    while !$crate::bytes::Haystack::is_boundary(self.haystack, end) {
+                        end -= 1;
+                    }
+                    let haystack = &self.haystack[self.cursor..end];
+
+                    let mut rules = rules().arms.iter();
+                    $(let arms = rules.next().expect("one pattern list per rule");
+                    if $crate::__in_mode!(self.mode; $([$($attr)*])*) && $crate::__guard!(self.previous; $([$($attr)*])*)
+                        && arms.iter().any(|arm| arm.pattern.matches_empty(haystack))
+                    {
+                        return Some(TokenKind::$token);
+                    })*
+                    None
+                }
+
+                fn apply(&mut self, action: Option<Action>, span: $crate::span::Span) {
+                    let Some(action) = action else {
+                        return;
+                    };
+                    // A pushed mode is saved with its span, the others are left
+                    if self.mode != Mode::INITIAL && !matches!(action, Action::Push(_)) {
+                        self.opened.pop();
+                    }
+                    match action {
+                        Action::Begin(mode) => self.mode = mode,
+                        Action::Push(mode) => {
+                            self.stack.push(self.mode);
+                            self.mode = mode;
+                        }
+                        Action::Pop => self.mode = self.stack.pop().unwrap_or(Mode::INITIAL),
+                    }
+                    // A popped mode already has its span
+                    if self.mode != Mode::INITIAL && !matches!(action, Action::Pop) {
+                        self.opened.push(span);
+                    }
+                }
+
+                // Kinds of the rules tried at the cursor, trivia isn't worth expecting
+                fn expected(&self) -> Vec<TokenKind> {
+                    let mut kinds = Vec::new();
+                    $(if !$crate::__trivia!($([$($attr)*])*)
+                        && $crate::__in_mode!(self.mode; $([$($attr)*])*)
+                        && $crate::__guard!(self.previous; $([$($attr)*])*)
+                    {
+                        kinds.push(TokenKind::$token);
+                    })*
+                    kinds
+                }
+
+                // Kinds of the rules that leave the mode, which a mode left open at the end of the haystack expected
+                fn closing(mode: Mode) -> Vec<TokenKind> {
+                    let mut kinds = Vec::new();
+                    $(if $crate::__in_mode!(mode; $([$($attr)*])*)
+                        && [$($crate::__action!($($action $(($target))?)?)),+]
+                            .iter()
+                            .any(|action| matches!(action, Some(Action::Pop | Action::Begin(Mode::INITIAL))))
+                    {
+                        kinds.push(TokenKind::$token);
+                    })*
+                    kinds
+                }
+
+                fn indent(&mut self, indent: TokenOf<'a>, dedent: TokenOf<'a>) -> Option<LexerResult<'a, (TokenOf<'a>, $crate::span::Span)>> {
+                    if self.dedents > 0 {
+                        self.dedents -= 1;
+                        self.previous = Some(dedent.kind());
+                        return Some(Ok((dedent, $crate::span::Span::new(self.cursor, self.cursor))));
+                    }
+                    let bytes = $crate::bytes::Haystack::as_bytes(self.haystack);
+                    if self.mode != Mode::INITIAL || !(self.cursor == 0 || bytes[self.cursor - 1] == b'\n') {
+                        return None;
+                    }
+
+                    let width = bytes[self.cursor..].iter().take_while(|&&byte| byte == b' ' || byte == b'\t').count();
+                    if matches!(bytes.get(self.cursor + width), None | Some(b'\n' | b'\r')) {
+                        return None;
+                    }
+
+                    let start = self.cursor;
+                    let position = self.position;
+                    self.cursor += width;
+                    $crate::bytes::Haystack::advance(&self.haystack[start..self.cursor], &mut self.position);
+
+                    if width > self.indents.last().copied().unwrap_or(0) {
+                        self.indents.push(width);
+                        self.previous = Some(indent.kind());
+                        return Some(Ok((indent, $crate::span::Span::new(start, self.cursor))));
+                    }
+                    let closed = self.indents.iter().rev().take_while(|&&level| level > width).count();
+                    self.indents.truncate(self.indents.len() - closed);
+                    // Like Python, a line can only go back to the level of an enclosing block,
+                    // the blocks it closed are still closed after the error
+                    if width != self.indents.last().copied().unwrap_or(0) {
+                        self.dedents = closed;
+                        let reason = Some(String::from("dedent doesn't match any outer indentation level"));
+                        return Some(Err(LexerError::rejected(self.haystack, start, self.cursor, position, reason)));
+                    }
+                    if closed == 0 {
+                        return None;
+                    }
+                    self.dedents = closed - 1;
+                    self.previous = Some(dedent.kind());
+                    Some(Ok((dedent, $crate::span::Span::new(self.cursor, self.cursor))))
+                }
+
+                fn char_len(&self) -> usize {
+                    $crate::bytes::Haystack::unit_len(self.haystack, self.cursor)
+                }
+
+                fn lex(&mut self) -> Option<LexerResult<'a, (TokenOf<'a>, $crate::span::Span)>> {
+                    match self.peeked.pop_front() {
+                        Some((_, result)) => Some(result),
+                        None => self.advance(),
+                    }
+                }
+
+                fn advance(&mut self) -> Option<LexerResult<'a, (TokenOf<'a>, $crate::span::Span)>> {
+                    if self.aborted {
+                        return None;
+                    }
+                    let result = loop {
+                        let result = self.advance_raw()?.or_else(|err| $crate::__error_token!(err; $([$($option)*])*));
+                        // Filtered out tokens still count as the previous token for guards
+                        match (&result, &self.kinds) {
+                            (Ok((token, _)), Some(kinds)) if !kinds.contains(&token.kind()) => continue,
+                            _ => break result,
+                        }
+                    };
+                    self.aborted = self.hooks.call(&result, self.haystack).is_break();
+                    Some(result)
+                }
+
+                fn dequeue(&mut self) -> Option<(TokenOf<'a>, $crate::span::Span)> {
+                    let (token, span) = self.queued.pop_front()?;
+                    self.previous = Some(token.kind());
+                    Some((token, span))
+                }
+
+                fn advance_raw(&mut self) -> Option<LexerResult<'a, (TokenOf<'a>, $crate::span::Span)>> {
+                    if let Some(queued) = self.dequeue() {
+                        return Some(Ok(queued));
+                    }
+
+                    while self.cursor < self.haystack.len() {
+                        if self.partial && self.haystack.len() - self.cursor < MAX_LENGTH {
+                            return None;
+                        }
+                        if let Some((indent, dedent)) = $crate::__indent!($([$($option)*])*) {
+                            if let Some(result) = self.indent(indent, dedent) {
+                                return Some(result);
+                            }
+                        }
+
+                        let start = self.cursor;
+                        let position = self.position;
+                        let best = self.traced_match(false);
+                        // Empty matches are never built, but a rule that only matched the empty string is worth reporting
+                        let empty = if matches!(best, Err(None)) { self.empty_match() } else { None };
+
+                        self.cursor += match &best {
+                            Ok((_, len, _, _, _)) => *len,
+                            Err(Some((len, _))) => *len,
+                            Err(None) => self.char_len(),
+                        };
+                        if GROUP_ERRORS && matches!(best, Err(None)) {
+                            while self.cursor < self.haystack.len() && matches!(self.traced_match(false), Err(None)) {
+                                self.cursor += self.char_len();
+                            }
+                        }
+                        $crate::bytes::Haystack::advance(&self.haystack[start..self.cursor], &mut self.position);
+
+                        match best {
+                            Ok((built, _, true, action, state)) => {
+                                self.apply(action, $crate::span::Span::new(start, self.cursor));
+                                self.state = state;
+                                if let Some(trivia) = &mut self.trivia {
+                                    built.spread($crate::span::Span::new(start, self.cursor), trivia);
+                                }
+                            }
+                            Ok((built, _, false, action, state)) => {
+                                self.apply(action, $crate::span::Span::new(start, self.cursor));
+                                self.state = state;
+                                built.spread($crate::span::Span::new(start, self.cursor), &mut self.queued);
+                                if let Some(queued) = self.dequeue() {
+                                    return Some(Ok(queued));
+                                }
+                            }
+                            Err(Some((_, reason))) => {
+                                let err = LexerError::rejected(self.haystack, start, self.cursor, position, reason);
+                                return Some(Err(err.with_expected(self.expected())));
+                            }
+                            Err(None) => {
+                                let err = LexerError::new(self.haystack, start, self.cursor, position).with_empty(empty);
+                                return Some(Err(err.with_expected(self.expected())));
+                            }
+                        }
+                    }
+
+                    // A mode that is still open at the end is reported once, and closed so the lexer can finish
+                    if self.mode != Mode::INITIAL && !self.partial {
+                        let end = $crate::span::Span::new(self.cursor, self.cursor);
+                        let open_span = self.opened.last().copied().unwrap_or(end);
+                        let expected = Self::closing(self.mode);
+                        self.mode = Mode::INITIAL;
+                        self.stack.clear();
+                        self.opened.clear();
+                        return Some(Err(LexerError::unterminated(self.haystack, self.cursor, self.position, open_span, expected)));
+                    }
+                    if let Some((_, dedent)) = $crate::__indent!($([$($option)*])*) {
+                        if self.indents.pop().is_some() {
+                            self.previous = Some(dedent.kind());
+                            return Some(Ok((dedent, $crate::span::Span::new(self.cursor, self.cursor))));
+                        }
+                    }
+                    if self.finished {
+                        return None;
+                    }
+                    self.finished = true;
+                    $crate::__eof!($([$($option)*])*).map(|eof| {
+                        Ok((eof, $crate::span::Span::new(self.cursor, self.cursor)))
+                    })
+                }
+            }
+
+            impl<'a> Iterator for Lexer<'a> {
+                type Item = LexerResult<'a, TokenOf<'a>>;
+
+                fn next(&mut self) -> Option<Self::Item> {
+                    self.lex().map(|res| res.map(|(token, _)| token))
+                }
+
+                fn size_hint(&self) -> (usize, Option<usize>) {
+                    // A single match can build any number of tokens, so only the end of the haystack gives an upper bound
+                    let ready = self.peeked.len() + self.queued.len();
+                    let (lower, upper) = if self.cursor < self.haystack.len() {
+                        (ready, None)
+                    } else if self.finished {
+                        (ready, Some(ready))
+                    } else {
+                        // The error of a mode left open, the remaining `DEDENT`s, and the `EOF` with `#![eof]`
+                        let left = ready
+                            + usize::from(self.mode != Mode::INITIAL && !self.partial)
+                            + self.indents.len()
+                            + usize::from($crate::__flag!(eof; $([$($option)*])*));
+                        (left, Some(left))
+                    };
+                    // Tokens that aren't peeked yet may be filtered out, or never lexed once a hook stopped the lexer
+                    if self.aborted {
+                        (self.peeked.len(), Some(self.peeked.len()))
+                    } else if self.kinds.is_some() {
+                        (self.peeked.len(), upper)
+                    } else {
+                        (lower, upper)
+                    }
+                }
+            }
+
+            // Once the haystack is consumed and the lexer finished, it stays so until it is rewound
+            impl<'a> core::iter::FusedIterator for Lexer<'a> {}
+
+            #[derive(Debug, Clone)]
+            pub struct SpannedLexer<'a> {
+                lexer: Lexer<'a>,
+            }
+
+            $crate::__std! {
+                #[allow(dead_code)]
+                impl<'a> Lexer<'a> {
+                    pub fn write_csv(self, writer: impl std::io::Write) -> std::io::Result<()> {
+                        $crate::dump::write_csv(writer, self.entries())
+                    }
+                }
+            }
+
+            $crate::__chumsky! {
+                #[allow(dead_code)]
+                impl<'a> Lexer<'a> {
+                    pub fn to_chumsky(self) -> Result<$crate::parser::TokenStream<'a, TokenOf<'a>>, Vec<LexerError<'a>>> {
+                        let end = self.haystack.len();
+                        $crate::parser::stream(self.spanned(), end)
+                    }
+                }
+            }
+
+            $crate::__wasm! {
+                #[allow(dead_code)]
+                impl<'a> Lexer<'a> {
+                    pub fn to_js(self) -> $crate::__private::js_sys::Array {
+                        $crate::wasm::array($crate::bytes::Haystack::as_bytes(self.haystack), self.entries())
+                    }
+                }
+            }
+
+            impl<'a> Iterator for SpannedLexer<'a> {
+                type Item = LexerResult<'a, $crate::span::Spanned<TokenOf<'a>>>;
+
+                fn next(&mut self) -> Option<Self::Item> {
+                    self.lexer.lex().map(|result| result.map($crate::span::Spanned::from))
+                }
+
+                fn size_hint(&self) -> (usize, Option<usize>) {
+                    self.lexer.size_hint()
+                }
+            }
+
+            impl<'a> core::iter::FusedIterator for SpannedLexer<'a> {}
+
+            #[derive(Debug, Clone)]
+            pub struct TripleLexer<'a> {
+                lexer: Lexer<'a>,
+            }
+
+            impl<'a> Iterator for TripleLexer<'a> {
+                type Item = LexerResult<'a, (usize, TokenOf<'a>, usize)>;
+
+                fn next(&mut self) -> Option<Self::Item> {
+                    self.lexer.lex().map(|result| result.map(|(token, span)| (span.start, token, span.end)))
+                }
+
+                fn size_hint(&self) -> (usize, Option<usize>) {
+                    self.lexer.size_hint()
+                }
+            }
+
+            impl<'a> core::iter::FusedIterator for TripleLexer<'a> {}
+
+            #[derive(Debug, Clone)]
+            pub struct LexemeLexer<'a> {
+                lexer: Lexer<'a>,
+            }
+
+            impl<'a> Iterator for LexemeLexer<'a> {
+                type Item = LexerResult<'a, (TokenOf<'a>, &'a Haystack)>;
+
+                fn next(&mut self) -> Option<Self::Item> {
+                    let haystack = self.lexer.haystack;
+                    self.lexer.lex().map(|result| result.map(|(token, span)| (token, &haystack[span.as_range()])))
+                }
+
+                fn size_hint(&self) -> (usize, Option<usize>) {
+                    self.lexer.size_hint()
+                }
+            }
+
+            impl<'a> core::iter::FusedIterator for LexemeLexer<'a> {}
+
+            #[derive(Debug, Clone)]
+            pub struct TriviaLexer<'a> {
+                lexer: Lexer<'a>,
+            }
+
+            impl<'a> TriviaLexer<'a> {
+                pub fn trailing(&mut self) -> Vec<$crate::span::Spanned<TokenOf<'a>>> {
+                    self.take_trivia()
+                }
+
+                fn take_trivia(&mut self) -> Vec<$crate::span::Spanned<TokenOf<'a>>> {
+                    let trivia = self.lexer.trivia.get_or_insert_with(Vec::new);
+                    trivia.drain(..).map($crate::span::Spanned::from).collect()
+                }
+            }
+
+            impl<'a> Iterator for TriviaLexer<'a> {
+                type Item = LexerResult<'a, $crate::span::WithTrivia<TokenOf<'a>>>;
+
+                fn next(&mut self) -> Option<Self::Item> {
+                    let result = self.lexer.lex()?;
+                    Some(result.map(|token| $crate::span::WithTrivia {
+                        leading: self.take_trivia(),
+                        token: token.into(),
+                    }))
+                }
+
+                fn size_hint(&self) -> (usize, Option<usize>) {
+                    self.lexer.size_hint()
+                }
+            }
+
+            impl<'a> core::iter::FusedIterator for TriviaLexer<'a> {}
+
+            #[derive(Debug, Clone)]
+            pub struct LosslessLexer<'a> {
+                lexer: TriviaLexer<'a>,
+                // End of the last lexeme, the next one starts there
+                end: usize,
+                pending: VecDeque<(LexerResult<'a, TokenOf<'a>>, $crate::span::Span)>,
+                exhausted: bool,
+            }
+
+            impl<'a> LosslessLexer<'a> {
+                pub fn reconstruct(self) -> <Haystack as ToOwned>::Owned {
+                    self.map(|(_, lexeme)| lexeme).collect::<Vec<_>>().concat()
+                }
+
+                // Keep one result ahead, to know which one is the last
+                fn fill(&mut self) {
+                    while self.pending.len() < 2 && !self.exhausted {
+                        match self.lexer.next() {
+                            Some(Ok(token)) => {
+                                self.pending.extend(token.leading.into_iter().map(|trivia| (Ok(trivia.value), trivia.span)));
+                                self.pending.push_back((Ok(token.token.value), token.token.span));
+                            }
+                            Some(Err(err)) => {
+                                let span = err.span();
+                                self.pending.push_back((Err(err), span));
+                            }
+                            None => {
+                                self.exhausted = true;
+                                let trailing = self.lexer.trailing();
+                                self.pending.extend(trailing.into_iter().map(|trivia| (Ok(trivia.value), trivia.span)));
+                            }
+                        }
+                    }
+                }
+            }
+
+            impl<'a> Iterator for LosslessLexer<'a> {
+                type Item = (LexerResult<'a, TokenOf<'a>>, &'a Haystack);
+
+                fn next(&mut self) -> Option<Self::Item> {
+                    self.fill();
+                    let (result, span) = self.pending.pop_front()?;
+
+                    // Text the lexer went over without a token goes with the next lexeme, or the last one
+                    let haystack = self.lexer.lexer.haystack;
+                    let end = if self.pending.is_empty() && self.exhausted {
+                        haystack.len()
+                    } else {
+                        core::cmp::max(self.end, span.end)
+                    };
+                    let lexeme = &haystack[self.end..end];
+                    self.end = end;
+                    Some((result, lexeme))
+                }
+            }
+
+            impl<'a> core::iter::FusedIterator for LosslessLexer<'a> {}
+
+            #[derive(Debug, Clone)]
+            pub struct RevLexer<'a> {
+                lexer: Lexer<'a>,
+                // Tokens built from the last match, the last one on top
+                queued: Vec<(TokenOf<'a>, $crate::span::Span)>,
+            }
+
+            impl<'a> RevLexer<'a> {
+                fn prev_char_len(&self) -> usize {
+                    let cursor = self.lexer.cursor;
+                    let mut start = cursor - 1;
+                    while !$crate::bytes::Haystack::is_boundary(self.lexer.haystack, start) {
+                        start -= 1;
+                    }
+                    cursor - start
+                }
+
+                fn lex(&mut self) -> Option<LexerResult<'a, (TokenOf<'a>, $crate::span::Span)>> {
+                    loop {
+                        if let Some(queued) = self.queued.pop() {
+                            return Some(Ok(queued));
+                        }
+                        if self.lexer.cursor == 0 {
+                            return None;
+                        }
+
+                        let end = self.lexer.cursor;
+                        let best = self.lexer.traced_match(true);
+
+                        self.lexer.cursor -= match &best {
+                            Ok((_, len, _, _, _)) => *len,
+                            Err(Some((len, _))) => *len,
+                            Err(None) => self.prev_char_len(),
+                        };
+                        if GROUP_ERRORS && matches!(best, Err(None)) {
+                            while self.lexer.cursor > 0 && matches!(self.lexer.traced_match(true), Err(None)) {
+                                self.lexer.cursor -= self.prev_char_len();
+                            }
+                        }
+
+                        let haystack = self.lexer.haystack;
+                        let start = self.lexer.cursor;
+                        match best {
+                            Ok((_, _, true, _, _)) => (),
+                            Ok((built, _, false, _, _)) => built.spread($crate::span::Span::new(start, end), &mut self.queued),
+                            // Errors are rare enough to count their position from the start
+                            Err(reason) => {
+                                let mut position = $crate::span::Position::default();
+                                $crate::bytes::Haystack::advance(&haystack[..start], &mut position);
+                                let err = match reason {
+                                    Some((_, reason)) => LexerError::rejected(haystack, start, end, position, reason),
+                                    None => LexerError::new(haystack, start, end, position),
+                                }
+                                .with_expected(self.lexer.expected());
+                                return Some($crate::__error_token!(err; $([$($option)*])*));
+                            }
+                        }
+                    }
+                }
+            }
+
+            impl<'a> Iterator for RevLexer<'a> {
+                type Item = LexerResult<'a, $crate::span::Spanned<TokenOf<'a>>>;
+
+                fn next(&mut self) -> Option<Self::Item> {
+                    self.lex().map(|result| result.map($crate::span::Spanned::from))
+                }
+            }
+
+            impl<'a> core::iter::FusedIterator for RevLexer<'a> {}
+
+            $crate::__error_lexer!($([$($option)*])*);
+            $crate::__owned_lexer!($([$($option)*])*);
+        }
+    };
+    // A malformed rule is already reported by `__rule!`
+    (@expand $($rest:tt)*) => {};
+    ($vis:vis mod $name:ident; $($rules:tt)*) => {
+        $crate::lexer!(@rules [$vis mod $name;] $($rules)*);
+    };
+    ($($rules:tt)*) => {
+        $crate::lexer!(mod lexer; $($rules)*);
+    };
 }
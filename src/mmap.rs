@@ -0,0 +1,98 @@
+/*!
+Module for lexing memory-mapped files, enabled by the `memmap2` feature. \
+
+A [`MappedFile`] maps a file in memory without reading it, and `Token::tokenize_mmap` lexes it in place,
+so tokens borrow the map instead of a copy of the file, which helps when indexing large codebases. \
+The tokens can't outlive the [`MappedFile`], which owns the map, so the file is opened first and then lexed.
+
+# Example
+```
+# #[cfg(feature = "memmap2")]
+# {
+# use plexer::lexer;
+use plexer::mmap::MappedFile;
+
+lexer!(
+    WORD(&'a str) {
+        regex!(r"[a-z]+") => |v| Token::WORD(v.as_str()),
+    },
+    #[skip]
+    WHITESPACE {
+        [' ', '\n'] => |_| Token::WHITESPACE,
+    },
+);
+
+let path = std::env::temp_dir().join("plexer-mmap-example.txt");
+std::fs::write(&path, "first line\nsecond line\n").unwrap();
+
+// SAFETY: the file isn't modified while it is mapped
+let file = unsafe { MappedFile::open(&path) }.unwrap();
+let words: Vec<_> = lexer::Token::tokenize_mmap(&file).unwrap().map(Result::unwrap).collect();
+assert_eq!(words[2], lexer::Token::WORD("second"));
+# drop(file);
+# std::fs::remove_file(&path).unwrap();
+# }
+```
+*/
+
+use std::fs::File;
+use std::io;
+use std::path::Path;
+
+/// A file mapped in memory, that a lexer can borrow as its haystack.
+#[derive(Debug)]
+pub struct MappedFile {
+    map: memmap2::Mmap,
+}
+
+impl MappedFile {
+    /**
+    Map the file at the path in memory, read-only.
+
+    # Errors
+    When the file can't be opened or mapped.
+
+    # Safety
+    The file must not be modified or truncated, by this process or another one, while it is mapped,
+    which would change the bytes borrowed by the tokens, see [`memmap2::Mmap::map`].
+    */
+    pub unsafe fn open(path: impl AsRef<Path>) -> io::Result<Self> {
+        let file = File::open(path)?;
+        // SAFETY: the caller guarantees that the file isn't modified while it is mapped
+        let map = unsafe { memmap2::Mmap::map(&file)? };
+        Ok(MappedFile { map })
+    }
+
+    /// The bytes of the file.
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.map
+    }
+
+    /**
+    The bytes of the file as the haystack of a lexer, `str` or `[u8]` with `#![bytes]`.
+
+    # Errors
+    When the haystack is a `str` and the file isn't valid UTF-8.
+    */
+    pub fn haystack<H: FromMapped + ?Sized>(&self) -> io::Result<&H> {
+        H::from_mapped(&self.map)
+    }
+}
+
+// Haystacks that can borrow the bytes of a map
+#[doc(hidden)]
+pub trait FromMapped {
+    fn from_mapped(bytes: &[u8]) -> io::Result<&Self>;
+}
+
+impl FromMapped for str {
+    fn from_mapped(bytes: &[u8]) -> io::Result<&Self> {
+        core::str::from_utf8(bytes).map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))
+    }
+}
+
+impl FromMapped for [u8] {
+    fn from_mapped(bytes: &[u8]) -> io::Result<&Self> {
+        Ok(bytes)
+    }
+}
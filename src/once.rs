@@ -0,0 +1,66 @@
+//! Stand-in for `std::sync::OnceLock` without `std`, the generated lexers use it to build their patterns once.
+
+use core::cell::UnsafeCell;
+use core::mem::MaybeUninit;
+use core::sync::atomic::{AtomicU8, Ordering};
+
+const EMPTY: u8 = 0;
+const BUSY: u8 = 1;
+const READY: u8 = 2;
+
+pub struct OnceLock<T> {
+    state: AtomicU8,
+    value: UnsafeCell<MaybeUninit<T>>,
+}
+
+// The value is only written once, by the thread that moved the state from EMPTY to BUSY
+unsafe impl<T: Send + Sync> Sync for OnceLock<T> {}
+
+impl<T> OnceLock<T> {
+    pub const fn new() -> Self {
+        Self {
+            state: AtomicU8::new(EMPTY),
+            value: UnsafeCell::new(MaybeUninit::uninit()),
+        }
+    }
+
+    pub fn get_or_init(&self, init: impl FnOnce() -> T) -> &T {
+        // Another thread may be initializing the value, or have panicked while doing it and left it empty
+        while self.state.load(Ordering::Acquire) != READY {
+            if self.state.compare_exchange(EMPTY, BUSY, Ordering::Acquire, Ordering::Acquire).is_ok() {
+                // Like `std::sync::OnceLock`, the next call tries again if `init` panics
+                let reset = Reset(&self.state);
+                unsafe { (*self.value.get()).write(init()) };
+                core::mem::forget(reset);
+                self.state.store(READY, Ordering::Release);
+                break;
+            }
+            core::hint::spin_loop();
+        }
+
+        unsafe { (*self.value.get()).assume_init_ref() }
+    }
+}
+
+// Empties the state when dropped during a panic
+struct Reset<'a>(&'a AtomicU8);
+
+impl Drop for Reset<'_> {
+    fn drop(&mut self) {
+        self.0.store(EMPTY, Ordering::Release);
+    }
+}
+
+impl<T> Default for OnceLock<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T> Drop for OnceLock<T> {
+    fn drop(&mut self) {
+        if *self.state.get_mut() == READY {
+            unsafe { self.value.get_mut().assume_init_drop() };
+        }
+    }
+}
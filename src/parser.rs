@@ -0,0 +1,342 @@
+/*!
+Module for feeding tokens to the parser-combinator crates `chumsky` and `nom`, enabled by the features of the same name. \
+
+With the `chumsky` feature, [`Span`](crate::span::Span) implements `chumsky::Span`, and `Lexer::to_chumsky` lexes the haystack into a `chumsky::Stream`
+of tokens with their spans, see [`stream`], so that the errors of a parser point back to the haystack. \
+With the `nom` feature, [`Tokens`] is an input of `nom` made of spanned tokens, [`token`] parses a single token,
+and [`Tokens::span`] or [`Tokens::consumed`] map the tokens back to the haystack.
+
+# Chumsky
+A parser of tokens takes the `Token` type as input and [`Span`](crate::span::Span) as span,
+the stream ends with an empty span at the end of the haystack. \
+`chumsky::error::Simple` needs tokens that implement `Eq` and `Hash`, which `#![derive(Eq, Hash)]` adds.
+```
+# #[cfg(feature = "chumsky")]
+# {
+# use plexer::lexer;
+# use plexer::span::Span;
+use chumsky::prelude::*;
+
+lexer!(
+    #![derive(Eq, Hash)]
+
+    NUMBER(u32) {
+        regex!(r"[0-9]+") => |v| v.parse().map(Token::NUMBER),
+    },
+    PLUS {
+        '+' => |_| Token::PLUS,
+    },
+    #[skip]
+    WHITESPACE {
+        ' ' => |_| Token::WHITESPACE,
+    },
+);
+use lexer::Token;
+
+let number = select! { Token::NUMBER(n) => n };
+let sum = number.separated_by(just(Token::PLUS)).at_least(1).then_ignore(end());
+let sum = sum.map(|numbers: Vec<u32>| numbers.into_iter().sum::<u32>());
+
+let stream = Token::tokenize("1 + 2 + 3").to_chumsky().unwrap();
+assert_eq!(sum.parse(stream), Ok(6));
+
+let errors: Vec<Simple<Token, Span>> = sum.parse(Token::tokenize("1 + ").to_chumsky().unwrap()).unwrap_err();
+assert_eq!(errors[0].span(), Span::new(4, 4));
+
+let errors = Token::tokenize("1 ? 2 ?").to_chumsky().err().unwrap();
+assert_eq!(errors.len(), 2);
+# }
+```
+
+# Nom
+[`Tokens`] borrows a slice of spanned tokens, like the ones collected from `Lexer::spanned`,
+and [`token`] turns a function picking the tokens it accepts into a parser.
+```
+# #[cfg(feature = "nom")]
+# {
+# use plexer::lexer;
+# use plexer::span::{Span, Spanned};
+use plexer::parser::{token, Tokens};
+use nom::multi::separated_list1;
+use nom::IResult;
+
+lexer!(
+    NUMBER(u32) {
+        regex!(r"[0-9]+") => |v| v.parse().map(Token::NUMBER),
+    },
+    PLUS {
+        '+' => |_| Token::PLUS,
+    },
+    #[skip]
+    WHITESPACE {
+        ' ' => |_| Token::WHITESPACE,
+    },
+);
+use lexer::Token;
+
+fn sum<'t, 'a>(input: Tokens<'t, Token<'a>>) -> IResult<Tokens<'t, Token<'a>>, u32> {
+    let number = token(|token| match token {
+        Token::NUMBER(n) => Some(*n),
+        _ => None,
+    });
+    let plus = token(|token| (*token == Token::PLUS).then_some(()));
+    let (rest, numbers) = separated_list1(plus, number)(input)?;
+    Ok((rest, numbers.into_iter().sum()))
+}
+
+let tokens: Vec<Spanned<Token>> = Token::tokenize("1 + 2 + 3 4").spanned().collect::<Result<_, _>>().unwrap();
+let input = Tokens::new(&tokens);
+let (rest, total) = sum(input).unwrap();
+
+assert_eq!(total, 6);
+assert_eq!(input.consumed(&rest), Span::new(0, 9));
+assert_eq!(rest.span(), Some(Span::new(10, 11)));
+# }
+```
+*/
+
+#[cfg(feature = "chumsky")]
+use crate::span::{Span, Spanned};
+#[cfg(feature = "chumsky")]
+use alloc::vec::Vec;
+
+/// A `chumsky::Stream` of tokens with their [`Span`], built by [`stream`].
+#[cfg(feature = "chumsky")]
+pub type TokenStream<'a, T> = chumsky::Stream<'a, T, Span, alloc::vec::IntoIter<(T, Span)>>;
+
+/**
+Lex all the tokens into a `chumsky::Stream` ending at `end`, the length of the haystack,
+or return all the errors if there are any, like `Token::tokenize_all`.
+
+A lexer with `#![error_tokens]` never fails, its `ERROR` tokens are left to the parser.
+
+# Example
+```
+# use plexer::parser;
+# use plexer::span::{Span, Spanned};
+# use chumsky::prelude::*;
+#
+let tokens = [Ok::<_, ()>(Spanned::new('a', Span::new(0, 1))), Ok(Spanned::new('b', Span::new(2, 3)))];
+let stream = parser::stream(tokens, 3).unwrap();
+
+assert_eq!(just::<_, _, Simple<char, Span>>('a').then(just('b')).parse(stream), Ok(('a', 'b')));
+```
+*/
+#[cfg(feature = "chumsky")]
+pub fn stream<'a, T, E>(
+    tokens: impl IntoIterator<Item = Result<Spanned<T>, E>>,
+    end: usize,
+) -> Result<TokenStream<'a, T>, Vec<E>> {
+    let mut spanned = Vec::new();
+    let mut errors = Vec::new();
+    for result in tokens {
+        match result {
+            Ok(token) => spanned.push(token.into()),
+            Err(err) => errors.push(err),
+        }
+    }
+
+    if errors.is_empty() {
+        Ok(chumsky::Stream::from_iter(Span::new(end, end), spanned.into_iter()))
+    } else {
+        Err(errors)
+    }
+}
+
+#[cfg(feature = "nom")]
+pub use self::nom_input::{token, Tokens};
+
+#[cfg(feature = "nom")]
+mod nom_input {
+    use crate::span::{Span, Spanned};
+    use core::ops::{Range, RangeFrom, RangeFull, RangeTo};
+    use nom::error::{ErrorKind, ParseError};
+    use nom::{IResult, InputIter, InputLength, InputTake, Needed, Offset, Slice, UnspecializedInput};
+
+    /**
+    An input of `nom` made of spanned tokens.
+
+    It is `Copy` like `&str`, and parsers return the tokens they didn't consume,
+    which [`Tokens::consumed`] maps back to the span of the haystack they covered.
+
+    # Example
+    ```
+    # use plexer::parser::Tokens;
+    # use plexer::span::{Span, Spanned};
+    # use nom::InputTake;
+    #
+    let tokens = [Spanned::new('a', Span::new(0, 1)), Spanned::new('b', Span::new(2, 3))];
+    let input = Tokens::new(&tokens);
+    let (rest, first) = input.take_split(1);
+
+    assert_eq!(first.span(), Some(Span::new(0, 1)));
+    assert_eq!(input.consumed(&rest), Span::new(0, 1));
+    assert_eq!(rest.first(), Some(&'b'));
+    ```
+    */
+    #[derive(Debug, PartialEq, Eq, Hash)]
+    pub struct Tokens<'t, T> {
+        tokens: &'t [Spanned<T>],
+    }
+
+    impl<'t, T> Tokens<'t, T> {
+        /// Create an input from spanned tokens.
+        pub fn new(tokens: &'t [Spanned<T>]) -> Self {
+            Self { tokens }
+        }
+
+        /// Returns the tokens that are left.
+        pub fn as_slice(&self) -> &'t [Spanned<T>] {
+            self.tokens
+        }
+
+        /// Returns the next token.
+        pub fn first(&self) -> Option<&'t T> {
+            self.tokens.first().map(|token| &token.value)
+        }
+
+        /// Returns the span from the start of the first token to the end of the last one, or `None` if there are none left.
+        pub fn span(&self) -> Option<Span> {
+            let (first, last) = (self.tokens.first()?, self.tokens.last()?);
+            Some(Span::new(first.span.start, last.span.end))
+        }
+
+        /**
+        Returns the span of the tokens consumed from `self` to get `rest`, the input left by a parser.
+
+        When no token was consumed, the span is empty and at the start of `rest`.
+
+        # Panics
+        When `rest` isn't the end of `self`.
+        */
+        pub fn consumed(&self, rest: &Self) -> Span {
+            let consumed = &self.tokens[..self.offset(rest)];
+            match (consumed.first(), consumed.last()) {
+                (Some(first), Some(last)) => Span::new(first.span.start, last.span.end),
+                _ => {
+                    let start = rest.tokens.first().map_or(0, |token| token.span.start);
+                    Span::new(start, start)
+                }
+            }
+        }
+    }
+
+    // Derives would need `T: Clone` and `T: Copy`
+    impl<T> Clone for Tokens<'_, T> {
+        fn clone(&self) -> Self {
+            *self
+        }
+    }
+
+    impl<T> Copy for Tokens<'_, T> {}
+
+    impl<T> InputLength for Tokens<'_, T> {
+        fn input_len(&self) -> usize {
+            self.tokens.len()
+        }
+    }
+
+    impl<T> InputTake for Tokens<'_, T> {
+        fn take(&self, count: usize) -> Self {
+            Self::new(&self.tokens[..count])
+        }
+
+        // The rest comes first, like for `&str`
+        fn take_split(&self, count: usize) -> (Self, Self) {
+            let (taken, rest) = self.tokens.split_at(count);
+            (Self::new(rest), Self::new(taken))
+        }
+    }
+
+    impl<'t, T> InputIter for Tokens<'t, T> {
+        type Item = &'t T;
+        type Iter = core::iter::Enumerate<Self::IterElem>;
+        type IterElem = core::iter::Map<core::slice::Iter<'t, Spanned<T>>, fn(&'t Spanned<T>) -> &'t T>;
+
+        fn iter_indices(&self) -> Self::Iter {
+            self.iter_elements().enumerate()
+        }
+
+        fn iter_elements(&self) -> Self::IterElem {
+            self.tokens.iter().map(|token| &token.value)
+        }
+
+        fn position<P>(&self, predicate: P) -> Option<usize>
+        where
+            P: Fn(Self::Item) -> bool,
+        {
+            self.tokens.iter().position(|token| predicate(&token.value))
+        }
+
+        fn slice_index(&self, count: usize) -> Result<usize, Needed> {
+            match count.checked_sub(self.tokens.len()) {
+                Some(missing) if missing > 0 => Err(Needed::new(missing)),
+                _ => Ok(count),
+            }
+        }
+    }
+
+    // Gives `InputTakeAtPosition`, for parsers like `nom::bytes::complete::take_while`
+    impl<T> UnspecializedInput for Tokens<'_, T> {}
+
+    impl<T> Offset for Tokens<'_, T> {
+        fn offset(&self, second: &Self) -> usize {
+            assert!(
+                second.tokens.len() <= self.tokens.len()
+                    && core::ptr::eq(self.tokens[self.tokens.len() - second.tokens.len()..].as_ptr(), second.tokens.as_ptr()),
+                "the second input doesn't end the first one"
+            );
+            self.tokens.len() - second.tokens.len()
+        }
+    }
+
+    macro_rules! slice {
+        ($($range:ty),*) => {
+            $(impl<T> Slice<$range> for Tokens<'_, T> {
+                fn slice(&self, range: $range) -> Self {
+                    Self::new(&self.tokens[range])
+                }
+            })*
+        };
+    }
+
+    slice!(Range<usize>, RangeTo<usize>, RangeFrom<usize>, RangeFull);
+
+    /**
+    A parser of a single token, which `f` turns into the output, or rejects by returning `None`.
+
+    It fails with `ErrorKind::Eof` when there are no tokens left, and `ErrorKind::Verify` when `f` rejects the token.
+
+    # Example
+    ```
+    # use plexer::parser::{token, Tokens};
+    # use plexer::span::{Span, Spanned};
+    # use nom::IResult;
+    #
+    let tokens = [Spanned::new('a', Span::new(0, 1))];
+    let mut letter = token(|c: &char| c.is_alphabetic().then_some(*c));
+    let mut digit = token(|c: &char| c.to_digit(10));
+
+    let result: IResult<_, _> = letter(Tokens::new(&tokens));
+    assert_eq!(result.map(|(_, letter)| letter), Ok('a'));
+
+    let result: IResult<_, _> = digit(Tokens::new(&tokens));
+    assert!(result.is_err());
+    ```
+    */
+    pub fn token<'t, T, O, E>(f: impl Fn(&'t T) -> Option<O>) -> impl FnMut(Tokens<'t, T>) -> IResult<Tokens<'t, T>, O, E>
+    where
+        T: 't,
+        E: ParseError<Tokens<'t, T>>,
+    {
+        move |input: Tokens<'t, T>| {
+            let Some((first, rest)) = input.tokens.split_first() else {
+                return Err(nom::Err::Error(E::from_error_kind(input, ErrorKind::Eof)));
+            };
+            match f(&first.value) {
+                Some(output) => Ok((Tokens::new(rest), output)),
+                None => Err(nom::Err::Error(E::from_error_kind(input, ErrorKind::Verify))),
+            }
+        }
+    }
+}
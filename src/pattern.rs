@@ -6,8 +6,9 @@ For more details on implementation, see the trait [`Pattern`].
 
 # Examples
 
-[`Pattern`] is implemented for `char`, `&str`, slices of `char` and `&str`, [`Regex`](https://docs.rs/regex/latest/regex/struct.Regex.html)
-and closures implementing `Fn(&str) -> bool`.
+[`Pattern`] is implemented for `char`, `&str`, ranges and slices of `char`, slices of `&str`, [`Regex`](https://docs.rs/regex/latest/regex/struct.Regex.html)
+and closures implementing `Fn(&str) -> bool`. \
+`char`, `&str` and sets of up to three ASCII `char` are searched with [memchr](https://docs.rs/memchr), which uses SIMD when available.
 
 ```
 # use plexer::pattern::Pattern;
@@ -22,15 +23,91 @@ assert!("you".find_in(hay).is_some_and(|m| m.start == 4));
 assert!(['a', 'e', 'i', 'o', 'u'].find_in(hay).is_some_and(|m| m.start == 1));
 // array of &str pattern
 assert!(["Can", "you"].find_in(hay).is_some_and(|m| m.start == 0));
+// range of chars pattern
+assert!(('x'..='z').find_in(hay).is_some_and(|m| m.start == 4));
 // closure pattern
 assert!((|s: &str| s.starts_with("f")).find_in(hay).is_some_and(|m| m.start == 8));
 ```
+
+Slices of `char` and `&str` find the leftmost match of any of their elements, and for `&str` the longest one if several start at the same place,
+so the order of the elements doesn't matter. \
+Up to 0.1.2, they returned the match of the first element found anywhere in the haystack, even if another one matched earlier,
+this changed in 0.2.0.
+```
+# use plexer::pattern::Pattern;
+#
+let hay = "Can you find a needle in a haystack";
+
+assert!(['u', 'o', 'a'].find_in(hay).is_some_and(|m| m.start == 1));
+assert!(["you", "Can"].find_in(hay).is_some_and(|m| m.start == 0));
+assert_eq!(["=", "=="].find_in("a == b").map(|m| m.as_str()), Some("=="));
+```
+
+Owned collections, a `Vec` of `char`, `&str` or `String` and a `HashSet<char>`, are patterns too,
+for sets of symbols only known at runtime, like operators read from a configuration file.
+```
+# use plexer::pattern::Pattern;
+#
+let operators: Vec<String> = "+ - ** *".split(' ').map(String::from).collect();
+
+assert_eq!(operators.find_in("2 ** 8").map(|m| m.as_str()), Some("**"));
+assert_eq!(operators.as_slice().find_prefix_in("-1").map(|m| m.end), Some(1));
+```
+
+References, `Box`, `Rc` and `Arc` of a pattern match like it, to share a pattern or store patterns of different types.
+Unlike for `Rc` and `Arc`, `&P` and `Box<P>` aren't implemented for any `P`, since they would overlap with closures,
+but for every pattern of this module, `Rc`, `Arc` and `dyn for<'h> Pattern<'h>`, what a lexer stores.
+```
+# use plexer::pattern::{Many1, Or, Pattern, Then};
+# use std::rc::Rc;
+#
+let patterns: Vec<Box<dyn for<'h> Pattern<'h>>> = vec![Box::new("=="), Box::new(Many1('=')), Box::new(['<', '>'])];
+let shared = Rc::new(["let", "in"]);
+
+assert_eq!(Or(&*patterns[0], &*patterns[1]).find_prefix_in("===").map(|m| m.end), Some(3));
+assert!(patterns[2].find_in("a < b").is_some());
+assert_eq!(Rc::clone(&shared).find_in("let x in y").map(|m| m.start), Some(0));
+assert_eq!(Then(&shared, ' ').rev_find_in("let x in y").map(|m| m.as_str()), Some("in "));
+```
+
+Patterns compose with [`Then`], [`Or`] and [`Not`], repeat with [`Optional`], [`Repeat`] and [`Many1`],
+[`Until`] and [`TakeUntil`] match up to a delimiter, [`Delimited`] matches string literals with escapes
+and [`Balanced`] matches nested delimiters, without falling back to a regex. \
+[`TakeWhile`] matches a run of `char` satisfying a predicate, much faster than a closure taking a `&str`,
+and [`NoneOf`] a run of `char` that aren't in a set, like the text of a string literal. \
+The [`classes`] module has ready-made character classes, like [`XidStart`](classes::XidStart) for identifiers.
+```
+# use plexer::pattern::{CharClass, Many1, Not, Optional, Or, Pattern, Then};
+#
+let comment = Then(Or("//", '#'), Not('\n'));
+let integer = Then(Optional('-'), Many1(CharClass::Digit));
+
+assert_eq!(comment.find_in("x = 1 # one\ny = 2").map(|m| m.as_str()), Some("# one"));
+assert_eq!(comment.find_prefix_in("// two").map(|m| m.end), Some(6));
+assert_eq!(integer.find_in("x = -12").map(|m| m.as_str()), Some("-12"));
+```
+
+Matches are always on `char` boundaries, even with multi-byte characters.
+```
+# use plexer::pattern::Pattern;
+#
+let hay = "crème brûlée";
+
+assert_eq!('è'.find_in(hay).map(|m| m.as_str()), Some("è"));
+assert_eq!('é'.rev_find_in(hay).map(|m| m.start), Some(12));
+assert_eq!((|s: &str| s.ends_with('û')).find_in(hay).map(|m| m.as_str()), Some("crème brû"));
+```
 */
 
-use regex::Regex;
+pub mod classes;
+
+use alloc::format;
+use alloc::string::String;
+use alloc::vec;
+use alloc::vec::Vec;
 
 /// Returned by [`Pattern`] on match.
-#[derive(Debug, Clone, PartialEq)]
+#[derive(Debug, Clone)]
 pub struct Match<'a> {
     /// The string that was searched in
     pub haystack: &'a str,
@@ -38,6 +115,7 @@ pub struct Match<'a> {
     pub start: usize,
     /// End of the match
     pub end: usize,
+    arena: Option<&'a crate::arena::Arena>,
 }
 
 impl<'a> Match<'a> {
@@ -61,9 +139,30 @@ impl<'a> Match<'a> {
             haystack,
             start,
             end,
+            arena: None,
         }
     }
 
+    /**
+    Returns the arena of the lexer that found the match, see [`arena`](crate::arena).
+
+    # Example
+    ```
+    # use plexer::pattern::Match;
+    #
+    assert!(Match::new("one", 0, 1).arena().is_none());
+    ```
+    */
+    pub fn arena(&self) -> Option<&'a crate::arena::Arena> {
+        self.arena
+    }
+
+    #[doc(hidden)]
+    pub fn in_arena(mut self, arena: Option<&'a crate::arena::Arena>) -> Self {
+        self.arena = arena;
+        self
+    }
+
     /**
     Returns the number of char in the match
 
@@ -78,6 +177,20 @@ impl<'a> Match<'a> {
         self.end - self.start
     }
 
+    /**
+    Returns `true` if the match is empty, which can't happen for a match built with [`Match::new`].
+
+    # Example
+    ```
+    # use plexer::pattern::Match;
+    #
+    assert!(!Match::new("one", 0, 1).is_empty());
+    ```
+    */
+    pub fn is_empty(&self) -> bool {
+        self.start == self.end
+    }
+
     /**
     Convert to to `&str`.
 
@@ -89,18 +202,49 @@ impl<'a> Match<'a> {
 
     assert_eq!(mat.as_str(), "here");
     ```
+
+    A `Match` also dereferences to `&str`, but it is tied to the `Match` instead of the haystack.
+    ```
+    # use plexer::pattern::Match;
+    #
+    let mat = Match::new("1234", 1, 3);
+
+    assert_eq!(mat.parse::<usize>(), Ok(23));
+    ```
     */
     pub fn as_str(&self) -> &'a str {
         &self.haystack[self.start..self.end]
     }
 }
 
-impl<'a> ToString for Match<'a> {
-    fn to_string(&self) -> String {
-        self.as_str().to_string()
+// The arena only matters to the builders
+impl<'a> PartialEq for Match<'a> {
+    fn eq(&self, other: &Self) -> bool {
+        self.haystack == other.haystack && self.start == other.start && self.end == other.end
+    }
+}
+
+impl<'a> core::ops::Deref for Match<'a> {
+    type Target = str;
+
+    fn deref(&self) -> &str {
+        self.as_str()
+    }
+}
+
+impl<'a> core::fmt::Display for Match<'a> {
+    fn fmt(&self, f: &mut core::fmt::Formatter) -> core::fmt::Result {
+        f.write_str(self.as_str())
     }
 }
 
+/**
+A pattern of any type, which works on every haystack and can be shared between threads, see [`Pattern`].
+
+It is a pattern itself, so lists of them can be given to [`LexerBuilder`](crate::runtime::LexerBuilder).
+*/
+pub type BoxedPattern = alloc::boxed::Box<dyn for<'h> Pattern<'h> + Send + Sync>;
+
 /**
 A string `Pattern` trait.
 
@@ -112,11 +256,89 @@ by default it is implemented for the following types.
 | ```char```                | is contained in string                  |
 | ```&str```                | is substring                            |
 | ```String```              | is substring                            |
-| ```&[char]```             | any `char` match                        |
-| ```&[&str]```             | any `&str` match                        |
+| ```&[char]```             | any `char` match, the leftmost          |
+| ```RangeInclusive<char>``` | `char` in the range                   |
+| ```Range<char>```         | `char` in the range                     |
+| ```&[&str]```             | any `&str` match, leftmost then longest |
+| ```Vec<char>```           | any `char` match                        |
+| ```Vec<&str>```           | any `&str` match                        |
+| ```Vec<String>```         | any `String` match                      |
+| ```&[String]```           | any `String` match                      |
+| ```HashSet<char>```       | any `char` match (`std` only)           |
+| ```&P```, ```Box<P>```    | `P` match                               |
+| ```Rc<P>```, ```Arc<P>``` | `P` match                               |
 | ```F: Fn(&str) -> bool``` | `F` returns `true` for substring (slow) |
 | ```Regex```               | `Regex` match substring                 |
+| ```pattern::Regex```      | `Regex` match substring (fast prefix)   |
+| ```fancy_regex::Regex```  | `Regex` match with lookaround (feature) |
+| ```Literals```            | any literal match (single pass)         |
+| ```Then<A, B>```          | `A` match followed by `B` match         |
+| ```Or<A, B>```            | `A` or `B` match, the longest           |
+| ```Not<P>```              | text up to the next `P` match           |
+| ```Until<P>```            | text up to the next `P` match, or end   |
+| ```TakeUntil<P>```        | text up to and with the next `P` match  |
+| ```Delimited<O, C>```     | text from `O` to `C`, with escapes      |
+| ```Balanced<O, C>```      | text from `O` to `C`, nested            |
+| ```TakeWhile<F>```        | longest run of `char` satisfying `F`    |
+| ```NoneOf<S>```           | longest run of `char` not in `S`        |
+| ```classes::*```          | `char` of the class, like `XidStart`    |
+| ```Optional<P>```         | `P` match or nothing                    |
+| ```Repeat<P>```           | `P` matches repeated in a range         |
+| ```Many1<P>```            | `P` matches repeated at least once      |
+| ```CharClass```           | `char` of one of the `classes::*`       |
+
+With the `fancy-regex` feature, a [`fancy_regex::Regex`](https://docs.rs/fancy-regex/latest/fancy_regex/struct.Regex.html) is a pattern too,
+for rules that need lookahead or lookbehind, like an identifier followed by `(` for a call. \
+Lexers try a pattern on the haystack from their cursor on, so a lookbehind can't see the text before the token,
+and a match that exceeds the backtracking limit of `fancy_regex` is treated as no match. \
+Unlike [`Regex`], it isn't prefiltered by the `RegexSet` of the lexer, so a pattern that doesn't start with `^` scans the rest of the haystack when it fails.
+```
+# #[cfg(feature = "fancy-regex")]
+# {
+# use plexer::lexer;
+#
+lexer!(
+    CALL(&'a str) {
+        fancy_regex::Regex::new(r"^[a-z]+(?=\()").unwrap() => |v| Token::CALL(v.as_str()),
+    },
+    VARIABLE(&'a str) {
+        regex!(r"[a-z]+") => |v| Token::VARIABLE(v.as_str()),
+    },
+    PAREN {
+        ['(', ')'] => |_| Token::PAREN,
+    },
+);
+use lexer::Token;
+
+let tokens: Vec<_> = Token::tokenize("f(x)").map(Result::unwrap).collect();
+assert_eq!(tokens, [Token::CALL("f"), Token::PAREN, Token::VARIABLE("x"), Token::PAREN]);
+# }
+```
+
+The trait is object safe, a [`BoxedPattern`] holds a pattern of any type,
+to build a list of patterns of different types while the program runs, like rules provided by plugins.
+
+# Example
+```
+# use plexer::pattern::{BoxedPattern, Many1, Pattern, Regex};
+# use plexer::runtime::LexerBuilder;
+#
+let patterns: Vec<BoxedPattern> = vec![Box::new(Many1('=')), Box::new(Regex::new("[a-z]+").unwrap()), Box::new("!=")];
+let lexer = patterns
+    .into_iter()
+    .fold(LexerBuilder::new(), |builder, pattern| builder.rule(pattern, |m| m.to_string()))
+    .skip(' ')
+    .build();
+
+let lexemes: Vec<_> = lexer.tokenize("a == b != c").map(|res| res.unwrap().value).collect();
+assert_eq!(lexemes, ["a", "==", "b", "!=", "c"]);
+```
 */
+#[diagnostic::on_unimplemented(
+    message = "`{Self}` isn't a pattern",
+    label = "this pattern of a rule is a `{Self}`",
+    note = "a pattern is a `char`, a `&str`, a `Regex` or one of the other types listed in the documentation of `Pattern`"
+)]
 pub trait Pattern<'a> {
     /**
     Find first occurence of the pattern in the given `&str`.
@@ -147,6 +369,9 @@ pub trait Pattern<'a> {
 
         while cursor > 0 {
             cursor -= 1;
+            if !haystack.is_char_boundary(cursor) {
+                continue;
+            }
             if let Some(mat) = self.find_in(&haystack[cursor..]) {
                 return Some(Match::new(haystack, cursor + mat.start, cursor + mat.end));
             }
@@ -171,33 +396,161 @@ pub trait Pattern<'a> {
     }
 
     /**
-    Find one occurrence of the pattern in the given `&str` that is suffix.
+    Find the longest occurrence of the pattern in the given `&str` that is suffix.
 
     # Examples
     ```
-    # use plexer::pattern::{Match, Pattern};
+    # use plexer::pattern::{Match, Pattern, Regex};
     #
     assert!("ab".find_suffix_in("abcd").is_none());
     assert_eq!("ab".find_suffix_in("cdab"), Some(Match::new("cdab", 2, 4)));
+    assert_eq!(Regex::new("[a-z]+").unwrap().find_suffix_in("12 abc"), Some(Match::new("12 abc", 3, 6)));
     ```
     */
     fn find_suffix_in(&self, haystack: &'a str) -> Option<Match<'a>> {
-        self.rev_find_in(haystack)
-            .filter(|mat| mat.end == haystack.len())
+        // The longest suffix is the one that starts first
+        haystack
+            .char_indices()
+            .map(|(start, _)| start)
+            .find(|&start| {
+                self.find_prefix_in(&haystack[start..])
+                    .is_some_and(|mat| mat.end == haystack.len() - start)
+            })
+            .map(|start| Match::new(haystack, start, haystack.len()))
+    }
+
+    /**
+    Returns the source of the pattern if it is a regex, which lets a lexer prefilter its rules with a single `RegexSet`.
+
+    Only [`Regex`] returns it, a plain `regex::Regex` may have been built with options its source doesn't show.
+
+    # Examples
+    ```
+    # use plexer::pattern::{Pattern, Regex};
+    #
+    assert_eq!(Regex::new("[0-9]+").unwrap().as_regex(), Some("[0-9]+"));
+    assert_eq!("ab".as_regex(), None);
+    ```
+    */
+    fn as_regex(&self) -> Option<&str> {
+        None
+    }
+
+    /**
+    Returns the bytes a match can start with, or `None` if it could start with any byte.
+
+    A lexer uses it to only try the patterns that can match the byte under its cursor.
+
+    # Examples
+    ```
+    # use plexer::pattern::{Pattern, Regex};
+    #
+    let digits = Regex::new("[0-9]+").unwrap().first_bytes().unwrap();
+
+    assert!(digits.contains(b'7') && !digits.contains(b'a'));
+    assert!(['a', 'é'].first_bytes().is_some_and(|set| set.contains(b'a') && set.contains(0xC3)));
+    assert!((|s: &str| s == "a").first_bytes().is_none());
+    ```
+    */
+    fn first_bytes(&self) -> Option<ByteSet> {
+        None
+    }
+
+    /**
+    Returns every string the pattern matches, or `None` if there are too many of them or they are unknown.
+
+    A lexer uses it to find rules that can never win, see `Token::shadowed_rules`.
+
+    # Examples
+    ```
+    # use plexer::pattern::{Pattern, Regex};
+    #
+    assert_eq!(Regex::new("if|else").unwrap().literals(), Some(vec![String::from("if"), String::from("else")]));
+    assert_eq!(['+', '-'].literals(), Some(vec![String::from("+"), String::from("-")]));
+    assert!(Regex::new("[a-z]+").unwrap().literals().is_none());
+    ```
+    */
+    fn literals(&self) -> Option<Vec<String>> {
+        None
+    }
+
+    /**
+    Returns `true` if the pattern only matches the empty string at the start of the given `&str`.
+
+    Empty matches are never returned by the other methods, a lexer uses it to report a rule that matched nothing.
+
+    # Examples
+    ```
+    # use plexer::pattern::{Pattern, Regex};
+    #
+    let re = Regex::new("a*").unwrap();
+
+    assert!(re.matches_empty("bab"));
+    assert!(!re.matches_empty("abc"));
+    assert_eq!(re.find_in("bab").map(|m| m.start), Some(1));
+    ```
+    */
+    fn matches_empty(&self, _haystack: &'a str) -> bool {
+        false
     }
 }
 
 impl<'a> Pattern<'a> for char {
     fn find_in(&self, haystack: &'a str) -> Option<Match<'a>> {
+        let found = match u8::try_from(*self) {
+            Ok(byte) if byte.is_ascii() => memchr::memchr(byte, haystack.as_bytes()),
+            _ => memchr::memmem::find(haystack.as_bytes(), self.encode_utf8(&mut [0; 4]).as_bytes()),
+        };
+        found.map(|i| Match::new(haystack, i, i + self.len_utf8()))
+    }
+
+    fn find_suffix_in(&self, haystack: &'a str) -> Option<Match<'a>> {
         haystack
-            .find(&self.to_string())
-            .map(|i| Match::new(haystack, i, i + 1))
+            .ends_with(*self)
+            .then(|| Match::new(haystack, haystack.len() - self.len_utf8(), haystack.len()))
+    }
+
+    fn first_bytes(&self) -> Option<ByteSet> {
+        Some(ByteSet::from_iter([self.encode_utf8(&mut [0; 4]).as_bytes()[0]]))
+    }
+
+    fn literals(&self) -> Option<Vec<String>> {
+        Some(vec![String::from(*self)])
     }
 }
 
 impl<'a> Pattern<'a> for [char] {
     fn find_in(&self, haystack: &'a str) -> Option<Match<'a>> {
-        self.into_iter().flat_map(|c| c.find_in(haystack)).next()
+        // ASCII bytes never appear inside a multi-byte char, so small ASCII sets can be searched as bytes
+        let bytes = haystack.as_bytes();
+        let found = match self {
+            [a] => return a.find_in(haystack),
+            [a, b] if a.is_ascii() && b.is_ascii() => memchr::memchr2(*a as u8, *b as u8, bytes),
+            [a, b, c] if a.is_ascii() && b.is_ascii() && c.is_ascii() => {
+                memchr::memchr3(*a as u8, *b as u8, *c as u8, bytes)
+            }
+            _ => haystack.char_indices().find(|(_, c)| self.contains(c)).map(|(i, _)| i),
+        };
+        found.map(|i| {
+            let len = haystack[i..].chars().next().map_or(1, char::len_utf8);
+            Match::new(haystack, i, i + len)
+        })
+    }
+
+    fn find_suffix_in(&self, haystack: &'a str) -> Option<Match<'a>> {
+        haystack
+            .chars()
+            .next_back()
+            .filter(|c| self.contains(c))
+            .map(|c| Match::new(haystack, haystack.len() - c.len_utf8(), haystack.len()))
+    }
+
+    fn first_bytes(&self) -> Option<ByteSet> {
+        Some(self.iter().map(|c| c.encode_utf8(&mut [0; 4]).as_bytes()[0]).collect())
+    }
+
+    fn literals(&self) -> Option<Vec<String>> {
+        Some(self.iter().map(|&c| String::from(c)).collect())
     }
 }
 
@@ -205,11 +558,89 @@ impl<'a, const N: usize> Pattern<'a> for [char; N] {
     fn find_in(&self, haystack: &'a str) -> Option<Match<'a>> {
         self.as_slice().find_in(haystack)
     }
+
+    fn find_suffix_in(&self, haystack: &'a str) -> Option<Match<'a>> {
+        self.as_slice().find_suffix_in(haystack)
+    }
+
+    fn first_bytes(&self) -> Option<ByteSet> {
+        self.as_slice().first_bytes()
+    }
+
+    fn literals(&self) -> Option<Vec<String>> {
+        self.as_slice().literals()
+    }
 }
 
-impl<'a, const N: usize> Pattern<'a> for &[char; N] {
+impl<'a> Pattern<'a> for core::ops::RangeInclusive<char> {
     fn find_in(&self, haystack: &'a str) -> Option<Match<'a>> {
-        self.as_slice().find_in(haystack)
+        haystack
+            .char_indices()
+            .find(|(_, c)| self.contains(c))
+            .map(|(i, c)| Match::new(haystack, i, i + c.len_utf8()))
+    }
+
+    fn find_prefix_in(&self, haystack: &'a str) -> Option<Match<'a>> {
+        haystack
+            .chars()
+            .next()
+            .filter(|c| self.contains(c))
+            .map(|c| Match::new(haystack, 0, c.len_utf8()))
+    }
+
+    fn find_suffix_in(&self, haystack: &'a str) -> Option<Match<'a>> {
+        haystack
+            .chars()
+            .next_back()
+            .filter(|c| self.contains(c))
+            .map(|c| Match::new(haystack, haystack.len() - c.len_utf8(), haystack.len()))
+    }
+
+    fn first_bytes(&self) -> Option<ByteSet> {
+        let mut set = ByteSet::new();
+        if !self.is_empty() {
+            for sequence in regex_syntax::utf8::Utf8Sequences::new(*self.start(), *self.end()) {
+                let first = sequence.as_slice()[0];
+                set.insert_range(first.start..=first.end);
+            }
+        }
+        Some(set)
+    }
+
+    fn literals(&self) -> Option<Vec<String>> {
+        // Like for regexes, too many literals are as good as unknown
+        let chars: Vec<char> = self.clone().take(257).collect();
+        (chars.len() <= 256).then(|| chars.into_iter().map(String::from).collect())
+    }
+}
+
+impl<'a> Pattern<'a> for core::ops::Range<char> {
+    fn find_in(&self, haystack: &'a str) -> Option<Match<'a>> {
+        inclusive(self).find_in(haystack)
+    }
+
+    fn find_prefix_in(&self, haystack: &'a str) -> Option<Match<'a>> {
+        inclusive(self).find_prefix_in(haystack)
+    }
+
+    fn find_suffix_in(&self, haystack: &'a str) -> Option<Match<'a>> {
+        inclusive(self).find_suffix_in(haystack)
+    }
+
+    fn first_bytes(&self) -> Option<ByteSet> {
+        inclusive(self).first_bytes()
+    }
+
+    fn literals(&self) -> Option<Vec<String>> {
+        inclusive(self).literals()
+    }
+}
+
+// The same range with its last `char`, which skips the surrogates, an empty range stays empty
+fn inclusive(range: &core::ops::Range<char>) -> core::ops::RangeInclusive<char> {
+    match range.clone().next_back() {
+        Some(last) => range.start..=last,
+        None => '\u{1}'..='\0',
     }
 }
 
@@ -217,19 +648,63 @@ impl<'a> Pattern<'a> for String {
     fn find_in(&self, haystack: &'a str) -> Option<Match<'a>> {
         self.as_str().find_in(haystack)
     }
+
+    fn find_suffix_in(&self, haystack: &'a str) -> Option<Match<'a>> {
+        self.as_str().find_suffix_in(haystack)
+    }
+
+    fn first_bytes(&self) -> Option<ByteSet> {
+        self.as_str().first_bytes()
+    }
+
+    fn literals(&self) -> Option<Vec<String>> {
+        self.as_str().literals()
+    }
 }
 
 impl<'a> Pattern<'a> for &str {
     fn find_in(&self, haystack: &'a str) -> Option<Match<'a>> {
-        haystack
-            .find(self)
+        if self.is_empty() {
+            return None;
+        }
+        memchr::memmem::find(haystack.as_bytes(), self.as_bytes())
             .map(|i| Match::new(haystack, i, i + self.len()))
     }
+
+    fn find_suffix_in(&self, haystack: &'a str) -> Option<Match<'a>> {
+        (!self.is_empty() && haystack.ends_with(*self))
+            .then(|| Match::new(haystack, haystack.len() - self.len(), haystack.len()))
+    }
+
+    fn first_bytes(&self) -> Option<ByteSet> {
+        Some(self.as_bytes().first().copied().into_iter().collect())
+    }
+
+    fn literals(&self) -> Option<Vec<String>> {
+        Some((!self.is_empty()).then(|| String::from(*self)).into_iter().collect())
+    }
 }
 
 impl<'a> Pattern<'a> for [&str] {
     fn find_in(&self, haystack: &'a str) -> Option<Match<'a>> {
-        self.into_iter().flat_map(|s| s.find_in(haystack)).next()
+        // Take the leftmost match, and the longest one if several start at the same place
+        self.iter()
+            .flat_map(|s| s.find_in(haystack))
+            .min_by_key(|mat| (mat.start, core::cmp::Reverse(mat.len())))
+    }
+
+    fn find_suffix_in(&self, haystack: &'a str) -> Option<Match<'a>> {
+        self.iter()
+            .flat_map(|s| s.find_suffix_in(haystack))
+            .min_by_key(|mat| mat.start)
+    }
+
+    fn first_bytes(&self) -> Option<ByteSet> {
+        Some(self.iter().filter_map(|s| s.as_bytes().first().copied()).collect())
+    }
+
+    fn literals(&self) -> Option<Vec<String>> {
+        Some(self.iter().filter(|s| !s.is_empty()).map(|&s| String::from(s)).collect())
     }
 }
 
@@ -237,40 +712,1314 @@ impl<'a, const N: usize> Pattern<'a> for [&str; N] {
     fn find_in(&self, haystack: &'a str) -> Option<Match<'a>> {
         self.as_slice().find_in(haystack)
     }
+
+    fn find_suffix_in(&self, haystack: &'a str) -> Option<Match<'a>> {
+        self.as_slice().find_suffix_in(haystack)
+    }
+
+    fn first_bytes(&self) -> Option<ByteSet> {
+        self.as_slice().first_bytes()
+    }
+
+    fn literals(&self) -> Option<Vec<String>> {
+        self.as_slice().literals()
+    }
 }
 
-impl<'a, const N: usize> Pattern<'a> for &[&str; N] {
+impl<'a> Pattern<'a> for Vec<char> {
     fn find_in(&self, haystack: &'a str) -> Option<Match<'a>> {
         self.as_slice().find_in(haystack)
     }
+
+    fn find_suffix_in(&self, haystack: &'a str) -> Option<Match<'a>> {
+        self.as_slice().find_suffix_in(haystack)
+    }
+
+    fn first_bytes(&self) -> Option<ByteSet> {
+        self.as_slice().first_bytes()
+    }
+
+    fn literals(&self) -> Option<Vec<String>> {
+        self.as_slice().literals()
+    }
 }
 
-impl<'a: 'b, 'b, F> Pattern<'a> for F
-where
-    F: Fn(&'b str) -> bool,
-{
+impl<'a> Pattern<'a> for Vec<&str> {
     fn find_in(&self, haystack: &'a str) -> Option<Match<'a>> {
-        let mut cur_1 = 0;
-        // The goal is to check from left to right and to take the largest match
-        while cur_1 < haystack.len() {
-            let mut cur_2 = haystack.len();
-            while cur_2 > cur_1 {
-                let sub = &haystack[cur_1..cur_2];
-                if (self)(sub) {
-                    return Some(Match::new(haystack, cur_1, cur_2));
-                }
-                cur_2 -= 1
-            }
-            cur_1 += 1;
-        }
+        self.as_slice().find_in(haystack)
+    }
 
-        None
+    fn find_suffix_in(&self, haystack: &'a str) -> Option<Match<'a>> {
+        self.as_slice().find_suffix_in(haystack)
+    }
+
+    fn first_bytes(&self) -> Option<ByteSet> {
+        self.as_slice().first_bytes()
+    }
+
+    fn literals(&self) -> Option<Vec<String>> {
+        self.as_slice().literals()
     }
 }
 
-impl<'a> Pattern<'a> for Regex {
+impl<'a> Pattern<'a> for [String] {
     fn find_in(&self, haystack: &'a str) -> Option<Match<'a>> {
-        self.find(haystack)
-            .map(|m| Match::new(haystack, m.start(), m.end()))
+        // Like for `[&str]`, the leftmost match and the longest one at the same place
+        self.iter()
+            .flat_map(|s| s.find_in(haystack))
+            .min_by_key(|mat| (mat.start, core::cmp::Reverse(mat.len())))
+    }
+
+    fn find_suffix_in(&self, haystack: &'a str) -> Option<Match<'a>> {
+        self.iter()
+            .flat_map(|s| s.find_suffix_in(haystack))
+            .min_by_key(|mat| mat.start)
+    }
+
+    fn first_bytes(&self) -> Option<ByteSet> {
+        Some(self.iter().filter_map(|s| s.as_bytes().first().copied()).collect())
+    }
+
+    fn literals(&self) -> Option<Vec<String>> {
+        Some(self.iter().filter(|s| !s.is_empty()).cloned().collect())
+    }
+}
+
+impl<'a> Pattern<'a> for Vec<String> {
+    fn find_in(&self, haystack: &'a str) -> Option<Match<'a>> {
+        self.as_slice().find_in(haystack)
+    }
+
+    fn find_suffix_in(&self, haystack: &'a str) -> Option<Match<'a>> {
+        self.as_slice().find_suffix_in(haystack)
+    }
+
+    fn first_bytes(&self) -> Option<ByteSet> {
+        self.as_slice().first_bytes()
+    }
+
+    fn literals(&self) -> Option<Vec<String>> {
+        self.as_slice().literals()
+    }
+}
+
+#[cfg(feature = "std")]
+impl<'a, S: core::hash::BuildHasher> Pattern<'a> for std::collections::HashSet<char, S> {
+    fn find_in(&self, haystack: &'a str) -> Option<Match<'a>> {
+        haystack
+            .char_indices()
+            .find(|(_, c)| self.contains(c))
+            .map(|(i, c)| Match::new(haystack, i, i + c.len_utf8()))
+    }
+
+    fn find_prefix_in(&self, haystack: &'a str) -> Option<Match<'a>> {
+        haystack
+            .chars()
+            .next()
+            .filter(|c| self.contains(c))
+            .map(|c| Match::new(haystack, 0, c.len_utf8()))
+    }
+
+    fn find_suffix_in(&self, haystack: &'a str) -> Option<Match<'a>> {
+        haystack
+            .chars()
+            .next_back()
+            .filter(|c| self.contains(c))
+            .map(|c| Match::new(haystack, haystack.len() - c.len_utf8(), haystack.len()))
+    }
+
+    fn first_bytes(&self) -> Option<ByteSet> {
+        Some(self.iter().map(|c| c.encode_utf8(&mut [0; 4]).as_bytes()[0]).collect())
+    }
+
+    fn literals(&self) -> Option<Vec<String>> {
+        // Sorted, since the order of a `HashSet` changes from one run to another
+        let mut literals: Vec<String> = self.iter().map(|&c| String::from(c)).collect();
+        literals.sort_unstable();
+        Some(literals)
+    }
+}
+
+impl<'a: 'b, 'b, F> Pattern<'a> for F
+where
+    F: Fn(&'b str) -> bool,
+{
+    fn find_in(&self, haystack: &'a str) -> Option<Match<'a>> {
+        // The goal is to check from left to right and to take the largest match
+        for (cur_1, _) in haystack.char_indices() {
+            let mut cur_2 = haystack.len();
+            while cur_2 > cur_1 {
+                let sub = &haystack[cur_1..cur_2];
+                if (self)(sub) {
+                    return Some(Match::new(haystack, cur_1, cur_2));
+                }
+                cur_2 -= 1;
+                while !haystack.is_char_boundary(cur_2) {
+                    cur_2 -= 1;
+                }
+            }
+        }
+
+        None
+    }
+
+    fn find_suffix_in(&self, haystack: &'a str) -> Option<Match<'a>> {
+        haystack
+            .char_indices()
+            .find(|&(start, _)| (self)(&haystack[start..]))
+            .map(|(start, _)| Match::new(haystack, start, haystack.len()))
+    }
+}
+
+impl<'a> Pattern<'a> for regex::Regex {
+    fn find_in(&self, haystack: &'a str) -> Option<Match<'a>> {
+        self.find_iter(haystack)
+            .find(|m| !m.is_empty())
+            .map(|m| Match::new(haystack, m.start(), m.end()))
+    }
+
+    fn matches_empty(&self, haystack: &'a str) -> bool {
+        self.find(haystack).is_some_and(|m| m.range() == (0..0))
+    }
+}
+
+#[cfg(feature = "fancy-regex")]
+impl<'a> Pattern<'a> for fancy_regex::Regex {
+    fn find_in(&self, haystack: &'a str) -> Option<Match<'a>> {
+        // A search that runs out of backtracking ends the matches
+        self.find_iter(haystack)
+            .map_while(Result::ok)
+            .find(|m| !m.as_str().is_empty())
+            .map(|m| Match::new(haystack, m.start(), m.end()))
+    }
+
+    fn matches_empty(&self, haystack: &'a str) -> bool {
+        matches!(self.find(haystack), Ok(Some(m)) if m.range() == (0..0))
+    }
+}
+
+/**
+A [Regex](https://docs.rs/regex/latest/regex/struct.Regex.html) that is fast at finding prefixes.
+
+A plain `Regex` has to scan the whole haystack to find a prefix,
+this one keeps an anchored copy of the regex so that [`Pattern::find_prefix_in`] only looks at the start of the haystack. \
+It dereferences to the plain `Regex`, and it is what the [`regex!`](crate::regex) macro builds.
+
+# Example
+```
+# use plexer::pattern::{Pattern, Regex};
+#
+let re = Regex::new(r"[0-9]+").unwrap();
+
+assert!(re.is_match("abc 123"));
+assert_eq!(re.find_in("abc 123").map(|m| m.start), Some(4));
+assert!(re.find_prefix_in("abc 123").is_none());
+assert_eq!(re.find_prefix_in("123 abc").map(|m| m.end), Some(3));
+```
+*/
+#[derive(Debug, Clone)]
+pub struct Regex {
+    regex: regex::Regex,
+    anchored: regex::Regex,
+}
+
+impl Regex {
+    /**
+    Compile a regex, see [`regex::Regex::new`](https://docs.rs/regex/latest/regex/struct.Regex.html#method.new).
+
+    # Errors
+    When the pattern is not a valid regex.
+    ```
+    # use plexer::pattern::Regex;
+    #
+    assert!(Regex::new("(").is_err());
+    ```
+    */
+    pub fn new(pattern: &str) -> Result<Self, regex::Error> {
+        Ok(Self {
+            regex: regex::Regex::new(pattern)?,
+            anchored: regex::Regex::new(&format!("^(?:{})", pattern))?,
+        })
+    }
+}
+
+impl core::ops::Deref for Regex {
+    type Target = regex::Regex;
+
+    fn deref(&self) -> &regex::Regex {
+        &self.regex
+    }
+}
+
+impl<'a> Pattern<'a> for Regex {
+    fn find_in(&self, haystack: &'a str) -> Option<Match<'a>> {
+        self.regex.find_in(haystack)
+    }
+
+    fn find_prefix_in(&self, haystack: &'a str) -> Option<Match<'a>> {
+        self.anchored.find_in(haystack)
+    }
+
+    fn matches_empty(&self, haystack: &'a str) -> bool {
+        self.anchored.matches_empty(haystack)
+    }
+
+    fn as_regex(&self) -> Option<&str> {
+        Some(self.regex.as_str())
+    }
+
+    fn first_bytes(&self) -> Option<ByteSet> {
+        ByteSet::of_regex(self.regex.as_str(), true)
+    }
+
+    fn literals(&self) -> Option<Vec<String>> {
+        literals_of_regex(self.regex.as_str(), true)?
+            .into_iter()
+            .map(|literal| String::from_utf8(literal).ok())
+            .collect()
+    }
+}
+
+/**
+A set of literals matched in a single pass by an [Aho-Corasick](https://docs.rs/aho-corasick) automaton.
+
+It matches like a slice of `&str`, the leftmost literal wins and the longest one if several start at the same place,
+but it doesn't try each literal in turn, which pays off for large sets of keywords or operators. \
+Keyword rules of the [`lexer!`](crate::lexer) macro are matched with it. Empty literals are ignored.
+
+# Example
+```
+# use plexer::pattern::{Literals, Pattern};
+#
+let ops = Literals::new(["+", "+=", "-", "-=", "<<", "<<="]).unwrap();
+
+assert_eq!(ops.find_in("a <<= 1").map(|m| m.as_str()), Some("<<="));
+assert!(ops.find_prefix_in("a += 1").is_none());
+assert_eq!(ops.find_prefix_in("-= 1").map(|m| m.end), Some(2));
+```
+*/
+#[derive(Debug, Clone)]
+pub struct Literals {
+    automaton: aho_corasick::AhoCorasick,
+    first_bytes: ByteSet,
+    literals: Vec<String>,
+}
+
+impl Literals {
+    /**
+    Build the automaton matching the given literals.
+
+    # Errors
+    When the automaton would be too large, see [`AhoCorasick::new`](https://docs.rs/aho-corasick/latest/aho_corasick/struct.AhoCorasick.html#method.new).
+    */
+    pub fn new<I>(literals: I) -> Result<Self, aho_corasick::BuildError>
+    where
+        I: IntoIterator,
+        I::Item: AsRef<str>,
+    {
+        let literals: Vec<String> = literals
+            .into_iter()
+            .filter(|literal| !literal.as_ref().is_empty())
+            .map(|literal| String::from(literal.as_ref()))
+            .collect();
+        let first_bytes = literals.iter().map(|literal| literal.as_bytes()[0]).collect();
+        let automaton = aho_corasick::AhoCorasick::builder()
+            .match_kind(aho_corasick::MatchKind::LeftmostLongest)
+            .start_kind(aho_corasick::StartKind::Both)
+            .build(&literals)?;
+
+        Ok(Self { automaton, first_bytes, literals })
+    }
+
+    // Literals are valid UTF-8, so their matches are always on `char` boundaries
+    fn search(&self, haystack: &[u8], anchored: bool) -> Option<aho_corasick::Match> {
+        let anchored = if anchored { aho_corasick::Anchored::Yes } else { aho_corasick::Anchored::No };
+        self.automaton.find(aho_corasick::Input::new(haystack).anchored(anchored))
+    }
+}
+
+impl<'a> Pattern<'a> for Literals {
+    fn find_in(&self, haystack: &'a str) -> Option<Match<'a>> {
+        self.search(haystack.as_bytes(), false)
+            .map(|m| Match::new(haystack, m.start(), m.end()))
+    }
+
+    fn find_prefix_in(&self, haystack: &'a str) -> Option<Match<'a>> {
+        self.search(haystack.as_bytes(), true)
+            .map(|m| Match::new(haystack, m.start(), m.end()))
+    }
+
+    fn first_bytes(&self) -> Option<ByteSet> {
+        Some(self.first_bytes)
+    }
+
+    fn literals(&self) -> Option<Vec<String>> {
+        Some(self.literals.clone())
+    }
+}
+
+impl<'a> crate::bytes::BytePattern<'a> for Literals {
+    fn find_in(&self, haystack: &'a [u8]) -> Option<crate::bytes::ByteMatch<'a>> {
+        self.search(haystack, false)
+            .map(|m| crate::bytes::ByteMatch::new(haystack, m.start(), m.end()))
+    }
+
+    fn find_prefix_in(&self, haystack: &'a [u8]) -> Option<crate::bytes::ByteMatch<'a>> {
+        self.search(haystack, true)
+            .map(|m| crate::bytes::ByteMatch::new(haystack, m.start(), m.end()))
+    }
+
+    fn first_bytes(&self) -> Option<ByteSet> {
+        Some(self.first_bytes)
+    }
+
+    fn literals(&self) -> Option<Vec<Vec<u8>>> {
+        Some(self.literals.iter().map(|literal| literal.as_bytes().to_vec()).collect())
+    }
+}
+
+/**
+A pattern matching the first pattern immediately followed by the second one.
+
+The first pattern keeps the match it finds on its own, there is no backtracking into it when the second one fails. \
+A part matching the empty string, like an [`Optional`] one, can be skipped, but the whole match is never empty.
+
+# Example
+```
+# use plexer::pattern::{Pattern, Then};
+#
+let hex = Then('0', ['x', 'X']);
+
+assert_eq!(hex.find_in("a = 0X1F").map(|m| m.as_str()), Some("0X"));
+assert!(hex.find_prefix_in("01").is_none());
+assert_eq!(hex.literals(), Some(vec![String::from("0x"), String::from("0X")]));
+assert_eq!(Then("0x", |s: &str| s.chars().all(|c| c.is_ascii_hexdigit())).find_prefix_in("0x1Fz").map(|m| m.end), Some(4));
+```
+*/
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Then<A, B>(pub A, pub B);
+
+impl<'a, A: Pattern<'a>, B: Pattern<'a>> Pattern<'a> for Then<A, B> {
+    fn find_in(&self, haystack: &'a str) -> Option<Match<'a>> {
+        find_by_prefix(self, haystack)
+    }
+
+    fn find_prefix_in(&self, haystack: &'a str) -> Option<Match<'a>> {
+        let first = prefix_len(&self.0, haystack)?;
+        let second = prefix_len(&self.1, &haystack[first..])?;
+        (first + second > 0).then(|| Match::new(haystack, 0, first + second))
+    }
+
+    fn matches_empty(&self, haystack: &'a str) -> bool {
+        prefix_len(&self.0, haystack) == Some(0) && prefix_len(&self.1, haystack) == Some(0)
+    }
+
+    fn first_bytes(&self) -> Option<ByteSet> {
+        self.0.first_bytes()
+    }
+
+    fn literals(&self) -> Option<Vec<String>> {
+        let (firsts, seconds) = (self.0.literals()?, self.1.literals()?);
+        // Like for regexes, too many combinations are as good as unknown
+        if firsts.len() * seconds.len() > 256 {
+            return None;
+        }
+        Some(firsts.iter().flat_map(|first| seconds.iter().map(move |second| format!("{}{}", first, second))).collect())
+    }
+}
+
+/**
+A pattern matching either of two patterns, the longest match wins if both start at the same place.
+
+# Example
+```
+# use plexer::pattern::{Or, Pattern};
+#
+let comment = Or("//", '#');
+
+assert_eq!(comment.find_in("x # y").map(|m| m.start), Some(2));
+assert_eq!(comment.find_prefix_in("// y").map(|m| m.end), Some(2));
+assert_eq!(Or("<", "<=").find_prefix_in("<= 1").map(|m| m.as_str()), Some("<="));
+```
+*/
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Or<A, B>(pub A, pub B);
+
+impl<'a, A: Pattern<'a>, B: Pattern<'a>> Pattern<'a> for Or<A, B> {
+    fn find_in(&self, haystack: &'a str) -> Option<Match<'a>> {
+        [self.0.find_in(haystack), self.1.find_in(haystack)]
+            .into_iter()
+            .flatten()
+            .min_by_key(|mat| (mat.start, core::cmp::Reverse(mat.len())))
+    }
+
+    fn find_prefix_in(&self, haystack: &'a str) -> Option<Match<'a>> {
+        [self.0.find_prefix_in(haystack), self.1.find_prefix_in(haystack)]
+            .into_iter()
+            .flatten()
+            .max_by_key(|mat| mat.end)
+    }
+
+    fn find_suffix_in(&self, haystack: &'a str) -> Option<Match<'a>> {
+        [self.0.find_suffix_in(haystack), self.1.find_suffix_in(haystack)]
+            .into_iter()
+            .flatten()
+            .min_by_key(|mat| mat.start)
+    }
+
+    fn matches_empty(&self, haystack: &'a str) -> bool {
+        self.find_prefix_in(haystack).is_none() && (self.0.matches_empty(haystack) || self.1.matches_empty(haystack))
+    }
+
+    fn first_bytes(&self) -> Option<ByteSet> {
+        let mut set = self.0.first_bytes()?;
+        set.union(&self.1.first_bytes()?);
+        Some(set)
+    }
+
+    fn literals(&self) -> Option<Vec<String>> {
+        let mut literals = self.0.literals()?;
+        for literal in self.1.literals()? {
+            if !literals.contains(&literal) {
+                literals.push(literal);
+            }
+        }
+        Some(literals)
+    }
+}
+
+/**
+A pattern matching the text up to the next match of a pattern, at least one `char` long.
+
+It matches like `[^"]+` for a single `char`, but for any pattern, like the body of a comment up to its closing delimiter.
+
+# Example
+```
+# use plexer::pattern::{Not, Pattern, Then};
+#
+let comment = Then("(*", Then(Not("*)"), "*)"));
+
+assert_eq!(comment.find_in("1 (* a * b *) 2").map(|m| m.as_str()), Some("(* a * b *)"));
+assert_eq!(Not('"').find_prefix_in("abc\" d").map(|m| m.as_str()), Some("abc"));
+assert!(Not('"').find_prefix_in("\"abc").is_none());
+```
+*/
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Not<P>(pub P);
+
+impl<'a, P: Pattern<'a>> Pattern<'a> for Not<P> {
+    fn find_in(&self, haystack: &'a str) -> Option<Match<'a>> {
+        let start = haystack
+            .char_indices()
+            .map(|(start, _)| start)
+            .find(|&start| self.0.find_prefix_in(&haystack[start..]).is_none())?;
+        self.find_prefix_in(&haystack[start..])
+            .map(|mat| Match::new(haystack, start, start + mat.end))
+    }
+
+    fn find_prefix_in(&self, haystack: &'a str) -> Option<Match<'a>> {
+        let end = self.0.find_in(haystack).map_or(haystack.len(), |mat| mat.start);
+        (end > 0).then(|| Match::new(haystack, 0, end))
+    }
+
+    fn find_suffix_in(&self, haystack: &'a str) -> Option<Match<'a>> {
+        // The suffix starts right after the last place the pattern matches from
+        let start = self.0.rev_find_in(haystack).map_or(0, |mat| {
+            mat.start + haystack[mat.start..].chars().next().map_or(1, char::len_utf8)
+        });
+        (start < haystack.len()).then(|| Match::new(haystack, start, haystack.len()))
+    }
+}
+
+/**
+A pattern matching the text up to the next match of a delimiter, or up to the end if there is none, without the delimiter.
+
+Unlike [`Not`], it matches the empty string in front of the delimiter, so it can be an empty part of a [`Then`],
+like the text of a line comment. [`TakeUntil`] also matches the delimiter.
+
+# Example
+```
+# use plexer::pattern::{Pattern, Then, Until};
+#
+let comment = Then("//", Until('\n'));
+
+assert_eq!(comment.find_prefix_in("// note\nx").map(|m| m.as_str()), Some("// note"));
+assert_eq!(comment.find_prefix_in("//\nx").map(|m| m.as_str()), Some("//"));
+assert_eq!(comment.find_prefix_in("// end").map(|m| m.as_str()), Some("// end"));
+```
+*/
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Until<P>(pub P);
+
+impl<'a, P: Pattern<'a>> Pattern<'a> for Until<P> {
+    fn find_in(&self, haystack: &'a str) -> Option<Match<'a>> {
+        find_by_prefix(self, haystack)
+    }
+
+    fn find_prefix_in(&self, haystack: &'a str) -> Option<Match<'a>> {
+        let end = self.0.find_in(haystack).map_or(haystack.len(), |mat| mat.start);
+        (end > 0).then(|| Match::new(haystack, 0, end))
+    }
+
+    fn matches_empty(&self, haystack: &'a str) -> bool {
+        haystack.is_empty() || self.0.find_prefix_in(haystack).is_some()
+    }
+}
+
+/**
+A pattern matching the text up to and including the next match of a delimiter, which has to be found.
+
+# Example
+```
+# use plexer::pattern::{Pattern, TakeUntil, Then};
+#
+let comment = Then("<!--", TakeUntil("-->"));
+
+assert_eq!(comment.find_in("a <!-- b --> c").map(|m| m.as_str()), Some("<!-- b -->"));
+assert_eq!(comment.find_prefix_in("<!---->").map(|m| m.end), Some(7));
+assert!(comment.find_prefix_in("<!-- open").is_none());
+```
+*/
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TakeUntil<P>(pub P);
+
+impl<'a, P: Pattern<'a>> Pattern<'a> for TakeUntil<P> {
+    fn find_in(&self, haystack: &'a str) -> Option<Match<'a>> {
+        // When the delimiter is found, the text before it is part of the match
+        self.find_prefix_in(haystack)
+    }
+
+    fn find_prefix_in(&self, haystack: &'a str) -> Option<Match<'a>> {
+        self.0.find_in(haystack).map(|mat| Match::new(haystack, 0, mat.end))
+    }
+}
+
+/**
+A pattern matching delimited text, like a string literal, where an escape `char` keeps the next `char` from closing it.
+
+The text is scanned once, unlike with a closure, and there is no match when it is never closed.
+
+# Example
+```
+# use plexer::pattern::{Delimited, Pattern};
+#
+let string = Delimited { open: '"', close: '"', escape: Some('\\') };
+
+assert_eq!(string.find_prefix_in(r#""a \" b" c"#).map(|m| m.as_str()), Some(r#""a \" b""#));
+assert_eq!(string.find_in(r#"x = "\\" + y"#).map(|m| m.as_str()), Some(r#""\\""#));
+assert!(string.find_prefix_in(r#""open \""#).is_none());
+
+let raw = Delimited { open: "r#\"", close: "\"#", escape: None };
+assert_eq!(raw.find_prefix_in(r##"r#"a \"b"# c"##).map(|m| m.end), Some(10));
+```
+*/
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Delimited<O, C> {
+    /// Pattern opening the text
+    pub open: O,
+    /// Pattern closing the text
+    pub close: C,
+    /// `char` escaping the next one, which is then never part of the closing pattern
+    pub escape: Option<char>,
+}
+
+impl<'a, O: Pattern<'a>, C: Pattern<'a>> Pattern<'a> for Delimited<O, C> {
+    fn find_in(&self, haystack: &'a str) -> Option<Match<'a>> {
+        find_from(self, &self.open, haystack)
+    }
+
+    fn find_prefix_in(&self, haystack: &'a str) -> Option<Match<'a>> {
+        let mut cursor = self.open.find_prefix_in(haystack)?.end;
+        loop {
+            let rest = &haystack[cursor..];
+            if let Some(close) = self.close.find_prefix_in(rest) {
+                return Some(Match::new(haystack, 0, cursor + close.end));
+            }
+            let mut chars = rest.chars();
+            let c = chars.next()?;
+            cursor += c.len_utf8();
+            if Some(c) == self.escape {
+                cursor += chars.next()?.len_utf8();
+            }
+        }
+    }
+
+    fn first_bytes(&self) -> Option<ByteSet> {
+        self.open.first_bytes()
+    }
+}
+
+/**
+A pattern matching text between balanced delimiters, which can be nested,
+like the nested comments of Rust or the lists of Lisp.
+
+The closing pattern is tried first, so the same pattern can't nest, and there is no match when the text is never balanced.
+
+# Example
+```
+# use plexer::pattern::{Balanced, Pattern};
+#
+let comment = Balanced { open: "(*", close: "*)" };
+let list = Balanced { open: '(', close: ')' };
+
+assert_eq!(comment.find_prefix_in("(* a (* b *) c *) d *)").map(|m| m.as_str()), Some("(* a (* b *) c *)"));
+assert!(comment.find_prefix_in("(* a (* b *)").is_none());
+assert_eq!(list.find_in("'(a (b c) ()) d").map(|m| m.as_str()), Some("(a (b c) ())"));
+```
+*/
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Balanced<O, C> {
+    /// Pattern opening a level
+    pub open: O,
+    /// Pattern closing a level
+    pub close: C,
+}
+
+impl<'a, O: Pattern<'a>, C: Pattern<'a>> Pattern<'a> for Balanced<O, C> {
+    fn find_in(&self, haystack: &'a str) -> Option<Match<'a>> {
+        find_from(self, &self.open, haystack)
+    }
+
+    fn find_prefix_in(&self, haystack: &'a str) -> Option<Match<'a>> {
+        let mut cursor = self.open.find_prefix_in(haystack)?.end;
+        let mut depth = 1usize;
+        loop {
+            let rest = &haystack[cursor..];
+            if let Some(close) = self.close.find_prefix_in(rest) {
+                cursor += close.end;
+                depth -= 1;
+                if depth == 0 {
+                    return Some(Match::new(haystack, 0, cursor));
+                }
+            } else if let Some(open) = self.open.find_prefix_in(rest) {
+                cursor += open.end;
+                depth += 1;
+            } else {
+                cursor += rest.chars().next()?.len_utf8();
+            }
+        }
+    }
+
+    fn first_bytes(&self) -> Option<ByteSet> {
+        self.open.first_bytes()
+    }
+}
+
+/**
+A pattern matching the longest run of `char` satisfying a predicate, like `char::is_alphanumeric`.
+
+It looks at each `char` once, unlike a closure taking a `&str`, which is tried on every substring.
+Predicates taking a `&char`, like `char::is_ascii_digit`, are written as closures, like `|c: char| c.is_ascii_digit()`.
+
+# Example
+```
+# use plexer::pattern::{Pattern, TakeWhile};
+#
+let word = TakeWhile(char::is_alphanumeric);
+
+assert_eq!(word.find_in("  café au lait").map(|m| m.as_str()), Some("café"));
+assert!(word.find_prefix_in(" café").is_none());
+assert!(TakeWhile(|c: char| c.is_ascii_digit()).first_bytes().is_some_and(|set| set.contains(b'7') && !set.contains(b'a')));
+```
+*/
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TakeWhile<F>(pub F);
+
+impl<'a, F: Fn(char) -> bool> Pattern<'a> for TakeWhile<F> {
+    fn find_in(&self, haystack: &'a str) -> Option<Match<'a>> {
+        let (start, _) = haystack.char_indices().find(|&(_, c)| (self.0)(c))?;
+        self.find_prefix_in(&haystack[start..])
+            .map(|mat| Match::new(haystack, start, start + mat.end))
+    }
+
+    fn find_prefix_in(&self, haystack: &'a str) -> Option<Match<'a>> {
+        let end = haystack
+            .char_indices()
+            .find(|&(_, c)| !(self.0)(c))
+            .map_or(haystack.len(), |(i, _)| i);
+        (end > 0).then(|| Match::new(haystack, 0, end))
+    }
+
+    fn find_suffix_in(&self, haystack: &'a str) -> Option<Match<'a>> {
+        let start = haystack
+            .char_indices()
+            .rev()
+            .find(|&(_, c)| !(self.0)(c))
+            .map_or(0, |(i, c)| i + c.len_utf8());
+        (start < haystack.len()).then(|| Match::new(haystack, start, haystack.len()))
+    }
+
+    fn first_bytes(&self) -> Option<ByteSet> {
+        // ASCII `char` are tested, any other one could satisfy the predicate
+        let mut set: ByteSet = (0..0x80).filter(|&byte| (self.0)(char::from(byte))).collect();
+        set.insert_range(0xC2..=0xF4);
+        Some(set)
+    }
+}
+
+/**
+A pattern matching the longest run of `char` that aren't in a set, like `[^"\\\n]+` in a regex.
+
+The end of the run is searched like a slice of `char`, with `memchr` for small ASCII sets.
+
+# Example
+```
+# use plexer::pattern::{NoneOf, Pattern};
+#
+let text = NoneOf(['"', '\\', '\n']);
+
+assert_eq!(text.find_prefix_in(r#"a "b" \n"#).map(|m| m.as_str()), Some("a "));
+assert_eq!(text.find_in(r#""\é""#).map(|m| m.as_str()), Some("é"));
+assert!(text.find_prefix_in("\"").is_none());
+```
+*/
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct NoneOf<S>(pub S);
+
+impl<'a, S: AsRef<[char]>> Pattern<'a> for NoneOf<S> {
+    fn find_in(&self, haystack: &'a str) -> Option<Match<'a>> {
+        let set = self.0.as_ref();
+        let (start, _) = haystack.char_indices().find(|(_, c)| !set.contains(c))?;
+        self.find_prefix_in(&haystack[start..])
+            .map(|mat| Match::new(haystack, start, start + mat.end))
+    }
+
+    fn find_prefix_in(&self, haystack: &'a str) -> Option<Match<'a>> {
+        let end = self.0.as_ref().find_in(haystack).map_or(haystack.len(), |mat| mat.start);
+        (end > 0).then(|| Match::new(haystack, 0, end))
+    }
+
+    fn find_suffix_in(&self, haystack: &'a str) -> Option<Match<'a>> {
+        let start = self.0.as_ref().rev_find_in(haystack).map_or(0, |mat| mat.end);
+        (start < haystack.len()).then(|| Match::new(haystack, start, haystack.len()))
+    }
+
+    fn first_bytes(&self) -> Option<ByteSet> {
+        // Any byte starting a `char` that can be outside of the set
+        let set = self.0.as_ref();
+        let mut bytes: ByteSet = (0..0x80).filter(|&byte| !set.contains(&char::from(byte))).collect();
+        bytes.insert_range(0xC2..=0xF4);
+        Some(bytes)
+    }
+}
+
+/**
+A pattern matching a pattern or nothing, to be used as a part of a [`Then`].
+
+On its own it only returns the matches of the pattern, since patterns never match the empty string,
+but [`Pattern::matches_empty`] is `true` where the pattern doesn't match.
+
+# Example
+```
+# use plexer::pattern::{CharClass, Many1, Optional, Pattern, Then};
+#
+let integer = Then(Optional('-'), Many1(CharClass::Digit));
+
+assert_eq!(integer.find_prefix_in("-12 + 3").map(|m| m.as_str()), Some("-12"));
+assert_eq!(integer.find_prefix_in("12 + 3").map(|m| m.as_str()), Some("12"));
+assert!(integer.find_prefix_in("- 3").is_none());
+```
+*/
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Optional<P>(pub P);
+
+impl<'a, P: Pattern<'a>> Pattern<'a> for Optional<P> {
+    fn find_in(&self, haystack: &'a str) -> Option<Match<'a>> {
+        self.0.find_in(haystack)
+    }
+
+    fn find_prefix_in(&self, haystack: &'a str) -> Option<Match<'a>> {
+        self.0.find_prefix_in(haystack)
+    }
+
+    fn find_suffix_in(&self, haystack: &'a str) -> Option<Match<'a>> {
+        self.0.find_suffix_in(haystack)
+    }
+
+    fn matches_empty(&self, haystack: &'a str) -> bool {
+        self.0.find_prefix_in(haystack).is_none()
+    }
+}
+
+/**
+A pattern matching a pattern repeated a number of times in a range, like `{2,4}` in a regex.
+
+Repetitions are greedy, there is no backtracking to let a following pattern match.
+
+# Example
+```
+# use plexer::pattern::{CharClass, Pattern, Repeat};
+#
+let hex = Repeat::new(CharClass::HexDigit, 2..=4);
+
+assert_eq!(hex.find_prefix_in("1f2e3d").map(|m| m.as_str()), Some("1f2e"));
+assert!(hex.find_prefix_in("1g").is_none());
+assert_eq!(Repeat::new("ab", 2..).find_in("ab abab").map(|m| m.start), Some(3));
+assert!(Repeat::new('a', ..3).matches_empty("bab"));
+```
+*/
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Repeat<P> {
+    pattern: P,
+    min: usize,
+    max: usize,
+}
+
+impl<P> Repeat<P> {
+    /**
+    Repeat the pattern a number of times in the range, like `2..=4` or `1..`.
+
+    # Panics
+    When the range is empty, like ```..0``` or ```3..2```.
+
+    # Example
+    ```should_panic
+    # use plexer::pattern::Repeat;
+    #
+    let never = Repeat::new('a', 3..2);
+    ```
+    */
+    pub fn new(pattern: P, range: impl core::ops::RangeBounds<usize>) -> Self {
+        use core::ops::Bound;
+
+        let min = match range.start_bound() {
+            Bound::Included(&min) => min,
+            Bound::Excluded(&min) => min + 1,
+            Bound::Unbounded => 0,
+        };
+        let max = match range.end_bound() {
+            Bound::Included(&max) => Some(max),
+            Bound::Excluded(&max) => max.checked_sub(1),
+            Bound::Unbounded => Some(usize::MAX),
+        };
+        let max = max.filter(|&max| min <= max).expect("empty range of repetitions");
+        Self { pattern, min, max }
+    }
+
+    // The length of the repetitions at the start of the haystack, if there are enough of them
+    fn repeat<'a>(&self, haystack: &'a str) -> Option<usize>
+    where
+        P: Pattern<'a>,
+    {
+        let (mut count, mut end) = (0, 0);
+        while count < self.max {
+            match self.pattern.find_prefix_in(&haystack[end..]) {
+                Some(mat) => end += mat.end,
+                None => break,
+            }
+            count += 1;
+        }
+        (count >= self.min).then_some(end)
+    }
+}
+
+impl<'a, P: Pattern<'a>> Pattern<'a> for Repeat<P> {
+    fn find_in(&self, haystack: &'a str) -> Option<Match<'a>> {
+        find_by_prefix(self, haystack)
+    }
+
+    fn find_prefix_in(&self, haystack: &'a str) -> Option<Match<'a>> {
+        let end = self.repeat(haystack)?;
+        (end > 0).then(|| Match::new(haystack, 0, end))
+    }
+
+    fn matches_empty(&self, haystack: &'a str) -> bool {
+        self.repeat(haystack) == Some(0)
+    }
+
+    fn first_bytes(&self) -> Option<ByteSet> {
+        // Without a minimum, the pattern can match the empty string
+        if self.min > 0 {
+            self.pattern.first_bytes()
+        } else {
+            None
+        }
+    }
+}
+
+/**
+A pattern matching a pattern repeated at least once, like `+` in a regex.
+
+# Example
+```
+# use plexer::pattern::{CharClass, Many1, Pattern};
+#
+let digits = Many1(CharClass::Digit);
+
+assert_eq!(digits.find_in("abc 123 4").map(|m| m.as_str()), Some("123"));
+assert!(digits.first_bytes().is_some_and(|set| set.contains(b'7') && !set.contains(b'a')));
+```
+*/
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Many1<P>(pub P);
+
+impl<'a, P: Pattern<'a>> Pattern<'a> for Many1<P> {
+    fn find_in(&self, haystack: &'a str) -> Option<Match<'a>> {
+        // A repetition starts where the pattern first matches
+        let first = self.0.find_in(haystack)?;
+        self.find_prefix_in(&haystack[first.start..])
+            .map(|mat| Match::new(haystack, first.start, first.start + mat.end))
+    }
+
+    fn find_prefix_in(&self, haystack: &'a str) -> Option<Match<'a>> {
+        let mut end = 0;
+        while let Some(mat) = self.0.find_prefix_in(&haystack[end..]) {
+            end += mat.end;
+        }
+        (end > 0).then(|| Match::new(haystack, 0, end))
+    }
+
+    fn first_bytes(&self) -> Option<ByteSet> {
+        self.0.first_bytes()
+    }
+}
+
+/**
+A pattern matching a single `char` of one of the [`classes`], to be repeated with [`Many1`] or [`Repeat`]. \
+Each variant behaves like the unit struct of the same name, it holds the class as a value,
+like when it is read from a configuration.
+
+# Example
+```
+# use plexer::pattern::{CharClass, Pattern};
+#
+assert!(CharClass::Word.contains('_') && CharClass::Alpha.contains('é'));
+assert_eq!(CharClass::Whitespace.find_in("a\tb").map(|m| m.start), Some(1));
+assert_eq!(CharClass::HexDigit.literals().map(|literals| literals.len()), Some(22));
+```
+*/
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum CharClass {
+    /// See [`classes::Digit`]
+    Digit,
+    /// See [`classes::HexDigit`]
+    HexDigit,
+    /// See [`classes::Alpha`]
+    Alpha,
+    /// See [`classes::Alnum`]
+    Alnum,
+    /// See [`classes::Word`]
+    Word,
+    /// See [`classes::Whitespace`]
+    Whitespace,
+    /// See [`classes::XidStart`]
+    XidStart,
+    /// See [`classes::XidContinue`]
+    XidContinue,
+}
+
+impl CharClass {
+    /// Returns `true` if the `char` is in the class.
+    pub fn contains(&self, c: char) -> bool {
+        match self {
+            CharClass::Digit => classes::Digit.contains(c),
+            CharClass::HexDigit => classes::HexDigit.contains(c),
+            CharClass::Alpha => classes::Alpha.contains(c),
+            CharClass::Alnum => classes::Alnum.contains(c),
+            CharClass::Word => classes::Word.contains(c),
+            CharClass::Whitespace => classes::Whitespace.contains(c),
+            CharClass::XidStart => classes::XidStart.contains(c),
+            CharClass::XidContinue => classes::XidContinue.contains(c),
+        }
+    }
+
+    fn class(&self) -> &'static (dyn for<'h> Pattern<'h> + Send + Sync) {
+        match self {
+            CharClass::Digit => &classes::Digit,
+            CharClass::HexDigit => &classes::HexDigit,
+            CharClass::Alpha => &classes::Alpha,
+            CharClass::Alnum => &classes::Alnum,
+            CharClass::Word => &classes::Word,
+            CharClass::Whitespace => &classes::Whitespace,
+            CharClass::XidStart => &classes::XidStart,
+            CharClass::XidContinue => &classes::XidContinue,
+        }
+    }
+}
+
+impl<'a> Pattern<'a> for CharClass {
+    fn find_in(&self, haystack: &'a str) -> Option<Match<'a>> {
+        self.class().find_in(haystack)
+    }
+
+    fn find_prefix_in(&self, haystack: &'a str) -> Option<Match<'a>> {
+        self.class().find_prefix_in(haystack)
+    }
+
+    fn find_suffix_in(&self, haystack: &'a str) -> Option<Match<'a>> {
+        self.class().find_suffix_in(haystack)
+    }
+
+    fn first_bytes(&self) -> Option<ByteSet> {
+        self.class().first_bytes()
+    }
+
+    fn literals(&self) -> Option<Vec<String>> {
+        self.class().literals()
+    }
+}
+
+// References and boxes of closures are closures too, so they can't be blanket impls, the one for `F` would overlap
+macro_rules! pointers {
+    ($([$($generics:tt)*] $ty:ty),* $(,)?) => {
+        $(
+            pointers!(@forward ['p, $($generics)*] &'p $ty, $ty);
+            pointers!(@forward [$($generics)*] alloc::boxed::Box<$ty>, $ty);
+        )*
+    };
+    (@forward [$($generics:tt)*] $pointer:ty, $ty:ty) => {
+        impl<'a, $($generics)*> Pattern<'a> for $pointer
+        where
+            $ty: Pattern<'a>,
+        {
+            fn find_in(&self, haystack: &'a str) -> Option<Match<'a>> {
+                (**self).find_in(haystack)
+            }
+
+            fn rev_find_in(&self, haystack: &'a str) -> Option<Match<'a>> {
+                (**self).rev_find_in(haystack)
+            }
+
+            fn find_prefix_in(&self, haystack: &'a str) -> Option<Match<'a>> {
+                (**self).find_prefix_in(haystack)
+            }
+
+            fn find_suffix_in(&self, haystack: &'a str) -> Option<Match<'a>> {
+                (**self).find_suffix_in(haystack)
+            }
+
+            fn as_regex(&self) -> Option<&str> {
+                (**self).as_regex()
+            }
+
+            fn first_bytes(&self) -> Option<ByteSet> {
+                (**self).first_bytes()
+            }
+
+            fn literals(&self) -> Option<Vec<String>> {
+                (**self).literals()
+            }
+
+            fn matches_empty(&self, haystack: &'a str) -> bool {
+                (**self).matches_empty(haystack)
+            }
+        }
+    };
+}
+
+pointers!(@forward [P: ?Sized] alloc::rc::Rc<P>, P);
+#[cfg(target_has_atomic = "ptr")]
+pointers!(@forward [P: ?Sized] alloc::sync::Arc<P>, P);
+
+pointers!(
+    [] char,
+    [] [char],
+    [const N: usize] [char; N],
+    [] Vec<char>,
+    [] core::ops::RangeInclusive<char>,
+    [] core::ops::Range<char>,
+    [] String,
+    ['s] &'s str,
+    ['s] [&'s str],
+    ['s, const N: usize] [&'s str; N],
+    ['s] Vec<&'s str>,
+    [] [String],
+    [] Vec<String>,
+    [] regex::Regex,
+    [] Regex,
+    [] Literals,
+    [A, B] Then<A, B>,
+    [A, B] Or<A, B>,
+    [P] Not<P>,
+    [P] Until<P>,
+    [P] TakeUntil<P>,
+    [O, C] Delimited<O, C>,
+    [O, C] Balanced<O, C>,
+    [F] TakeWhile<F>,
+    [S] NoneOf<S>,
+    [P] Optional<P>,
+    [P] Repeat<P>,
+    [P] Many1<P>,
+    [] CharClass,
+    [] classes::Digit,
+    [] classes::HexDigit,
+    [] classes::Alpha,
+    [] classes::Alnum,
+    [] classes::Word,
+    [] classes::Whitespace,
+    [] classes::XidStart,
+    [] classes::XidContinue,
+    ['b] dyn for<'h> Pattern<'h> + 'b,
+    ['b] dyn for<'h> Pattern<'h> + Send + Sync + 'b,
+    [P: ?Sized] alloc::rc::Rc<P>,
+);
+
+#[cfg(target_has_atomic = "ptr")]
+pointers!([P: ?Sized] alloc::sync::Arc<P>);
+
+#[cfg(feature = "std")]
+pointers!([S] std::collections::HashSet<char, S>);
+
+#[cfg(feature = "fancy-regex")]
+pointers!([] fancy_regex::Regex);
+
+// The length of the match at the start of the haystack, `0` when the pattern only matches the empty string there
+fn prefix_len<'a>(pattern: &impl Pattern<'a>, haystack: &'a str) -> Option<usize> {
+    match pattern.find_prefix_in(haystack) {
+        Some(mat) => Some(mat.end),
+        None => pattern.matches_empty(haystack).then_some(0),
+    }
+}
+
+// The first match, tried where the pattern starting it matches
+fn find_from<'a>(pattern: &impl Pattern<'a>, start: &impl Pattern<'a>, haystack: &'a str) -> Option<Match<'a>> {
+    let mut cursor = 0;
+    while let Some(first) = start.find_in(&haystack[cursor..]) {
+        let start = cursor + first.start;
+        if let Some(mat) = pattern.find_prefix_in(&haystack[start..]) {
+            return Some(Match::new(haystack, start, start + mat.end));
+        }
+        cursor = start + haystack[start..].chars().next().map_or(1, char::len_utf8);
+    }
+
+    None
+}
+
+// The first match, tried at each `char` boundary, for patterns that can't tell where their matches start
+fn find_by_prefix<'a>(pattern: &impl Pattern<'a>, haystack: &'a str) -> Option<Match<'a>> {
+    haystack.char_indices().find_map(|(start, _)| {
+        pattern
+            .find_prefix_in(&haystack[start..])
+            .map(|mat| Match::new(haystack, start, start + mat.end))
+    })
+}
+
+/**
+A set of bytes, returned by [`Pattern::first_bytes`] to tell which bytes a match can start with.
+
+# Example
+```
+# use plexer::pattern::ByteSet;
+#
+let mut set = ByteSet::new();
+set.insert(b'_');
+set.insert_range(b'a'..=b'z');
+
+assert!(set.contains(b'_') && set.contains(b'q'));
+assert!(!set.contains(b'A'));
+```
+*/
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ByteSet {
+    bits: [u128; 2],
+}
+
+impl ByteSet {
+    /// Create an empty set.
+    pub const fn new() -> Self {
+        Self { bits: [0; 2] }
+    }
+
+    /// Add a byte to the set.
+    pub fn insert(&mut self, byte: u8) {
+        self.bits[usize::from(byte / 128)] |= 1 << (byte % 128);
+    }
+
+    /// Add an inclusive range of bytes to the set.
+    pub fn insert_range(&mut self, range: core::ops::RangeInclusive<u8>) {
+        range.for_each(|byte| self.insert(byte));
+    }
+
+    /// Returns `true` if the byte is in the set.
+    pub fn contains(&self, byte: u8) -> bool {
+        self.bits[usize::from(byte / 128)] & (1 << (byte % 128)) != 0
+    }
+
+    /// Add all the bytes of another set.
+    pub fn union(&mut self, other: &ByteSet) {
+        self.bits[0] |= other.bits[0];
+        self.bits[1] |= other.bits[1];
+    }
+
+    // The first bytes of a regex, or `None` if it can match the empty string or doesn't parse
+    pub(crate) fn of_regex(regex: &str, utf8: bool) -> Option<ByteSet> {
+        let hir = regex_syntax::ParserBuilder::new().utf8(utf8).build().parse(regex).ok()?;
+        match Self::of_hir(&hir) {
+            (set, false) => Some(set),
+            (_, true) => None,
+        }
+    }
+
+    // Returns the first bytes and whether the expression can match the empty string
+    fn of_hir(hir: &regex_syntax::hir::Hir) -> (ByteSet, bool) {
+        use regex_syntax::hir::{Class, HirKind};
+
+        let mut set = ByteSet::new();
+        match hir.kind() {
+            HirKind::Empty | HirKind::Look(_) => (set, true),
+            HirKind::Literal(literal) => match literal.0.first() {
+                Some(&byte) => {
+                    set.insert(byte);
+                    (set, false)
+                }
+                None => (set, true),
+            },
+            HirKind::Class(Class::Bytes(class)) => {
+                class.iter().for_each(|range| set.insert_range(range.start()..=range.end()));
+                (set, false)
+            }
+            HirKind::Class(Class::Unicode(class)) => {
+                for range in class.iter() {
+                    for sequence in regex_syntax::utf8::Utf8Sequences::new(range.start(), range.end()) {
+                        let first = sequence.as_slice()[0];
+                        set.insert_range(first.start..=first.end);
+                    }
+                }
+                (set, false)
+            }
+            HirKind::Repetition(repetition) => {
+                let (set, empty) = Self::of_hir(&repetition.sub);
+                (set, empty || repetition.min == 0)
+            }
+            HirKind::Capture(capture) => Self::of_hir(&capture.sub),
+            HirKind::Concat(hirs) => {
+                for hir in hirs {
+                    let (first, empty) = Self::of_hir(hir);
+                    set.union(&first);
+                    if !empty {
+                        return (set, false);
+                    }
+                }
+                (set, true)
+            }
+            HirKind::Alternation(hirs) => {
+                let mut empty = false;
+                for hir in hirs {
+                    let (first, can_be_empty) = Self::of_hir(hir);
+                    set.union(&first);
+                    empty |= can_be_empty;
+                }
+                (set, empty)
+            }
+        }
+    }
+}
+
+// The strings a regex matches, or `None` if there are too many of them or it doesn't parse
+pub(crate) fn literals_of_regex(regex: &str, utf8: bool) -> Option<Vec<Vec<u8>>> {
+    let hir = regex_syntax::ParserBuilder::new().utf8(utf8).build().parse(regex).ok()?;
+    let seq = regex_syntax::hir::literal::Extractor::new().extract(&hir);
+    if !seq.is_exact() {
+        return None;
+    }
+    let literals = seq.literals()?.iter().map(|literal| literal.as_bytes().to_vec());
+    Some(literals.filter(|literal| !literal.is_empty()).collect())
+}
+
+impl FromIterator<u8> for ByteSet {
+    fn from_iter<I: IntoIterator<Item = u8>>(iter: I) -> Self {
+        let mut set = ByteSet::new();
+        iter.into_iter().for_each(|byte| set.insert(byte));
+        set
     }
 }
@@ -0,0 +1,138 @@
+/*!
+Module for ready-made character classes. \
+
+Each class matches a single `char`, to be repeated with [`Many1`](super::Many1) or [`Repeat`](super::Repeat)
+and composed with [`Then`](super::Then), like an identifier made of an [`XidStart`] followed by any [`XidContinue`]. \
+The classes are Unicode-aware, except [`Digit`] and [`HexDigit`] which only match the ASCII digits that `str::parse` and `u32::from_str_radix` read. \
+[`CharClass`](super::CharClass) holds any of them as a value, when the class is chosen while the program runs.
+
+# Example
+```
+# use plexer::pattern::classes::{Digit, XidContinue, XidStart};
+# use plexer::pattern::{Many1, Pattern, Repeat, Then};
+#
+let identifier = Then(XidStart, Repeat::new(XidContinue, ..));
+let number = Many1(Digit);
+
+assert_eq!(identifier.find_in("1 + été2 * x").map(|m| m.as_str()), Some("été2"));
+assert_eq!(number.find_in("x = ٣ 42").map(|m| m.as_str()), Some("42"));
+```
+*/
+
+use super::{ByteSet, Match, Pattern};
+use alloc::string::String;
+use alloc::vec::Vec;
+
+macro_rules! class {
+    ($(#[$attr:meta])* $name:ident, $ascii_only:literal, $contains:expr) => {
+        $(#[$attr])*
+        #[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+        pub struct $name;
+
+        impl $name {
+            /// Returns `true` if the `char` is in the class.
+            pub fn contains(&self, c: char) -> bool {
+                let contains: fn(char) -> bool = $contains;
+                contains(c)
+            }
+        }
+
+        impl<'a> Pattern<'a> for $name {
+            fn find_in(&self, haystack: &'a str) -> Option<Match<'a>> {
+                haystack
+                    .char_indices()
+                    .find(|&(_, c)| self.contains(c))
+                    .map(|(i, c)| Match::new(haystack, i, i + c.len_utf8()))
+            }
+
+            fn find_prefix_in(&self, haystack: &'a str) -> Option<Match<'a>> {
+                haystack
+                    .chars()
+                    .next()
+                    .filter(|&c| self.contains(c))
+                    .map(|c| Match::new(haystack, 0, c.len_utf8()))
+            }
+
+            fn find_suffix_in(&self, haystack: &'a str) -> Option<Match<'a>> {
+                haystack
+                    .chars()
+                    .next_back()
+                    .filter(|&c| self.contains(c))
+                    .map(|c| Match::new(haystack, haystack.len() - c.len_utf8(), haystack.len()))
+            }
+
+            fn first_bytes(&self) -> Option<ByteSet> {
+                first_bytes(|c| self.contains(c), $ascii_only)
+            }
+
+            fn literals(&self) -> Option<Vec<String>> {
+                $ascii_only.then(|| (0..0x80u8).map(char::from).filter(|&c| self.contains(c)).map(String::from).collect())
+            }
+        }
+    };
+}
+
+class!(
+    /// ASCII digits, `0` to `9`
+    Digit,
+    true,
+    |c| c.is_ascii_digit()
+);
+
+class!(
+    /// ASCII hexadecimal digits, `0` to `9`, `a` to `f` and `A` to `F`
+    HexDigit,
+    true,
+    |c| c.is_ascii_hexdigit()
+);
+
+class!(
+    /// Alphabetic characters, see [`char::is_alphabetic`]
+    Alpha,
+    false,
+    char::is_alphabetic
+);
+
+class!(
+    /// Alphanumeric characters, see [`char::is_alphanumeric`]
+    Alnum,
+    false,
+    char::is_alphanumeric
+);
+
+class!(
+    /// Alphanumeric characters and `_`
+    Word,
+    false,
+    |c| c.is_alphanumeric() || c == '_'
+);
+
+class!(
+    /// Whitespace characters, see [`char::is_whitespace`]
+    Whitespace,
+    false,
+    char::is_whitespace
+);
+
+class!(
+    /// Characters starting an identifier, with the `XID_Start` property of Unicode
+    XidStart,
+    false,
+    unicode_ident::is_xid_start
+);
+
+class!(
+    /// Characters continuing an identifier, with the `XID_Continue` property of Unicode, like digits and `_`
+    XidContinue,
+    false,
+    unicode_ident::is_xid_continue
+);
+
+// ASCII `char` are tested, any other one could be in the class unless it is ASCII only
+fn first_bytes(contains: impl Fn(char) -> bool, ascii_only: bool) -> Option<ByteSet> {
+    let mut set: ByteSet = (0..0x80).filter(|&byte| contains(char::from(byte))).collect();
+    if !ascii_only {
+        set.insert_range(0xC2..=0xF4);
+    }
+    Some(set)
+}
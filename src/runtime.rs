@@ -0,0 +1,384 @@
+/*!
+Module for building lexers at runtime. \
+
+The [`lexer!`](crate::lexer) macro needs its rules at compile time, a [`LexerBuilder`] takes them while the program runs,
+which suits tools loading language definitions from configuration. \
+Rules follow the same logic as the macro: the longest match wins, the first rule wins on ties,
+and a rejected match falls back to the next best one.
+
+# Example
+```
+# use plexer::regex;
+# use plexer::runtime::LexerBuilder;
+# use plexer::span::{Span, Spanned};
+#
+#[derive(Debug, PartialEq)]
+enum Token {
+    Number(u32),
+    Word(String),
+}
+
+let lexer = LexerBuilder::new()
+    .try_rule(regex!("[0-9]+"), |v| v.parse().map(Token::Number))
+    .rule(regex!("[a-z]+"), |v| Token::Word(v.to_string()))
+    .skip(' ')
+    .build();
+
+let tokens: Vec<_> = lexer.tokenize("add 4 2").map(Result::unwrap).collect();
+assert_eq!(tokens[1], Spanned::new(Token::Number(4), Span::new(4, 5)));
+
+let err = lexer.tokenize("4 + 2").nth(1).unwrap().unwrap_err();
+assert_eq!(err.to_string(), "unexpected character '+' at line 1, column 3");
+```
+*/
+
+use crate::build::{parse_grammar, GrammarError, Outcome};
+use crate::pattern::{BoxedPattern, Match, Pattern, Regex};
+use crate::source::{FileId, FileSpan, SourceMap};
+use crate::span::{Position, Span, Spanned};
+use alloc::boxed::Box;
+use alloc::format;
+use alloc::string::String;
+use alloc::vec::Vec;
+
+type BoxedBuild<T> = Box<dyn for<'h> Fn(Match<'h>) -> Result<T, Option<String>> + Send + Sync>;
+type Rejection = (usize, Option<String>);
+
+struct Rule<T> {
+    pattern: BoxedPattern,
+    build: Option<BoxedBuild<T>>,
+}
+
+/// Collects the rules of a [`Lexer`], in order of priority.
+pub struct LexerBuilder<T> {
+    rules: Vec<Rule<T>>,
+}
+
+impl<T> LexerBuilder<T> {
+    /// Create a builder without any rule.
+    pub fn new() -> Self {
+        Self { rules: Vec::new() }
+    }
+
+    /**
+    Add a rule building a token from the matches of `pattern`.
+
+    # Example
+    ```
+    # use plexer::runtime::LexerBuilder;
+    #
+    let lexer = LexerBuilder::new().rule(['a', 'b'], |v| v.to_uppercase()).build();
+
+    assert_eq!(lexer.tokenize("ab").map(|res| res.unwrap().value).collect::<String>(), "AB");
+    ```
+    */
+    pub fn rule<P, F>(self, pattern: P, build: F) -> Self
+    where
+        P: for<'h> Pattern<'h> + Send + Sync + 'static,
+        F: for<'h> Fn(Match<'h>) -> T + Send + Sync + 'static,
+    {
+        self.try_rule(pattern, move |mat| Some(build(mat)))
+    }
+
+    /**
+    Add a rule whose builder can reject a match by returning an [`Option`] or a [`Result`], see [`Outcome`].
+
+    # Example
+    ```
+    # use plexer::regex;
+    # use plexer::runtime::LexerBuilder;
+    #
+    let lexer = LexerBuilder::new().try_rule(regex!("[0-9]+"), |v| v.parse::<u8>()).build();
+
+    assert!(lexer.tokenize("255").next().is_some_and(|res| res.is_ok()));
+    assert!(lexer.tokenize("256").next().is_some_and(|res| res.is_err()));
+    ```
+    */
+    pub fn try_rule<P, O, F>(mut self, pattern: P, build: F) -> Self
+    where
+        P: for<'h> Pattern<'h> + Send + Sync + 'static,
+        O: Outcome<T>,
+        F: for<'h> Fn(Match<'h>) -> O + Send + Sync + 'static,
+    {
+        self.rules.push(Rule {
+            pattern: Box::new(pattern),
+            build: Some(Box::new(move |mat| build(mat).outcome())),
+        });
+        self
+    }
+
+    /**
+    Add a rule whose matches are consumed but never yielded, like `#[skip]` in [`lexer!`](crate::lexer).
+
+    # Example
+    ```
+    # use plexer::runtime::LexerBuilder;
+    #
+    let lexer = LexerBuilder::new().rule('a', |_| 'a').skip(' ').build();
+
+    assert_eq!(lexer.tokenize(" a  a ").count(), 2);
+    ```
+    */
+    pub fn skip<P>(mut self, pattern: P) -> Self
+    where
+        P: for<'h> Pattern<'h> + Send + Sync + 'static,
+    {
+        self.rules.push(Rule { pattern: Box::new(pattern), build: None });
+        self
+    }
+
+    /// Finish the [`Lexer`].
+    pub fn build(self) -> Lexer<T> {
+        Lexer { rules: self.rules }
+    }
+}
+
+impl LexerBuilder<String> {
+    /**
+    Create a builder with the rules of a grammar file, whose tokens are the names of their rules,
+    see [`build`](crate::build#grammar-files) for the syntax.
+
+    # Errors
+    When the grammar is invalid, see [`parse_grammar`].
+
+    # Example
+    ```
+    # use plexer::runtime::LexerBuilder;
+    #
+    let lexer = LexerBuilder::from_grammar("skip SPACE 0 \\s+\nKEYWORD 2 let\nIDENTIFIER 1 [a-z]+").unwrap().build();
+    let tokens: Vec<_> = lexer.tokenize("let x").map(|res| res.unwrap().value).collect();
+
+    assert_eq!(tokens, ["KEYWORD", "IDENTIFIER"]);
+    ```
+    */
+    pub fn from_grammar(grammar: &str) -> Result<Self, GrammarError> {
+        let mut builder = Self::new();
+        for rule in parse_grammar(grammar)? {
+            // The pattern was already checked while parsing
+            let pattern = Regex::new(&rule.pattern).expect("valid regex");
+            builder = if rule.skip {
+                builder.skip(pattern)
+            } else {
+                builder.rule(pattern, move |_| rule.name.clone())
+            };
+        }
+        Ok(builder)
+    }
+}
+
+impl<T> Default for LexerBuilder<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T> core::fmt::Debug for LexerBuilder<T> {
+    fn fmt(&self, f: &mut core::fmt::Formatter) -> core::fmt::Result {
+        f.debug_struct("LexerBuilder").field("rules", &self.rules.len()).finish()
+    }
+}
+
+/// A lexer built at runtime by a [`LexerBuilder`], it can tokenize any number of haystacks.
+pub struct Lexer<T> {
+    rules: Vec<Rule<T>>,
+}
+
+impl<T> Lexer<T> {
+    /// Returns an iterator over the tokens of `haystack` and their spans.
+    pub fn tokenize<'l, 'h>(&'l self, haystack: &'h str) -> Tokens<'l, 'h, T> {
+        Tokens {
+            lexer: self,
+            haystack,
+            cursor: 0,
+            position: Position::default(),
+        }
+    }
+
+    fn best_match(&self, haystack: &str, start: usize) -> Result<(Option<T>, usize), Option<Rejection>> {
+        let mut token = None;
+        let mut len = 0;
+        let mut rejection: Option<Rejection> = None;
+
+        for rule in &self.rules {
+            let Some(mat) = rule.pattern.find_prefix_in(&haystack[start..]) else {
+                continue;
+            };
+            if mat.len() <= len {
+                continue;
+            }
+
+            let mat = Match::new(haystack, start, start + mat.len());
+            let mat_len = mat.len();
+            match rule.build.as_ref().map(|build| build(mat)) {
+                Some(Ok(built)) => {
+                    token = Some(Some(built));
+                    len = mat_len;
+                }
+                None => {
+                    token = Some(None);
+                    len = mat_len;
+                }
+                Some(Err(reason)) => {
+                    if rejection.as_ref().is_none_or(|(rejected, _)| mat_len > *rejected) {
+                        rejection = Some((mat_len, reason));
+                    }
+                }
+            }
+        }
+
+        match token {
+            Some(token) => Ok((token, len)),
+            None => Err(rejection),
+        }
+    }
+}
+
+impl<T> core::fmt::Debug for Lexer<T> {
+    fn fmt(&self, f: &mut core::fmt::Formatter) -> core::fmt::Result {
+        f.debug_struct("Lexer").field("rules", &self.rules.len()).finish()
+    }
+}
+
+/// Iterator over the tokens of a haystack, returned by [`Lexer::tokenize`].
+pub struct Tokens<'l, 'h, T> {
+    lexer: &'l Lexer<T>,
+    haystack: &'h str,
+    cursor: usize,
+    position: Position,
+}
+
+impl<'l, 'h, T> Tokens<'l, 'h, T> {
+    /// Returns the position of the next token.
+    pub fn position(&self) -> Position {
+        self.position
+    }
+}
+
+impl<'l, 'h, T> Iterator for Tokens<'l, 'h, T> {
+    type Item = Result<Spanned<T>, Error<'h>>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while self.cursor < self.haystack.len() {
+            let start = self.cursor;
+            let position = self.position;
+            let best = self.lexer.best_match(self.haystack, start);
+
+            self.cursor += match &best {
+                Ok((_, len)) => *len,
+                Err(Some((len, _))) => *len,
+                Err(None) => self.haystack[start..].chars().next().map_or(1, char::len_utf8),
+            };
+            self.position.advance(&self.haystack[start..self.cursor]);
+
+            let span = Span::new(start, self.cursor);
+            match best {
+                Ok((None, _)) => (),
+                Ok((Some(token), _)) => return Some(Ok(Spanned::new(token, span))),
+                Err(rejection) => {
+                    return Some(Err(Error {
+                        haystack: self.haystack,
+                        span,
+                        position,
+                        rejection: rejection.map(|(_, reason)| reason),
+                    }))
+                }
+            }
+        }
+
+        None
+    }
+}
+
+impl<'l, 'h, T> core::fmt::Debug for Tokens<'l, 'h, T> {
+    fn fmt(&self, f: &mut core::fmt::Formatter) -> core::fmt::Result {
+        f.debug_struct("Tokens")
+            .field("lexer", self.lexer)
+            .field("cursor", &self.cursor)
+            .field("position", &self.position)
+            .finish()
+    }
+}
+
+/// Error returned by [`Tokens`], with the same messages as the `LexerError` of [`lexer!`](crate::lexer).
+#[derive(Debug, Clone, PartialEq)]
+pub struct Error<'h> {
+    haystack: &'h str,
+    span: Span,
+    position: Position,
+    rejection: Option<Option<String>>,
+}
+
+impl<'h> Error<'h> {
+    /// Returns the span of the unexpected characters or the rejected token.
+    pub fn span(&self) -> Span {
+        self.span
+    }
+
+    /// Returns the position where the error starts.
+    pub fn position(&self) -> Position {
+        self.position
+    }
+
+    /// Returns the reason given by the builder that rejected the token, if there is one.
+    pub fn reason(&self) -> Option<&str> {
+        self.rejection.as_ref().and_then(|reason| reason.as_deref())
+    }
+
+    /// Returns the error rendered with the line of the haystack it is on, see [`Span::render`].
+    pub fn render(&self) -> String {
+        self.span.render(self.haystack, &self.message())
+    }
+
+    /**
+    Returns the error rendered with the name of its file, or `None` if the span isn't valid in this file,
+    see [`SourceMap::render`].
+
+    # Example
+    ```
+    # use plexer::runtime::LexerBuilder;
+    # use plexer::source::SourceMap;
+    #
+    let mut sources = SourceMap::new();
+    let file = sources.add("main.txt", "a?");
+    let lexer = LexerBuilder::new().rule('a', |_| ()).build();
+
+    let err = lexer.tokenize(sources.get(file).unwrap().text()).find_map(Result::err).unwrap();
+    assert_eq!(err.render_in(&sources, file).unwrap().lines().nth(1), Some(" --> main.txt:1:2"));
+    ```
+    */
+    pub fn render_in(&self, sources: &SourceMap, file: FileId) -> Option<String> {
+        sources.render(FileSpan::new(file, self.span), &self.message())
+    }
+
+    fn message(&self) -> String {
+        match self.reason() {
+            Some(reason) => format!("{}: {}", self.description(), reason),
+            None => self.description(),
+        }
+    }
+
+    fn description(&self) -> String {
+        let value = &self.haystack[self.span.as_range()];
+        match &self.rejection {
+            None if value.chars().count() > 1 => format!("unexpected characters '{}'", value),
+            None => format!("unexpected character '{}'", value),
+            Some(_) => format!("invalid token '{}'", value),
+        }
+    }
+}
+
+impl<'h> core::fmt::Display for Error<'h> {
+    fn fmt(&self, f: &mut core::fmt::Formatter) -> core::fmt::Result {
+        if f.alternate() {
+            write!(f, "{}", self.render())
+        } else {
+            write!(f, "{} at {}", self.description(), self.position)?;
+            match self.reason() {
+                Some(reason) => write!(f, ": {}", reason),
+                None => Ok(()),
+            }
+        }
+    }
+}
+
+impl<'h> core::error::Error for Error<'h> {}
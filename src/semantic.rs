@@ -0,0 +1,224 @@
+/*!
+Module for highlighting tokens in a language server. \
+
+[`encode`] turns spanned tokens into LSP semantic tokens, each one relative to the previous one:
+the line and start character deltas, the length, the type and the modifiers. \
+The caller maps each token to a [`Highlight`], which holds the indices of its type and modifiers in the legend of the server,
+or to `None` to leave it out, like whitespace. \
+[`flatten`] then gives the `data` array of `SemanticTokens`.
+
+# Example
+```
+# use plexer::lexer;
+# use plexer::semantic::{self, Encoding, Highlight, SemanticToken};
+#
+lexer!(
+    KEYWORD {
+        "let" => |_| Token::KEYWORD,
+    },
+    IDENTIFIER(&'a str) {
+        regex!(r"[a-zé]+") => |v| Token::IDENTIFIER(v.as_str()),
+    },
+    NUMBER(u32) {
+        regex!(r"[0-9]+") => |v| v.parse().map(Token::NUMBER),
+    },
+    WHITESPACE {
+        [' ', '=', '\n'] => |_| Token::WHITESPACE,
+    },
+);
+use lexer::{Token, TokenKind};
+
+// Indices in the legend, like ["keyword", "variable", "number"]
+let highlight = |token: &Token| match token.kind() {
+    TokenKind::KEYWORD => Some(Highlight::new(0)),
+    TokenKind::IDENTIFIER => Some(Highlight::new(1).with_modifiers(0b1)),
+    TokenKind::NUMBER => Some(Highlight::new(2)),
+    TokenKind::WHITESPACE => None,
+};
+
+let text = "let é = 1\nlet x = 2";
+let tokens = Token::tokenize(text).spanned().filter_map(Result::ok);
+let encoded = semantic::encode(text, tokens, Encoding::Utf16, highlight);
+
+assert_eq!(encoded[1], SemanticToken { delta_line: 0, delta_start: 4, length: 1, token_type: 1, token_modifiers_bitset: 1 });
+assert_eq!(encoded[3], SemanticToken { delta_line: 1, delta_start: 0, length: 3, token_type: 0, token_modifiers_bitset: 0 });
+assert_eq!(semantic::flatten(&encoded)[..10], [0, 0, 3, 0, 0, 0, 4, 1, 1, 1]);
+```
+*/
+
+use crate::span::Spanned;
+use alloc::vec::Vec;
+
+/// Type and modifiers of a token, as indices in the legend of the language server.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub struct Highlight {
+    /// Index of the type in the legend
+    pub token_type: u32,
+    /// Bitset of the modifiers, bit `i` for the modifier at index `i` in the legend
+    pub modifiers: u32,
+}
+
+impl Highlight {
+    /// Create a highlight with the given type and no modifiers.
+    pub fn new(token_type: u32) -> Self {
+        Self {
+            token_type,
+            modifiers: 0,
+        }
+    }
+
+    /// Set the bitset of the modifiers.
+    pub fn with_modifiers(self, modifiers: u32) -> Self {
+        Self { modifiers, ..self }
+    }
+}
+
+/// Unit of the characters and lengths, as negotiated with the client through `positionEncoding`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum Encoding {
+    /// Bytes of UTF-8
+    Utf8,
+    /// Code units of UTF-16, the default of the protocol
+    #[default]
+    Utf16,
+    /// Code points, a `char` each
+    Utf32,
+}
+
+impl Encoding {
+    // Length of the text in this unit
+    fn units(self, text: &str) -> u32 {
+        let units = match self {
+            Encoding::Utf8 => text.len(),
+            Encoding::Utf16 => text.chars().map(char::len_utf16).sum(),
+            Encoding::Utf32 => text.chars().count(),
+        };
+        units as u32
+    }
+}
+
+/// A token relative to the previous one, with the same fields as in the LSP specification.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub struct SemanticToken {
+    /// Lines since the previous token
+    pub delta_line: u32,
+    /// Start character, relative to the start of the previous token if it is on the same line
+    pub delta_start: u32,
+    /// Length of the token
+    pub length: u32,
+    /// Index of the type in the legend
+    pub token_type: u32,
+    /// Bitset of the modifiers
+    pub token_modifiers_bitset: u32,
+}
+
+/**
+Encode spanned tokens as semantic tokens, leaving out the ones `highlight` maps to `None`.
+
+Tokens have to be in order and not overlap, like the ones of `Lexer::spanned`. \
+Lines end with `\n` or `\r\n`, and a token over several lines is split in one semantic token per line,
+since clients don't have to support multiline tokens.
+
+# Panics
+When a span is out of bounds or not on `char` boundaries, or when the tokens aren't in order.
+
+# Example
+```
+# use plexer::semantic::{self, Encoding, Highlight};
+# use plexer::span::{Span, Spanned};
+#
+let text = "(* a\r\nb *) c";
+let tokens = [Spanned::new("comment", Span::new(0, 10)), Spanned::new("name", Span::new(11, 12))];
+let encoded = semantic::encode(text, tokens, Encoding::Utf8, |&token| Some(Highlight::new((token == "name") as u32)));
+
+assert_eq!(semantic::flatten(&encoded), [0, 0, 4, 0, 0, 1, 0, 4, 0, 0, 0, 5, 1, 1, 0]);
+```
+*/
+pub fn encode<T>(
+    haystack: &str,
+    tokens: impl IntoIterator<Item = Spanned<T>>,
+    encoding: Encoding,
+    mut highlight: impl FnMut(&T) -> Option<Highlight>,
+) -> Vec<SemanticToken> {
+    let mut encoder = Encoder {
+        haystack,
+        encoding,
+        cursor: 0,
+        line: 0,
+        column: 0,
+        previous: (0, 0),
+        tokens: Vec::new(),
+    };
+
+    for Spanned { value, span } in tokens {
+        let Some(highlight) = highlight(&value) else {
+            continue;
+        };
+        encoder.advance(span.start);
+        for (i, piece) in haystack[span.as_range()].split('\n').enumerate() {
+            if i > 0 {
+                encoder.advance(encoder.cursor + 1);
+            }
+            encoder.push(piece.trim_end_matches('\r'), highlight);
+            encoder.advance(encoder.cursor + piece.len());
+        }
+    }
+    encoder.tokens
+}
+
+/// Returns the `data` array of `SemanticTokens`, with five integers for each token.
+pub fn flatten(tokens: &[SemanticToken]) -> Vec<u32> {
+    tokens
+        .iter()
+        .flat_map(|token| {
+            [
+                token.delta_line,
+                token.delta_start,
+                token.length,
+                token.token_type,
+                token.token_modifiers_bitset,
+            ]
+        })
+        .collect()
+}
+
+// Keeps the line and column of the cursor, so that each byte is only counted once
+struct Encoder<'a> {
+    haystack: &'a str,
+    encoding: Encoding,
+    cursor: usize,
+    line: u32,
+    column: u32,
+    previous: (u32, u32),
+    tokens: Vec<SemanticToken>,
+}
+
+impl Encoder<'_> {
+    fn advance(&mut self, index: usize) {
+        let text = &self.haystack[self.cursor..index];
+        match text.rfind('\n') {
+            Some(last) => {
+                self.line += text.matches('\n').count() as u32;
+                self.column = self.encoding.units(&text[last + 1..]);
+            }
+            None => self.column += self.encoding.units(text),
+        }
+        self.cursor = index;
+    }
+
+    fn push(&mut self, text: &str, highlight: Highlight) {
+        let length = self.encoding.units(text);
+        if length == 0 {
+            return;
+        }
+        let (line, column) = self.previous;
+        self.tokens.push(SemanticToken {
+            delta_line: self.line - line,
+            delta_start: if self.line == line { self.column - column } else { self.column },
+            length,
+            token_type: highlight.token_type,
+            token_modifiers_bitset: highlight.modifiers,
+        });
+        self.previous = (self.line, self.column);
+    }
+}
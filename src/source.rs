@@ -0,0 +1,258 @@
+/*!
+Module for keeping track of several source files. \
+
+A [`SourceMap`] owns the name and text of each file, and gives them a [`FileId`]. \
+A [`FileSpan`] is a [`Span`] along with the file it is in, so locations are unambiguous in multi-file projects,
+and diagnostics can be rendered from the right file. \
+With the `codespan` feature, a [`SourceMap`] implements `codespan_reporting::files::Files`, to emit diagnostics with `codespan_reporting`.
+
+# Example
+```
+# use plexer::source::{FileSpan, SourceMap};
+# use plexer::span::Span;
+#
+let mut sources = SourceMap::new();
+let main = sources.add("main.txt", "let x = 1;");
+let lib = sources.add("lib.txt", "let y = $;");
+
+let span = FileSpan::new(lib, Span::new(8, 9));
+assert_eq!(sources.snippet(span), Some("$"));
+assert_eq!(sources.locate(span).as_deref(), Some("lib.txt:1:9"));
+assert_ne!(main, lib);
+```
+
+# Lexer errors
+With several files, a [`SourceMap`] names them, and `LexerError::render_in` renders an error with the name of its file.
+```
+# use plexer::lexer;
+# use plexer::source::SourceMap;
+#
+# lexer!(
+#     NUMBER(usize) {
+#         regex!(r"[0-9]+") => |v| v.parse().map(Token::NUMBER),
+#     },
+#     WHITESPACE {
+#         [' ', '\n'] => |_| Token::WHITESPACE,
+#     },
+# );
+#
+let mut sources = SourceMap::new();
+let a = sources.add("a.txt", "1 2");
+let b = sources.add("b.txt", "3\n4 ?");
+
+let text = sources.get(b).unwrap().text();
+let err = lexer::Token::tokenize(text).find_map(Result::err).unwrap();
+
+assert_eq!(err.render_in(&sources, b).unwrap().lines().nth(1), Some(" --> b.txt:2:3"));
+assert!(sources.get(a).is_some_and(|file| file.name() == "a.txt"));
+```
+
+With the `codespan` feature, `LexerError::to_diagnostic` builds a `codespan_reporting` diagnostic for a file,
+with the same code and labels, and the expected rules as a note. \
+A [`SourceMap`] then implements `codespan_reporting::files::Files` to emit it.
+```
+# #[cfg(feature = "codespan")]
+# {
+# use plexer::lexer;
+# use plexer::source::SourceMap;
+# use codespan_reporting::diagnostic::LabelStyle;
+# use codespan_reporting::files::Files;
+#
+# lexer!(
+#     NUMBER(usize) {
+#         regex!(r"[0-9]+") => |v| v.parse().map(Token::NUMBER),
+#     },
+#     WHITESPACE {
+#         [' ', '\n'] => |_| Token::WHITESPACE,
+#     },
+# );
+#
+let mut sources = SourceMap::new();
+let file = sources.add("b.txt", "3\n4 ?");
+
+let text = sources.get(file).unwrap().text();
+let diagnostic = lexer::Token::tokenize(text).find_map(Result::err).unwrap().to_diagnostic(file);
+
+assert_eq!(diagnostic.code.as_deref(), Some("plexer::unexpected"));
+assert_eq!(diagnostic.labels[0].style, LabelStyle::Primary);
+assert_eq!(diagnostic.labels[0].range, 4..5);
+assert_eq!(diagnostic.notes, ["expected NUMBER or WHITESPACE"]);
+assert_eq!(sources.location(file, 4).map(|location| location.line_number).ok(), Some(2));
+# }
+```
+*/
+
+use crate::span::{Position, Span};
+use alloc::format;
+use alloc::string::String;
+use alloc::vec::Vec;
+#[cfg(feature = "codespan")]
+use core::ops::Range;
+
+/// Identifier of a file in a [`SourceMap`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct FileId(u32);
+
+impl FileId {
+    /// Returns the index of the file, files are numbered from `0` in the order they were added.
+    pub fn index(self) -> u32 {
+        self.0
+    }
+}
+
+/// A [`Span`] in a given file.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct FileSpan {
+    /// File of the span
+    pub file: FileId,
+    /// Byte range in the file
+    pub span: Span,
+}
+
+impl FileSpan {
+    /// Create a span in a file.
+    pub fn new(file: FileId, span: Span) -> Self {
+        Self { file, span }
+    }
+}
+
+/// A file of a [`SourceMap`], with its name and text.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SourceFile {
+    name: String,
+    text: String,
+}
+
+impl SourceFile {
+    /// Returns the name of the file.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Returns the text of the file.
+    pub fn text(&self) -> &str {
+        &self.text
+    }
+}
+
+/**
+The files of a project, each one identified by a [`FileId`].
+
+# Example
+```
+# use plexer::source::{FileSpan, SourceMap};
+# use plexer::span::Span;
+#
+let mut sources = SourceMap::new();
+let id = sources.add("config.ini", "[server]\nport = ?");
+
+assert_eq!(sources.get(id).map(|file| file.name()), Some("config.ini"));
+assert_eq!(
+    sources.render(FileSpan::new(id, Span::new(16, 17)), "unexpected character '?'").unwrap(),
+    "error: unexpected character '?'\n --> config.ini:2:8\n  |\n2 | port = ?\n  |        ^"
+);
+```
+*/
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SourceMap {
+    files: Vec<SourceFile>,
+}
+
+impl SourceMap {
+    /// Create an empty source map.
+    pub const fn new() -> Self {
+        Self { files: Vec::new() }
+    }
+
+    /**
+    Add a file to the source map, and returns its identifier.
+
+    # Panics
+    When more than `u32::MAX` files are added.
+    */
+    pub fn add(&mut self, name: impl Into<String>, text: impl Into<String>) -> FileId {
+        let id = FileId(u32::try_from(self.files.len()).expect("too many files"));
+        self.files.push(SourceFile {
+            name: name.into(),
+            text: text.into(),
+        });
+        id
+    }
+
+    /// Returns the file with the given identifier, or `None` if it isn't in this source map.
+    pub fn get(&self, id: FileId) -> Option<&SourceFile> {
+        self.files.get(id.0 as usize)
+    }
+
+    /// Returns the files of the source map along with their identifiers.
+    pub fn files(&self) -> impl Iterator<Item = (FileId, &SourceFile)> {
+        (0..).map(FileId).zip(self.files.iter())
+    }
+
+    /// Returns the number of files in the source map.
+    pub fn len(&self) -> usize {
+        self.files.len()
+    }
+
+    /// Returns `true` if there are no files in the source map.
+    pub fn is_empty(&self) -> bool {
+        self.files.is_empty()
+    }
+
+    /// Returns the text covered by the span, or `None` if its file is unknown or it is out of bounds.
+    pub fn snippet(&self, span: FileSpan) -> Option<&str> {
+        self.get(span.file)?.text.get(span.span.as_range())
+    }
+
+    /// Returns the position of the start of the span in its file, or `None` if the span isn't valid.
+    pub fn position(&self, span: FileSpan) -> Option<Position> {
+        let text = &self.get(span.file)?.text;
+        text.get(span.span.as_range())?;
+        Some(Position::of(text, span.span.start))
+    }
+
+    /// Returns the location of the span as `name:line:column`, or `None` if the span isn't valid.
+    pub fn locate(&self, span: FileSpan) -> Option<String> {
+        let position = self.position(span)?;
+        Some(format!("{}:{}:{}", self.get(span.file)?.name, position.line, position.column))
+    }
+
+    /// Render a diagnostic for the span like [`Span::render`], using the name of its file, or `None` if the span isn't valid.
+    pub fn render(&self, span: FileSpan, message: &str) -> Option<String> {
+        let location = self.locate(span)?;
+        Some(span.span.render_at(&self.get(span.file)?.text, message, &location))
+    }
+}
+
+#[cfg(feature = "codespan")]
+impl<'a> codespan_reporting::files::Files<'a> for SourceMap {
+    type FileId = FileId;
+    type Name = &'a str;
+    type Source = &'a str;
+
+    fn name(&'a self, id: FileId) -> Result<&'a str, codespan_reporting::files::Error> {
+        self.get(id).map(SourceFile::name).ok_or(codespan_reporting::files::Error::FileMissing)
+    }
+
+    fn source(&'a self, id: FileId) -> Result<&'a str, codespan_reporting::files::Error> {
+        self.get(id).map(SourceFile::text).ok_or(codespan_reporting::files::Error::FileMissing)
+    }
+
+    fn line_index(&'a self, id: FileId, byte_index: usize) -> Result<usize, codespan_reporting::files::Error> {
+        let text = self.source(id)?.as_bytes();
+        Ok(memchr::memchr_iter(b'\n', &text[..byte_index.min(text.len())]).count())
+    }
+
+    fn line_range(&'a self, id: FileId, line_index: usize) -> Result<Range<usize>, codespan_reporting::files::Error> {
+        let text = self.source(id)?;
+        // The last line ends with the text
+        let mut starts = codespan_reporting::files::line_starts(text).chain(Some(text.len()));
+        match (starts.nth(line_index), starts.next()) {
+            (Some(start), Some(end)) => Ok(start..end),
+            _ => Err(codespan_reporting::files::Error::LineTooLarge {
+                given: line_index,
+                max: codespan_reporting::files::line_starts(text).count() - 1,
+            }),
+        }
+    }
+}
@@ -0,0 +1,505 @@
+/*!
+Module for locating tokens in the haystack. \
+
+A [`Span`] is the byte range covered by a token or an error, it can be used to report diagnostics back to the source. \
+A [`Position`] is the line and column of a character, which is easier to read for a human. \
+A [`Spanned`] holds a value, like a token, along with its span.
+
+# Example
+```
+# use plexer::span::{Position, Span};
+#
+let hay = "let x = 42;";
+let span = Span::new(8, 10);
+
+assert_eq!(&hay[span.as_range()], "42");
+assert_eq!(Position::of(hay, span.start).to_string(), "line 1, column 9");
+```
+
+# Spans
+Calling `Lexer::spanned` gives an iterator which yields each token in a [`Spanned`],
+along with its [`Span`] in the haystack.
+```
+# use plexer::lexer;
+# use plexer::span::{Span, Spanned};
+#
+lexer!(
+    NUMBER(usize) {
+        |s: &str| s.chars().all(|c| c.is_ascii_digit())
+            => |v| Token::NUMBER(v.parse().unwrap()),
+    },
+    WHITESPACE {
+        ' ' => |_| Token::WHITESPACE,
+    },
+);
+
+let mut lex = lexer::Token::tokenize("12 345").spanned();
+assert_eq!(lex.nth(2), Some(Ok(Spanned::new(lexer::Token::NUMBER(345), Span::new(3, 6)))));
+
+let err = lexer::Token::tokenize("1 + 2").spanned().nth(2).unwrap().unwrap_err();
+assert_eq!(err.span(), Span::new(2, 3));
+```
+
+Calling `Lexer::lexemes` instead yields each token along with the text it was built from,
+even for tokens without fields.
+```
+# use plexer::lexer;
+#
+lexer!(
+    NUMBER(usize) {
+        regex!(r"[0-9]+") => |v| v.parse().map(Token::NUMBER),
+    },
+    WHITESPACE {
+        [' ', '\t'] => |_| Token::WHITESPACE,
+    },
+);
+
+let mut lex = lexer::Token::tokenize("12\t345").lexemes();
+assert_eq!(lex.nth(1), Some(Ok((lexer::Token::WHITESPACE, "\t"))));
+```
+
+Calling `Lexer::triples` yields `(start, Token, end)` instead, which is the shape expected from an external lexer by LALRPOP.
+```
+# use plexer::lexer;
+#
+lexer!(
+    NUMBER(usize) {
+        regex!(r"[0-9]+") => |v| v.parse().map(Token::NUMBER),
+    },
+    #[skip]
+    WHITESPACE {
+        ' ' => |_| Token::WHITESPACE,
+    },
+);
+
+let mut lex = lexer::Token::tokenize("12 345").triples();
+assert_eq!(lex.nth(1), Some(Ok((3, lexer::Token::NUMBER(345), 6))));
+```
+
+Builders receive the [`Match`] in the whole haystack, so they can also make use of its position.
+```
+# use plexer::lexer;
+#
+lexer!(
+    LABEL(usize, &'a str) {
+        regex!(r"[a-z]+:") => |v| Token::LABEL(v.start, &v.as_str()[..v.len() - 1]),
+    },
+    #[skip]
+    WHITESPACE {
+        ' ' => |_| Token::WHITESPACE,
+    },
+);
+
+let mut lex = lexer::Token::tokenize("start: loop:");
+assert_eq!(lex.nth(1), Some(Ok(lexer::Token::LABEL(7, "loop"))));
+```
+
+# Positions
+The `Lexer` keeps track of the line and column it is at, `Lexer::position` and `LexerError::position` give a [`Position`].
+```
+# use plexer::lexer;
+# use plexer::span::Position;
+#
+lexer!(
+    WORD(&'a str) {
+        regex!(r"[a-z]+") => |v| Token::WORD(v.as_str()),
+    },
+    WHITESPACE {
+        [' ', '\n'] => |_| Token::WHITESPACE,
+    },
+);
+
+let mut lex = lexer::Token::tokenize("one two\nthree 4");
+lex.nth(3);
+assert_eq!(lex.position(), Position::new(2, 1));
+
+let err = lex.nth(2).unwrap().unwrap_err();
+assert_eq!(err.position(), Position::new(2, 7));
+assert_eq!(err.to_string(), "unexpected character '4' at line 2, column 7");
+```
+
+# Trivia
+`Lexer::with_trivia` returns a `TriviaLexer`, which keeps the tokens of `#[skip]` rules, like whitespace and comments,
+and yields them in a [`WithTrivia`] as the leading trivia of the next token. \
+The trivia after the last token is given by `TriviaLexer::trailing`, so formatters can keep every comment.
+```
+# use plexer::lexer;
+# use plexer::span::{Span, Spanned};
+#
+lexer!(
+    WORD(&'a str) {
+        regex!(r"[a-z]+") => |v| Token::WORD(v.as_str()),
+    },
+    #[skip]
+    COMMENT(&'a str) {
+        regex!(r"#[^\n]*") => |v| Token::COMMENT(v.as_str()),
+    },
+    #[skip]
+    WHITESPACE {
+        [' ', '\n'] => |_| Token::WHITESPACE,
+    },
+);
+use lexer::Token;
+
+let mut lex = Token::tokenize("# doc\nitem #end").with_trivia();
+
+let item = lex.next().unwrap().unwrap();
+assert_eq!(item.token, Spanned::new(Token::WORD("item"), Span::new(6, 10)));
+assert_eq!(item.leading, vec![
+    Spanned::new(Token::COMMENT("# doc"), Span::new(0, 5)),
+    Spanned::new(Token::WHITESPACE, Span::new(5, 6)),
+]);
+
+assert!(lex.next().is_none());
+assert_eq!(lex.trailing().len(), 2);
+```
+
+# Lossless
+`Lexer::lossless` returns a `LosslessLexer`, which yields every token, including the ones of `#[skip]` rules, and every error
+along with its lexeme, so no text is lost. \
+The lexemes put back together give the haystack, which `LosslessLexer::reconstruct` does for formatters and refactoring tools.
+```
+# use plexer::lexer;
+#
+lexer!(
+    WORD(&'a str) {
+        regex!(r"[a-z]+") => |v| Token::WORD(v.as_str()),
+    },
+    #[skip]
+    WHITESPACE {
+        [' ', '\n'] => |_| Token::WHITESPACE,
+    },
+);
+use lexer::Token;
+
+let lexemes: Vec<_> = Token::tokenize("one  two\n").lossless().map(|(_, lexeme)| lexeme).collect();
+assert_eq!(lexemes, vec!["one", " ", " ", "two", "\n"]);
+
+let haystack = " one ? two ";
+assert_eq!(Token::tokenize(haystack).lossless().reconstruct(), haystack);
+```
+*/
+
+use crate::pattern::Match;
+use alloc::format;
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+use core::ops::Range;
+
+/// Byte range `start..end` in a haystack.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, PartialOrd, Ord)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Span {
+    /// Start of the span
+    pub start: usize,
+    /// End of the span (exclusive)
+    pub end: usize,
+}
+
+impl Span {
+    /**
+    Create a span from a `start..end` range.
+
+    # Panics
+    When ```start > end```.
+
+    # Example
+    ```should_panic
+    # use plexer::span::Span;
+    #
+    let span = Span::new(4, 2);
+    ```
+    */
+    pub fn new(start: usize, end: usize) -> Self {
+        assert!(start <= end);
+        Self { start, end }
+    }
+
+    /**
+    Returns the number of bytes in the span.
+
+    # Example
+    ```
+    # use plexer::span::Span;
+    #
+    assert_eq!(Span::new(2, 5).len(), 3);
+    ```
+    */
+    pub fn len(&self) -> usize {
+        self.end - self.start
+    }
+
+    /**
+    Returns `true` if the span doesn't contain any byte.
+
+    # Example
+    ```
+    # use plexer::span::Span;
+    #
+    assert!(Span::new(3, 3).is_empty());
+    ```
+    */
+    pub fn is_empty(&self) -> bool {
+        self.start == self.end
+    }
+
+    /**
+    Convert to a `Range<usize>`, useful for slicing.
+
+    # Example
+    ```
+    # use plexer::span::Span;
+    #
+    assert_eq!(Span::new(1, 4).as_range(), 1..4);
+    ```
+    */
+    pub fn as_range(&self) -> Range<usize> {
+        self.start..self.end
+    }
+}
+
+impl Span {
+    /**
+    Render a diagnostic for the span in the given haystack, showing the line with a caret under the span.
+
+    # Panics
+    When the span is out of bounds or not on `char` boundaries.
+
+    # Example
+    ```
+    # use plexer::span::Span;
+    #
+    let hay = "let x = 1;\nlet y = $;";
+    let report = Span::new(19, 20).render(hay, "unexpected character '$'");
+
+    assert_eq!(
+        report,
+        "error: unexpected character '$'\n --> line 2, column 9\n  |\n2 | let y = $;\n  |         ^"
+    );
+    ```
+    */
+    pub fn render(&self, haystack: &str, message: &str) -> String {
+        self.render_at(haystack, message, &Position::of(haystack, self.start).to_string())
+    }
+
+    // Render with the given location after the arrow, like a file name and a position
+    pub(crate) fn render_at(&self, haystack: &str, message: &str, location: &str) -> String {
+        let position = Position::of(haystack, self.start);
+        let line_start = haystack[..self.start].rfind('\n').map_or(0, |i| i + 1);
+        let line_end = haystack[self.start..]
+            .find('\n')
+            .map_or(haystack.len(), |i| self.start + i);
+        let line = haystack[line_start..line_end].trim_end_matches('\r');
+
+        // Keep tabs so that the caret is aligned whatever their width
+        let indent: String = haystack[line_start..self.start]
+            .chars()
+            .map(|c| if c == '\t' { '\t' } else { ' ' })
+            .collect();
+        let carets = haystack[self.start..core::cmp::min(self.end, line_end)]
+            .chars()
+            .count()
+            .max(1);
+
+        let number = position.line.to_string();
+        let gutter = " ".repeat(number.len());
+        format!(
+            "error: {message}\n{gutter}--> {location}\n{gutter} |\n{number} | {line}\n{gutter} | {indent}{}",
+            "^".repeat(carets)
+        )
+    }
+}
+
+impl From<Range<usize>> for Span {
+    fn from(range: Range<usize>) -> Self {
+        Self::new(range.start, range.end)
+    }
+}
+
+impl From<Span> for Range<usize> {
+    fn from(span: Span) -> Self {
+        span.as_range()
+    }
+}
+
+#[cfg(feature = "miette")]
+impl From<Span> for miette::SourceSpan {
+    fn from(span: Span) -> Self {
+        span.as_range().into()
+    }
+}
+
+// Spans of tokens, given by `Lexer::to_chumsky`, are in the same haystack
+#[cfg(feature = "chumsky")]
+impl chumsky::Span for Span {
+    type Context = ();
+    type Offset = usize;
+
+    fn new((): Self::Context, range: Range<usize>) -> Self {
+        range.into()
+    }
+
+    fn context(&self) -> Self::Context {}
+
+    fn start(&self) -> Self::Offset {
+        self.start
+    }
+
+    fn end(&self) -> Self::Offset {
+        self.end
+    }
+}
+
+impl<'a> From<Match<'a>> for Span {
+    fn from(mat: Match<'a>) -> Self {
+        Self::new(mat.start, mat.end)
+    }
+}
+
+impl core::fmt::Display for Span {
+    fn fmt(&self, f: &mut core::fmt::Formatter) -> core::fmt::Result {
+        write!(f, "{}..{}", self.start, self.end)
+    }
+}
+
+/// A value along with the [`Span`] it comes from.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Spanned<T> {
+    /// The value, usually a token
+    pub value: T,
+    /// Where the value is in the haystack
+    pub span: Span,
+}
+
+impl<T> Spanned<T> {
+    /// Create a spanned value.
+    pub fn new(value: T, span: Span) -> Self {
+        Self { value, span }
+    }
+
+    /**
+    Map the value, keeping the span.
+
+    # Example
+    ```
+    # use plexer::span::{Span, Spanned};
+    #
+    let spanned = Spanned::new("42", Span::new(3, 5));
+
+    assert_eq!(spanned.map(str::len), Spanned::new(2, Span::new(3, 5)));
+    ```
+    */
+    pub fn map<U>(self, f: impl FnOnce(T) -> U) -> Spanned<U> {
+        Spanned::new(f(self.value), self.span)
+    }
+}
+
+/// A token along with the skipped tokens before it, like whitespace and comments.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash)]
+pub struct WithTrivia<T> {
+    /// The skipped tokens since the previous token
+    pub leading: Vec<Spanned<T>>,
+    /// The token itself
+    pub token: Spanned<T>,
+}
+
+impl<T> From<(T, Span)> for Spanned<T> {
+    fn from((value, span): (T, Span)) -> Self {
+        Self::new(value, span)
+    }
+}
+
+impl<T> From<Spanned<T>> for (T, Span) {
+    fn from(spanned: Spanned<T>) -> Self {
+        (spanned.value, spanned.span)
+    }
+}
+
+/// Line and column of a character in a haystack, both starting at 1.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Position {
+    /// Line of the character
+    pub line: usize,
+    /// Column of the character, counted in `char`
+    pub column: usize,
+}
+
+impl Position {
+    /**
+    Create a position from a `line` and a `column`.
+
+    # Panics
+    When ```line == 0``` or ```column == 0```.
+
+    # Example
+    ```should_panic
+    # use plexer::span::Position;
+    #
+    let pos = Position::new(0, 0);
+    ```
+    */
+    pub fn new(line: usize, column: usize) -> Self {
+        assert!(line > 0 && column > 0);
+        Self { line, column }
+    }
+
+    /**
+    Compute the position of the byte at `index` in the given `&str`.
+
+    # Panics
+    When `index` is not on a `char` boundary.
+
+    # Example
+    ```
+    # use plexer::span::Position;
+    #
+    let hay = "one\ntwo";
+
+    assert_eq!(Position::of(hay, 2), Position::new(1, 3));
+    assert_eq!(Position::of(hay, 5), Position::new(2, 2));
+    ```
+    */
+    pub fn of(haystack: &str, index: usize) -> Self {
+        let mut position = Self::default();
+        position.advance(&haystack[..index]);
+        position
+    }
+
+    /**
+    Move the position after the given `&str`, going to the next line for each `\n`.
+
+    # Example
+    ```
+    # use plexer::span::Position;
+    #
+    let mut pos = Position::new(1, 5);
+    pos.advance("é\nab");
+
+    assert_eq!(pos, Position::new(2, 3));
+    ```
+    */
+    pub fn advance(&mut self, text: &str) {
+        for c in text.chars() {
+            if c == '\n' {
+                self.line += 1;
+                self.column = 1;
+            } else {
+                self.column += 1;
+            }
+        }
+    }
+}
+
+impl Default for Position {
+    fn default() -> Self {
+        Self::new(1, 1)
+    }
+}
+
+impl core::fmt::Display for Position {
+    fn fmt(&self, f: &mut core::fmt::Formatter) -> core::fmt::Result {
+        write!(f, "line {}, column {}", self.line, self.column)
+    }
+}
@@ -0,0 +1,118 @@
+/*!
+Module for property testing lexers with `proptest`, enabled by the `proptest` feature. \
+
+[`lexemes`] generates the strings a pattern matches, from its literals or its regex,
+[`token`] generates a token of any pattern described by `Token::patterns`, with its kind,
+and [`sequence`] renders tokens into an input. \
+`Token::strategy` only keeps the tokens that lex back on their own as their kind and leave the lexer in the `INITIAL` mode,
+like an identifier matching a keyword is left out, and `Token::sequence_strategy` joins them with a separator,
+so that tokenizing the input gives back the kinds.
+
+# Example
+```
+# use plexer::lexer;
+# use proptest::prelude::*;
+#
+lexer!(
+    KEYWORD keywords ["let", "in"],
+    IDENTIFIER(&'a str) {
+        regex!(r"[a-z][a-z0-9]*") => |v| Token::IDENTIFIER(v.as_str()),
+    },
+    NUMBER(&'a str) {
+        regex!(r"[0-9]+") => |v| Token::NUMBER(v.as_str()),
+    },
+    OPERATOR {
+        ['+', '-', '='] => |_| Token::OPERATOR,
+    },
+    #[skip]
+    WHITESPACE {
+        regex!(r"[ \n]+") => |_| Token::WHITESPACE,
+    },
+);
+use lexer::Token;
+
+proptest!(|((kinds, input) in Token::sequence_strategy(0..16, " "))| {
+    let tokens: Vec<_> = Token::tokenize(&input).map(|token| token.unwrap().kind()).collect();
+    prop_assert_eq!(tokens, kinds);
+});
+```
+*/
+
+use crate::summary::RulePattern;
+use alloc::string::String;
+use alloc::vec::Vec;
+use proptest::collection::SizeRange;
+use proptest::prelude::*;
+use proptest::strategy::Union;
+
+/**
+Returns a strategy generating the strings the pattern matches,
+or `None` when its strings are unknown, like for closures, or its regex isn't supported by proptest.
+
+# Example
+```
+# use plexer::strategy;
+# use plexer::summary::RulePattern;
+# use proptest::prelude::*;
+#
+let pattern = RulePattern { kind: (), regex: Some(String::from("[a-f]{2}")), ..Default::default() };
+
+proptest!(|(lexeme in strategy::lexemes(&pattern).unwrap())| {
+    prop_assert!(lexeme.len() == 2 && lexeme.chars().all(|c| ('a'..='f').contains(&c)));
+});
+```
+*/
+pub fn lexemes<K>(pattern: &RulePattern<K>) -> Option<BoxedStrategy<String>> {
+    match (&pattern.literals, &pattern.regex) {
+        (Some(literals), _) if !literals.is_empty() => Some(proptest::sample::select(literals.clone()).boxed()),
+        (_, Some(regex)) => proptest::string::string_regex(regex).ok().map(|strategy| strategy.boxed()),
+        _ => None,
+    }
+}
+
+/**
+Returns a strategy generating a token of one of the patterns tried in the `INITIAL` mode, as its kind and lexeme.
+
+# Panics
+When none of the patterns can be generated, see [`lexemes`].
+*/
+pub fn token<K: Copy + core::fmt::Debug + 'static>(patterns: &[RulePattern<K>]) -> BoxedStrategy<(K, String)> {
+    let tokens: Vec<BoxedStrategy<(K, String)>> = patterns
+        .iter()
+        .filter(|pattern| pattern.modes.contains(&"INITIAL"))
+        .filter_map(|pattern| {
+            let kind = pattern.kind;
+            lexemes(pattern).map(|lexemes| lexemes.prop_map(move |lexeme| (kind, lexeme)).boxed())
+        })
+        .collect();
+    assert!(!tokens.is_empty(), "none of the patterns can be generated");
+    Union::new(tokens).boxed()
+}
+
+/**
+Returns a strategy generating sequences of tokens, as their kinds and the input made of their lexemes joined by `separator`.
+
+# Example
+```
+# use plexer::strategy;
+# use proptest::prelude::*;
+#
+let digit = (0..10u8).prop_map(|n| ((), n.to_string())).boxed();
+
+proptest!(|((kinds, input) in strategy::sequence(digit, 1..4, ","))| {
+    prop_assert_eq!(input.split(',').count(), kinds.len());
+});
+```
+*/
+pub fn sequence<K: core::fmt::Debug + 'static>(
+    token: BoxedStrategy<(K, String)>,
+    size: impl Into<SizeRange>,
+    separator: &'static str,
+) -> BoxedStrategy<(Vec<K>, String)> {
+    proptest::collection::vec(token, size)
+        .prop_map(move |tokens| {
+            let (kinds, lexemes): (Vec<K>, Vec<String>) = tokens.into_iter().unzip();
+            (kinds, lexemes.join(separator))
+        })
+        .boxed()
+}
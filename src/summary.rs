@@ -0,0 +1,144 @@
+/*!
+Module for describing the rules of a lexer, to publish its token grammar. \
+
+`Token::patterns` describes each pattern of the lexer in a [`RulePattern`], in order of priority:
+the rule it belongs to, the literals or the regex it matches when they are known, and the modes it is tried in. \
+[`html`] renders them as a table, and [`textmate::grammar`](crate::textmate::grammar) as a TextMate grammar.
+
+# Example
+```
+# use plexer::lexer;
+# use plexer::summary;
+#
+lexer!(
+    #![modes(STRING)]
+
+    KEYWORD keywords ["if", "else"],
+    IDENTIFIER(&'a str) {
+        regex!(r"[a-z]+") => |v| Token::IDENTIFIER(v.as_str()),
+    },
+    QUOTE {
+        '"' => |_| Token::QUOTE => push(STRING),
+    },
+    #[mode(STRING)]
+    TEXT {
+        |s: &str| !s.contains('"') => |_| Token::TEXT,
+        '"' => |_| Token::TEXT => pop,
+    },
+);
+use lexer::{Token, TokenKind};
+
+let patterns = Token::patterns();
+assert_eq!(patterns[1].kind, TokenKind::IDENTIFIER);
+assert_eq!(patterns[1].priority, 1);
+assert_eq!(patterns[0].literals, Some(vec![String::from("if"), String::from("else")]));
+assert_eq!(patterns[3].modes, ["STRING"]);
+assert_eq!(patterns[1].description(), "regex!(\"[a-z]+\")");
+assert_eq!(patterns[3].description(), "custom pattern");
+
+let html = summary::html("Tokens", &patterns);
+assert!(html.contains("<tr><td>0</td><td>KEYWORD</td><td><code>if</code>, <code>else</code></td><td>INITIAL</td></tr>"));
+```
+*/
+
+use alloc::format;
+use alloc::string::String;
+use alloc::vec::Vec;
+
+/// A pattern of a lexer, as described by `Token::patterns`.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct RulePattern<K> {
+    /// Kind of the rule of the pattern
+    pub kind: K,
+    /// Index of the rule, the first rule wins between matches of the same length
+    pub priority: usize,
+    /// Index of the pattern in its rule
+    pub arm: usize,
+    /// Source of the regex of the pattern, `None` if it isn't a regex
+    pub regex: Option<String>,
+    /// Every string the pattern matches, when there are only a few known ones
+    pub literals: Option<Vec<String>>,
+    /// Names of the modes the rule is tried in
+    pub modes: Vec<&'static str>,
+}
+
+impl<K> RulePattern<K> {
+    /**
+    Returns what the pattern matches, as its literals, its regex or `custom pattern` for closures.
+
+    # Example
+    ```
+    # use plexer::summary::RulePattern;
+    #
+    let pattern = RulePattern { kind: (), literals: Some(vec![String::from("+"), String::from("-")]), ..Default::default() };
+
+    assert_eq!(pattern.description(), "\"+\", \"-\"");
+    ```
+    */
+    pub fn description(&self) -> String {
+        // A regex like `if|else` is clearer as its literals
+        match (&self.literals, &self.regex) {
+            (Some(literals), _) => literals.iter().map(|literal| format!("{:?}", literal)).collect::<Vec<_>>().join(", "),
+            (None, Some(regex)) => format!("regex!({:?})", regex),
+            (None, None) => String::from("custom pattern"),
+        }
+    }
+}
+
+// Literals as strings, or `None` if one of them isn't valid UTF-8
+#[doc(hidden)]
+pub fn utf8_literals<'b>(literals: impl IntoIterator<Item = &'b [u8]>) -> Option<Vec<String>> {
+    literals
+        .into_iter()
+        .map(|literal| core::str::from_utf8(literal).ok().map(String::from))
+        .collect()
+}
+
+/**
+Render the patterns as an HTML table, with their priority, token, description and modes, under a title.
+
+The output is a fragment, to be embedded in a page with its own style.
+
+# Example
+```
+# use plexer::summary::{self, RulePattern};
+#
+#[derive(Debug, Default)]
+enum Kind {
+    #[default]
+    LT,
+}
+
+let patterns = [RulePattern { kind: Kind::LT, literals: Some(vec![String::from("<")]), modes: vec!["INITIAL"], ..Default::default() }];
+
+assert_eq!(
+    summary::html("Operators", &patterns),
+    "<h2>Operators</h2>\n<table>\n  <thead><tr><th>Priority</th><th>Token</th><th>Pattern</th><th>Modes</th></tr></thead>\n  <tbody>\n    <tr><td>0</td><td>LT</td><td><code>&lt;</code></td><td>INITIAL</td></tr>\n  </tbody>\n</table>\n"
+);
+```
+*/
+pub fn html<K: core::fmt::Debug>(title: &str, patterns: &[RulePattern<K>]) -> String {
+    let escape = crate::highlight::escape;
+    let mut out = format!("<h2>{}</h2>\n<table>\n", escape(title));
+    out.push_str("  <thead><tr><th>Priority</th><th>Token</th><th>Pattern</th><th>Modes</th></tr></thead>\n  <tbody>\n");
+    for pattern in patterns {
+        let description = match (&pattern.literals, &pattern.regex) {
+            (Some(literals), _) => literals
+                .iter()
+                .map(|literal| format!("<code>{}</code>", escape(literal)))
+                .collect::<Vec<_>>()
+                .join(", "),
+            (None, Some(regex)) => format!("<code>{}</code>", escape(regex)),
+            (None, None) => pattern.description(),
+        };
+        out.push_str(&format!(
+            "    <tr><td>{}</td><td>{}</td><td>{}</td><td>{}</td></tr>\n",
+            pattern.priority,
+            escape(&format!("{:?}", pattern.kind)),
+            description,
+            pattern.modes.join(", ")
+        ));
+    }
+    out.push_str("  </tbody>\n</table>\n");
+    out
+}
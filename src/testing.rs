@@ -0,0 +1,182 @@
+/*!
+Module for testing grammars. \
+
+[`assert_tokens!`](crate::assert_tokens) checks the tokens of an input in one line, naming the variants of `Token` without their path. \
+`Lexer::to_snapshot` dumps the tokens and errors in a stable textual format, one per line with its span,
+to be compared with a golden file, see [`snapshot`].
+
+# Example
+```
+# use plexer::{assert_tokens, lexer};
+#
+lexer!(
+    NUMBER(u8) {
+        regex!(r"[0-9]+") => |v| v.parse().map(Token::NUMBER),
+    },
+    OPERATOR(char) {
+        ['+', '-'] => |v| Token::OPERATOR(v.as_str().chars().next().unwrap()),
+    },
+);
+
+assert_tokens!("1+2", [NUMBER(1), OPERATOR('+'), NUMBER(2)]);
+assert_eq!(
+    lexer::Token::tokenize("1+300").to_snapshot(),
+    "0..1 NUMBER(1)\n1..2 OPERATOR('+')\n2..5 error: invalid token '300': number too large to fit in target type\n"
+);
+```
+
+# Statistics
+`Lexer::stats` consumes the `Lexer` and returns a `LexerStats`, with the number of tokens of each kind,
+the number of errors and the number of bytes consumed, to check a grammar against a corpus.
+```
+# use plexer::lexer;
+#
+lexer!(
+    NUMBER(usize) {
+        regex!(r"[0-9]+") => |v| v.parse().map(Token::NUMBER),
+    },
+    #[trivia]
+    COMMENT {
+        regex!(r"#[^\n]*") => |_| Token::COMMENT,
+    },
+    #[skip]
+    WHITESPACE {
+        [' ', '\n'] => |_| Token::WHITESPACE,
+    },
+);
+use lexer::{Token, TokenKind};
+
+let stats = Token::tokenize("1 2 # two\n3 ?").stats();
+
+assert_eq!(stats.count(TokenKind::NUMBER), 3);
+assert_eq!(stats.count(TokenKind::COMMENT), 1);
+assert_eq!((stats.tokens(), stats.errors(), stats.bytes()), (4, 1, 13));
+assert_eq!(stats.counts().collect::<Vec<_>>(), vec![(TokenKind::NUMBER, 3), (TokenKind::COMMENT, 1)]);
+```
+
+`Lexer::to_json` and `Lexer::write_csv` dump the kind, lexeme and span of each token, and the message of each error,
+to diff the output of two versions of a grammar or feed it to other tools, see [`dump`](crate::dump).
+
+# Fuzzing
+`Token::assert_invariants` lexes an input and panics if the lexer breaks an invariant:
+the cursor never goes back and reaches the end, spans are in bounds, on `char` boundaries and in order,
+and the lexemes of `Lexer::lossless` give back the input. \
+With the `arbitrary` feature, `TokenKind` implements `arbitrary::Arbitrary`, for fuzz targets generating token kinds.
+```
+# use plexer::lexer;
+#
+lexer!(
+    NUMBER(u8) {
+        regex!(r"[0-9]+") => |v| v.parse().map(Token::NUMBER),
+    },
+    PAIR {
+        ',' => |_| [Token::PAIR, Token::PAIR],
+    },
+    #[skip]
+    WHITESPACE {
+        [' ', '\n'] => |_| Token::WHITESPACE,
+    },
+);
+use lexer::{Token, TokenKind};
+
+for input in ["", "1, 2", "300 ?", "é\n,,"] {
+    Token::assert_invariants(input);
+}
+
+# #[cfg(feature = "arbitrary")]
+# {
+use arbitrary::{Arbitrary, Unstructured};
+
+let kind = TokenKind::arbitrary(&mut Unstructured::new(&[1])).unwrap();
+assert_eq!(kind, TokenKind::PAIR);
+# }
+```
+With `cargo fuzz`, a fuzz target is then a single line.
+```ignore
+libfuzzer_sys::fuzz_target!(|input: &str| lexer::Token::assert_invariants(input));
+```
+
+With the `proptest` feature, `Token::strategy` generates tokens from the literals and regexes of the rules, with their kind,
+and `Token::sequence_strategy` joins them into inputs, for round-trip properties: tokenizing the input gives back the kinds,
+see `plexer::strategy`.
+*/
+
+use crate::span::{Span, Spanned};
+use alloc::format;
+use alloc::string::String;
+
+/**
+Asserts that an input lexes to the given tokens, without any error.
+
+The variants of `Token` are in scope, so they can be written without their path. \
+The lexer is the `lexer` module by default, another module can be given first, like `assert_tokens!(math, "1+2", [...])`.
+
+# Panics
+When the input doesn't lex to the tokens, with the snapshot of what it lexed to if there are errors.
+
+# Example
+```should_panic
+# use plexer::{assert_tokens, lexer};
+#
+lexer!(
+    mod math;
+
+    NUMBER(u8) {
+        regex!(r"[0-9]+") => |v| v.parse().map(Token::NUMBER),
+    },
+);
+
+assert_tokens!(math, "1+2", [NUMBER(1), NUMBER(2)]);
+```
+*/
+#[macro_export]
+macro_rules! assert_tokens {
+    ($module:ident, $input:expr, [$($token:expr),* $(,)?] $(,)?) => {{
+        #[allow(unused_imports)]
+        use $module::Token::*;
+        let input = $input;
+        let expected: $crate::__private::Vec<$module::Token> = $crate::__private::vec![$($token),*];
+        match $module::Token::tokenize_all(input) {
+            Ok(tokens) => assert_eq!(tokens, expected, "tokens of {:?}", input),
+            Err(_) => panic!(
+                "{:?} doesn't lex without errors:\n{}",
+                input,
+                $module::Token::tokenize(input).to_snapshot()
+            ),
+        }
+    }};
+    ($input:expr, [$($token:expr),* $(,)?] $(,)?) => {
+        $crate::assert_tokens!(lexer, $input, [$($token),*])
+    };
+}
+
+pub use crate::assert_tokens;
+
+/**
+Format tokens and errors as a snapshot, with a line `start..end Token` for each token and `start..end error: message` for each error.
+
+The tokens are written with their `Debug` implementation, which is stable for the derived one.
+
+# Example
+```
+# use plexer::span::{Span, Spanned};
+# use plexer::testing;
+#
+let results = [Ok(Spanned::new('a', Span::new(0, 1))), Err((Span::new(1, 2), "unexpected character '?'"))];
+
+assert_eq!(testing::snapshot(results), "0..1 'a'\n1..2 error: unexpected character '?'\n");
+```
+*/
+pub fn snapshot<T: core::fmt::Debug, E: core::fmt::Display>(
+    results: impl IntoIterator<Item = Result<Spanned<T>, (Span, E)>>,
+) -> String {
+    let mut out = String::new();
+    for result in results {
+        let line = match result {
+            Ok(Spanned { value, span }) => format!("{} {:?}\n", span, value),
+            Err((span, message)) => format!("{} error: {}\n", span, message),
+        };
+        out.push_str(&line);
+    }
+    out
+}
@@ -0,0 +1,113 @@
+/*!
+Module for exporting the rules of a lexer as a TextMate grammar, like the ones of VS Code. \
+
+[`grammar`] writes the patterns described by `Token::patterns` as a `tmLanguage.json`,
+with a `match` for each regex or set of literals, and a scope chosen by the caller for each rule. \
+It is an approximation: TextMate takes the earliest match instead of the longest one, then the first pattern in order,
+so literals ending with a word character only match a whole word,
+and the rules only tried in another mode than `INITIAL` or whose patterns are closures are left out.
+
+# Example
+```
+# use plexer::lexer;
+# use plexer::textmate;
+#
+lexer!(
+    KEYWORD keywords ["if", "else"],
+    IDENTIFIER(&'a str) {
+        regex!(r"[a-z]+") => |v| Token::IDENTIFIER(v.as_str()),
+    },
+    OPERATOR {
+        ['+', '*'] => |_| Token::OPERATOR,
+    },
+    #[skip]
+    WHITESPACE {
+        ' ' => |_| Token::WHITESPACE,
+    },
+);
+use lexer::{Token, TokenKind};
+
+let json = textmate::grammar("calc", "source.calc", &Token::patterns(), |kind| match kind {
+    TokenKind::KEYWORD => Some("keyword.control.calc"),
+    TokenKind::IDENTIFIER => Some("variable.other.calc"),
+    TokenKind::OPERATOR => Some("keyword.operator.calc"),
+    TokenKind::WHITESPACE => None,
+});
+assert_eq!(json.lines().nth(2), Some(r#"  "scopeName": "source.calc","#));
+assert!(json.contains(r#"{ "name": "keyword.control.calc", "match": "if\\b|else\\b" }"#));
+assert!(json.contains(r#"{ "name": "keyword.operator.calc", "match": "\\+|\\*" }"#));
+```
+*/
+
+use crate::dump::quote;
+use crate::summary::RulePattern;
+use alloc::format;
+use alloc::string::String;
+use alloc::vec::Vec;
+
+/**
+Write a TextMate grammar in JSON, with a `match` for each pattern whose regex is known, in the `INITIAL` mode,
+and whose rule is given a scope by `scope`.
+
+# Example
+```
+# use plexer::summary::RulePattern;
+# use plexer::textmate;
+#
+let patterns = [
+    RulePattern { kind: "comment", regex: Some(String::from(r#"//[^\n]*"#)), modes: vec!["INITIAL"], ..Default::default() },
+    RulePattern { kind: "string", regex: Some(String::from(r#""[^"]*""#)), modes: vec!["INITIAL"], ..Default::default() },
+];
+let json = textmate::grammar("mini", "source.mini", &patterns, |kind| Some(if kind == "comment" { "comment.line" } else { "string.quoted" }));
+
+assert_eq!(
+    json,
+    r#"{
+  "name": "mini",
+  "scopeName": "source.mini",
+  "patterns": [
+    { "name": "comment.line", "match": "//[^\\n]*" },
+    { "name": "string.quoted", "match": "\"[^\"]*\"" }
+  ]
+}
+"#
+);
+```
+*/
+pub fn grammar<K: Copy>(
+    name: &str,
+    scope_name: &str,
+    patterns: &[RulePattern<K>],
+    mut scope: impl FnMut(K) -> Option<&'static str>,
+) -> String {
+    let entries: Vec<String> = patterns
+        .iter()
+        .filter(|pattern| pattern.modes.contains(&"INITIAL"))
+        .filter_map(|pattern| {
+            let regex = pattern.regex.clone().or_else(|| pattern.literals.as_deref().map(alternation))?;
+            let scope = scope(pattern.kind)?;
+            Some(format!("    {{ \"name\": {}, \"match\": {} }}", quote(scope), quote(&regex)))
+        })
+        .collect();
+    format!(
+        "{{\n  \"name\": {},\n  \"scopeName\": {},\n  \"patterns\": [\n{}\n  ]\n}}\n",
+        quote(name),
+        quote(scope_name),
+        entries.join(",\n")
+    )
+}
+
+// Literals ending with a word character are followed by `\b`, since TextMate doesn't look for the longest match
+fn alternation(literals: &[String]) -> String {
+    let alternatives: Vec<String> = literals
+        .iter()
+        .map(|literal| {
+            let mut alternative = regex_syntax::escape(literal);
+            if literal.ends_with(|c: char| c.is_alphanumeric() || c == '_') {
+                alternative.push_str(r"\b");
+            }
+            alternative
+        })
+        .collect();
+    alternatives.join("|")
+}
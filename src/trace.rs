@@ -0,0 +1,166 @@
+/*!
+Module for tracing the rules tried by a lexer. \
+
+`Lexer::with_trace` makes the `Lexer` record an [`Attempt`] for each pattern it tries, in order,
+with whether it matched, its length and why it won or lost against the others. \
+`Lexer::take_trace` returns the attempts recorded so far, which helps to find out why an input gave the wrong token.
+
+# Example
+```
+# use plexer::lexer;
+# use plexer::trace::Verdict;
+#
+lexer!(
+    KEYWORD {
+        "if" => |_| Token::KEYWORD,
+    },
+    IDENTIFIER(&'a str) {
+        regex!(r"[a-z]+") => |v| Token::IDENTIFIER(v.as_str()),
+    },
+    NUMBER(u8) {
+        regex!(r"[0-9]+") => |v| v.parse().map(Token::NUMBER),
+    },
+);
+use lexer::{Token, TokenKind};
+
+let mut lex = Token::tokenize("iffy").with_trace();
+assert_eq!(lex.next(), Some(Ok(Token::IDENTIFIER("iffy"))));
+
+let trace = lex.take_trace();
+assert_eq!(trace[0].kind, TokenKind::KEYWORD);
+assert_eq!(trace[0].verdict, Verdict::Beaten);
+assert_eq!(trace[1].verdict, Verdict::Won);
+assert_eq!(trace[2].verdict, Verdict::NoMatch);
+assert_eq!(trace[1].to_string(), "0: IDENTIFIER[0] matched 4 bytes, won");
+
+let mut lex = Token::tokenize("300").with_trace();
+assert!(lex.next().is_some_and(|result| result.is_err()));
+assert_eq!(
+    lex.take_trace()[2].verdict,
+    Verdict::Rejected(Some(String::from("number too large to fit in target type")))
+);
+```
+
+# Formatting
+Each [`Attempt`] is displayed on a line, with the cursor, the rule and index of the pattern, the length of its match and its [`Verdict`].
+```
+# use plexer::lexer;
+#
+lexer!(
+    INTEGER(&'a str) {
+        regex!(r"[0-9]+") => |v| Token::INTEGER(v.as_str()),
+    },
+    FLOAT(&'a str) {
+        regex!(r"[0-9]+\.[0-9]+") => |v| Token::FLOAT(v.as_str()),
+    },
+);
+use lexer::Token;
+
+let mut lex = Token::tokenize("1.5").with_trace();
+lex.next();
+
+let lines: Vec<_> = lex.take_trace().iter().map(ToString::to_string).collect();
+assert_eq!(lines, vec![
+    "0: INTEGER[0] matched 1 bytes, beaten by a longer match",
+    "0: FLOAT[0] matched 3 bytes, won",
+]);
+```
+
+# Shadowed rules
+`Token::shadowed_rules` finds the patterns that can never win, like keywords declared after identifiers,
+to be checked in a test. \
+A pattern is shadowed if it only matches known strings, which is the case of literals and of regexes like `if|else`,
+and each of them is won by an earlier rule when lexed on its own, in every mode of the rule and without a previous token.
+```
+# use plexer::lexer;
+# use plexer::trace::Shadowed;
+#
+lexer!(
+    IDENTIFIER(&'a str) {
+        regex!(r"[a-z]+") => |v| Token::IDENTIFIER(v.as_str()),
+    },
+    KEYWORD(&'a str) keywords ["if", "else"],
+    OPERATOR {
+        '+' => |_| Token::OPERATOR,
+        "++" => |_| Token::OPERATOR,
+    },
+);
+use lexer::{Token, TokenKind};
+
+let shadowed = Token::shadowed_rules();
+assert_eq!(shadowed, vec![Shadowed { kind: TokenKind::KEYWORD, arm: 0, by: TokenKind::IDENTIFIER }]);
+assert_eq!(shadowed[0].to_string(), "KEYWORD[0] is shadowed by IDENTIFIER");
+```
+*/
+
+use alloc::string::String;
+
+/// A pattern tried by the `Lexer` while tracing.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Attempt<K> {
+    /// Position of the cursor when the pattern was tried
+    pub cursor: usize,
+    /// Kind of the rule of the pattern
+    pub kind: K,
+    /// Index of the pattern in its rule
+    pub arm: usize,
+    /// Length of the match, `None` if the pattern didn't match
+    pub len: Option<usize>,
+    /// What became of the match
+    pub verdict: Verdict,
+}
+
+impl<K: core::fmt::Debug> core::fmt::Display for Attempt<K> {
+    fn fmt(&self, f: &mut core::fmt::Formatter) -> core::fmt::Result {
+        write!(f, "{}: {:?}[{}] ", self.cursor, self.kind, self.arm)?;
+        match self.len {
+            Some(len) => write!(f, "matched {} bytes, {}", len, self.verdict),
+            None => write!(f, "{}", self.verdict),
+        }
+    }
+}
+
+/// What became of a pattern tried by the `Lexer`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Verdict {
+    /// The pattern didn't match
+    NoMatch,
+    /// The match wasn't longer than the best one so far, so it wasn't built
+    Shorter,
+    /// The builder rejected the match, with its reason if there is one
+    Rejected(Option<String>),
+    /// The match was built, but a longer one was found afterwards
+    Beaten,
+    /// The match was the best one
+    Won,
+}
+
+impl core::fmt::Display for Verdict {
+    fn fmt(&self, f: &mut core::fmt::Formatter) -> core::fmt::Result {
+        match self {
+            Verdict::NoMatch => f.write_str("no match"),
+            Verdict::Shorter => f.write_str("not longer than the best match"),
+            Verdict::Rejected(Some(reason)) => write!(f, "rejected: {}", reason),
+            Verdict::Rejected(None) => f.write_str("rejected"),
+            Verdict::Beaten => f.write_str("beaten by a longer match"),
+            Verdict::Won => f.write_str("won"),
+        }
+    }
+}
+
+/// A pattern that can never win, because an earlier rule always matches at least as much, see `Token::shadowed_rules`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Shadowed<K> {
+    /// Kind of the rule of the pattern
+    pub kind: K,
+    /// Index of the pattern in its rule
+    pub arm: usize,
+    /// Kind of the earlier rule that wins instead
+    pub by: K,
+}
+
+impl<K: core::fmt::Debug> core::fmt::Display for Shadowed<K> {
+    fn fmt(&self, f: &mut core::fmt::Formatter) -> core::fmt::Result {
+        write!(f, "{:?}[{}] is shadowed by {:?}", self.kind, self.arm, self.by)
+    }
+}
@@ -0,0 +1,126 @@
+/*!
+Module for using lexers from JavaScript with `wasm-bindgen`, enabled by the `wasm-bindgen` feature. \
+
+`Lexer::to_js` turns the tokens and errors into a JavaScript array of `{kind, start, end}` objects, see [`array`](fn@array),
+and [`wasm_tokenize!`](crate::wasm_tokenize) exports a `tokenize(input)` function doing it,
+so a web playground can reuse the lexer of a crate built for `wasm32-unknown-unknown`.
+
+# Example
+```no_run
+# use plexer::lexer;
+#
+lexer!(
+    NUMBER(u8) {
+        regex!(r"[0-9]+") => |v| v.parse().map(Token::NUMBER),
+    },
+    #[skip]
+    WHITESPACE {
+        ' ' => |_| Token::WHITESPACE,
+    },
+);
+
+// `import { tokenize } from "./pkg/calc.js"` in JavaScript
+plexer::wasm_tokenize!();
+```
+*/
+
+use crate::dump::Entry;
+use js_sys::{Array, Object, Reflect};
+use wasm_bindgen::JsValue;
+
+/**
+Exports a `tokenize(input: string)` function to JavaScript with `wasm-bindgen`, returning the array of `Lexer::to_js`.
+
+The lexer is the `lexer` module by default, another module can be given first,
+and a name for the function second, to export several lexers like `wasm_tokenize!(math, tokenizeMath)`.
+
+# Example
+```no_run
+# use plexer::lexer;
+#
+lexer!(
+    mod math;
+
+    NUMBER(u8) {
+        regex!(r"[0-9]+") => |v| v.parse().map(Token::NUMBER),
+    },
+);
+
+plexer::wasm_tokenize!(math, tokenizeMath);
+```
+*/
+#[macro_export]
+macro_rules! wasm_tokenize {
+    () => {
+        $crate::wasm_tokenize!(lexer);
+    };
+    ($module:ident $(,)?) => {
+        $crate::wasm_tokenize!($module, tokenize);
+    };
+    ($module:ident, $name:ident $(,)?) => {
+        #[$crate::__private::wasm_bindgen::prelude::wasm_bindgen(wasm_bindgen = $crate::__private::wasm_bindgen)]
+        #[allow(non_snake_case)]
+        pub fn $name(input: &str) -> $crate::__private::js_sys::Array {
+            $module::Token::tokenize(input.as_ref()).to_js()
+        }
+    };
+}
+
+pub use crate::wasm_tokenize;
+
+/**
+Convert the entries of a haystack to a JavaScript array of objects, with the name of the kind of the token and the offsets of its span,
+like `{kind: "NUMBER", start: 0, end: 1}`. \
+Offsets are in UTF-16 code units, like the indices of a JavaScript string, so `input.slice(start, end)` gives the lexeme. \
+Errors have a `null` kind and their message as `error`.
+
+# Panics
+When it isn't run on a `wasm32` target, like every function calling into JavaScript.
+*/
+pub fn array(haystack: &[u8], entries: impl IntoIterator<Item = Entry>) -> Array {
+    let array = Array::new();
+    let mut offsets = Utf16 { haystack, byte: 0, unit: 0 };
+    for entry in entries {
+        let object = Object::new();
+        let kind = entry.kind.map_or(JsValue::NULL, JsValue::from_str);
+        set(&object, "kind", &kind);
+        set(&object, "start", &JsValue::from(offsets.offset(entry.span.start)));
+        set(&object, "end", &JsValue::from(offsets.offset(entry.span.end)));
+        if let Some(error) = entry.error {
+            set(&object, "error", &JsValue::from_str(&error));
+        }
+        array.push(&object);
+    }
+    array
+}
+
+// UTF-16 offsets of byte offsets, counted on from the previous one since entries come in order
+struct Utf16<'h> {
+    haystack: &'h [u8],
+    byte: usize,
+    unit: usize,
+}
+
+impl Utf16<'_> {
+    fn offset(&mut self, byte: usize) -> usize {
+        if byte < self.byte {
+            (self.byte, self.unit) = (0, 0);
+        }
+        // Like `semantic::Encoding::Utf16`, a char takes two units when it takes four bytes, and one otherwise
+        let units: usize = self.haystack[self.byte..byte]
+            .iter()
+            .map(|&byte| match byte {
+                0x80..=0xBF => 0,
+                0xF0..=0xFF => 2,
+                _ => 1,
+            })
+            .sum();
+        (self.byte, self.unit) = (byte, self.unit + units);
+        self.unit
+    }
+}
+
+// Setting a property of a plain object can't fail
+fn set(object: &Object, key: &str, value: &JsValue) {
+    let _ = Reflect::set(object, &JsValue::from_str(key), value);
+}